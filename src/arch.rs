@@ -0,0 +1,181 @@
+//! Normalisation of code for architectures other than x86, for keying functions by instructions.
+//!
+//! Unlike for x86, we don't fully decode instructions. We only need to know how long each one is
+//! and which ones are PC-relative, e.g. branches and literal loads. The offsets of these depend on
+//! where the function is, so we zero them and instead record the address that they refer to. Each
+//! architecture that we can do this for implements [`Arch`], which also splits code into
+//! instructions for counting basic blocks.
+
+use crate::arm::Arm;
+use crate::mips::Mips;
+use crate::powerpc::PowerPc;
+use crate::Function;
+use object::Architecture;
+use object::Object as _;
+use std::ops::Range;
+
+/// An instruction set that we can find the PC-relative instructions of.
+pub(crate) trait Arch: Sync {
+    /// Normalises `fn_bytes`, the code of `function`. Trailing NOPs are removed if `strip_padding`
+    /// is set.
+    fn normalise(&self, fn_bytes: &[u8], function: &Function, strip_padding: bool) -> Normalised;
+
+    /// Splits `fn_bytes`, the code at `address`, into instructions, or returns `None` if we can't
+    /// tell where they are without knowing more about the function than its address. Trailing NOPs
+    /// are left out if `strip_padding` is set.
+    fn instructions(
+        &self,
+        fn_bytes: &[u8],
+        address: u64,
+        strip_padding: bool,
+    ) -> Option<Vec<Instruction>>;
+}
+
+/// Returns how to normalise the code in `object`, or `None` for x86, which is decoded with
+/// `iced_x86`, and for architectures that we don't support.
+pub(crate) fn for_object(object: &object::File) -> Option<Box<dyn Arch>> {
+    let little_endian = object.is_little_endian();
+    match object.architecture() {
+        Architecture::Arm => Some(Box::new(Arm)),
+        Architecture::Mips | Architecture::Mips64 => Some(Box::new(Mips { little_endian })),
+        Architecture::PowerPc | Architecture::PowerPc64 => {
+            Some(Box::new(PowerPc { little_endian }))
+        }
+        _ => None,
+    }
+}
+
+/// A function's code with the offsets of PC-relative instructions zeroed.
+pub(crate) struct Normalised {
+    pub(crate) bytes: Vec<u8>,
+
+    /// The offset within the function of each PC-relative instruction and the address that it
+    /// refers to.
+    pub(crate) references: Vec<(usize, u64)>,
+}
+
+/// An instruction, with as much as we need to know to split code into basic blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    /// The address after the instruction, or for architectures with delay slots, after its delay
+    /// slot. If the instruction ends a basic block, the next one starts here.
+    pub(crate) end: u64,
+
+    pub(crate) flow: Flow,
+}
+
+/// How an instruction affects which one runs after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Flow {
+    /// Carries on with the next instruction. Calls do this too, once the callee returns.
+    Next,
+
+    /// A direct branch to the address, conditional or not.
+    Branch(u64),
+
+    /// Ends the basic block without a target that we can follow, e.g. a return or indirect jump.
+    Stop,
+}
+
+impl Normalised {
+    /// Returns bytes that identify the function, including what each PC-relative instruction
+    /// refers to. References within `fn_range` are described relative to its start, so that they
+    /// match wherever the copy is. Other references are passed to `name_of` and if it returns a
+    /// name, that's returned alongside the offset instead of the address being included in the
+    /// bytes.
+    pub(crate) fn into_key(
+        self,
+        fn_range: Range<u64>,
+        mut name_of: impl FnMut(u64) -> Option<String>,
+    ) -> (Vec<u8>, Vec<(usize, String)>) {
+        let mut bytes = self.bytes;
+        let mut names = Vec::new();
+        for (offset, target) in self.references {
+            if fn_range.contains(&target) {
+                bytes.push(0);
+                bytes.extend_from_slice(&offset.to_le_bytes());
+                bytes.extend_from_slice(&(target - fn_range.start).to_le_bytes());
+            } else if let Some(name) = name_of(target) {
+                names.push((offset, name));
+            } else {
+                bytes.push(1);
+                bytes.extend_from_slice(&offset.to_le_bytes());
+                bytes.extend_from_slice(&target.to_le_bytes());
+            }
+        }
+        (bytes, names)
+    }
+}
+
+/// Normalises code made up of 32 bit instructions. `reference` is given each instruction and its
+/// address and, if it's PC-relative, returns its target and the bits that hold its offset. The
+/// bytes of the instructions are in the order given by `little_endian`.
+pub(crate) fn normalise_words(
+    fn_bytes: &[u8],
+    address: u64,
+    little_endian: bool,
+    reference: impl Fn(u32, u64) -> Option<(u64, u32)>,
+) -> Normalised {
+    let mut bytes = fn_bytes.to_vec();
+    let mut references = Vec::new();
+    for (index, chunk) in bytes.chunks_exact_mut(4).enumerate() {
+        let word = read_word(chunk, little_endian);
+        let offset = index * 4;
+        if let Some((target, mask)) = reference(word, address + offset as u64) {
+            write_word(chunk, word & !mask, little_endian);
+            references.push((offset, target));
+        }
+    }
+    Normalised { bytes, references }
+}
+
+/// Removes any of `nops`, which are 32 bit instructions in the order given by `little_endian`, from
+/// the end of `bytes`.
+pub(crate) fn strip_trailing_words(bytes: &mut Vec<u8>, nops: &[u32], little_endian: bool) {
+    let len = trim_trailing_words(bytes, nops, little_endian).len();
+    bytes.truncate(len);
+}
+
+/// Returns `bytes` without any of `nops` at the end, as for `strip_trailing_words`.
+pub(crate) fn trim_trailing_words<'a>(
+    mut bytes: &'a [u8],
+    nops: &[u32],
+    little_endian: bool,
+) -> &'a [u8] {
+    while bytes.len() >= 4 && nops.contains(&read_word(&bytes[bytes.len() - 4..], little_endian)) {
+        bytes = &bytes[..bytes.len() - 4];
+    }
+    bytes
+}
+
+pub(crate) fn read_word(bytes: &[u8], little_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
+
+pub(crate) fn write_word(bytes: &mut [u8], word: u32, little_endian: bool) {
+    let word = if little_endian {
+        word.to_le_bytes()
+    } else {
+        word.to_be_bytes()
+    };
+    bytes[..4].copy_from_slice(&word);
+}
+
+/// Sign extends the low `bits` bits of `value`.
+pub(crate) fn sign_extend(value: impl Into<u64>, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value.into() << shift) as i64) >> shift
+}
+
+pub(crate) fn add_or_sub(base: u64, offset: u64, add: bool) -> u64 {
+    if add {
+        base.wrapping_add(offset)
+    } else {
+        base.wrapping_sub(offset)
+    }
+}
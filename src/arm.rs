@@ -0,0 +1,225 @@
+//! Normalisation of 32 bit ARM code, both Thumb-2 and A32, for keying functions by instructions.
+//! The PC-relative instructions are branches, literal loads and `adr`.
+
+use crate::arch::add_or_sub;
+use crate::arch::normalise_words;
+use crate::arch::sign_extend;
+use crate::arch::strip_trailing_words;
+use crate::arch::Arch;
+use crate::arch::Instruction;
+use crate::arch::Normalised;
+use crate::Function;
+
+/// Thumb `nop`.
+const THUMB_NOP: u16 = 0xbf00;
+
+/// A32 `nop` and `mov r0, r0`, which older assemblers use as a NOP.
+const A32_NOPS: [u32; 2] = [0xe320_f000, 0xe1a0_0000];
+
+pub(crate) struct Arm;
+
+impl Arch for Arm {
+    fn normalise(&self, fn_bytes: &[u8], function: &Function, strip_padding: bool) -> Normalised {
+        normalise(fn_bytes, function.address, function.thumb, strip_padding)
+    }
+
+    fn instructions(
+        &self,
+        _fn_bytes: &[u8],
+        _address: u64,
+        _strip_padding: bool,
+    ) -> Option<Vec<Instruction>> {
+        // Whether the code is Thumb or A32 decides how long its instructions are, but that's only
+        // known from the symbol.
+        None
+    }
+}
+
+/// Normalises the function at `address`, which is Thumb code if `thumb` is set and A32 code
+/// otherwise. Trailing NOPs are removed if `strip_padding` is set.
+fn normalise(fn_bytes: &[u8], address: u64, thumb: bool, strip_padding: bool) -> Normalised {
+    let mut normalised = if thumb {
+        normalise_thumb(fn_bytes, address)
+    } else {
+        normalise_a32(fn_bytes, address)
+    };
+    if strip_padding {
+        strip_trailing_nops(&mut normalised.bytes, thumb);
+    }
+    normalised
+}
+
+fn normalise_thumb(fn_bytes: &[u8], address: u64) -> Normalised {
+    let mut bytes = fn_bytes.to_vec();
+    let mut references = Vec::new();
+    let mut offset = 0;
+    while offset + 2 <= bytes.len() {
+        let hw1 = u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        // Reads as the instruction executes are 4 bytes ahead of it.
+        let pc = address + offset as u64 + 4;
+        let is_32_bit = matches!(hw1 >> 11, 0b11101..=0b11111);
+        if is_32_bit {
+            if offset + 4 > bytes.len() {
+                break;
+            }
+            let hw2 = u16::from_le_bytes([bytes[offset + 2], bytes[offset + 3]]);
+            if let Some((target, mask1, mask2)) = thumb32_reference(hw1, hw2, pc) {
+                bytes[offset..offset + 2].copy_from_slice(&(hw1 & !mask1).to_le_bytes());
+                bytes[offset + 2..offset + 4].copy_from_slice(&(hw2 & !mask2).to_le_bytes());
+                references.push((offset, target));
+            }
+            offset += 4;
+        } else {
+            if let Some((target, mask)) = thumb16_reference(hw1, pc) {
+                bytes[offset..offset + 2].copy_from_slice(&(hw1 & !mask).to_le_bytes());
+                references.push((offset, target));
+            }
+            offset += 2;
+        }
+    }
+    Normalised { bytes, references }
+}
+
+/// If the 16 bit Thumb instruction `hw` is PC-relative, returns its target and the bits that hold
+/// its offset.
+fn thumb16_reference(hw: u16, pc: u64) -> Option<(u64, u16)> {
+    let aligned_pc = pc & !3;
+    if hw & 0xf000 == 0xd000 && (hw >> 8) & 0xf < 0xe {
+        // Conditional `b`. Conditions 14 and 15 are `udf` and `svc`.
+        let offset = sign_extend(u32::from(hw & 0xff) << 1, 9);
+        Some((pc.wrapping_add_signed(offset), 0x00ff))
+    } else if hw & 0xf800 == 0xe000 {
+        // Unconditional `b`.
+        let offset = sign_extend(u32::from(hw & 0x7ff) << 1, 12);
+        Some((pc.wrapping_add_signed(offset), 0x07ff))
+    } else if hw & 0xf500 == 0xb100 {
+        // `cbz` and `cbnz`.
+        let offset = (u64::from(hw >> 9) & 1) << 6 | (u64::from(hw >> 3) & 0x1f) << 1;
+        Some((pc + offset, 0x02f8))
+    } else if hw & 0xf800 == 0x4800 || hw & 0xf800 == 0xa000 {
+        // `ldr` from a literal and `adr`.
+        Some((aligned_pc + u64::from(hw & 0xff) * 4, 0x00ff))
+    } else {
+        None
+    }
+}
+
+/// If the 32 bit Thumb instruction made up of `hw1` and `hw2` is PC-relative, returns its target
+/// and the bits of each halfword that hold its offset.
+fn thumb32_reference(hw1: u16, hw2: u16, pc: u64) -> Option<(u64, u16, u16)> {
+    let aligned_pc = pc & !3;
+    let s = u32::from(hw1 >> 10) & 1;
+    let j1 = u32::from(hw2 >> 13) & 1;
+    let j2 = u32::from(hw2 >> 11) & 1;
+    if hw1 & 0xf800 == 0xf000 && hw2 & 0x8000 == 0x8000 {
+        // Branches. `b`, `bl` and `blx` all use the same encoding of their offset, except for the
+        // conditional `b`, which has a condition where the others have more offset bits.
+        let i1 = !(j1 ^ s) & 1;
+        let i2 = !(j2 ^ s) & 1;
+        let long_offset = s << 24
+            | i1 << 23
+            | i2 << 22
+            | u32::from(hw1 & 0x3ff) << 12
+            | u32::from(hw2 & 0x7ff) << 1;
+        match hw2 & 0xd000 {
+            0x8000 if (hw1 >> 7) & 0x7 != 0x7 => {
+                let offset = s << 20
+                    | j2 << 19
+                    | j1 << 18
+                    | u32::from(hw1 & 0x3f) << 12
+                    | u32::from(hw2 & 0x7ff) << 1;
+                let target = pc.wrapping_add_signed(sign_extend(offset, 21));
+                Some((target, 0x043f, 0x2fff))
+            }
+            0x9000 | 0xd000 => {
+                let target = pc.wrapping_add_signed(sign_extend(long_offset, 25));
+                Some((target, 0x07ff, 0x2fff))
+            }
+            // `blx` switches to A32, whose instructions are 4 byte aligned.
+            0xc000 if hw2 & 1 == 0 => {
+                let target = aligned_pc.wrapping_add_signed(sign_extend(long_offset, 25));
+                Some((target, 0x07ff, 0x2ffe))
+            }
+            _ => None,
+        }
+    } else if hw1 & 0xfe1f == 0xf81f {
+        // `ldr`, `ldrb`, `ldrh`, `ldrsb`, `ldrsh` and `pld` from a literal.
+        let offset = u64::from(hw2 & 0xfff);
+        Some((
+            add_or_sub(aligned_pc, offset, hw1 & 0x80 != 0),
+            0x0080,
+            0x0fff,
+        ))
+    } else if hw1 & 0xff7f == 0xe95f || hw1 & 0xff3f == 0xed1f {
+        // `ldrd` and `vldr` from a literal.
+        let offset = u64::from(hw2 & 0xff) * 4;
+        Some((
+            add_or_sub(aligned_pc, offset, hw1 & 0x80 != 0),
+            0x0080,
+            0x00ff,
+        ))
+    } else if (hw1 & 0xfbff == 0xf20f || hw1 & 0xfbff == 0xf2af) && hw2 & 0x8000 == 0 {
+        // `adr`, which is encoded as adding to or subtracting from the PC.
+        let offset = (u64::from(hw1 >> 10) & 1) << 11
+            | (u64::from(hw2 >> 12) & 0x7) << 8
+            | u64::from(hw2 & 0xff);
+        let target = add_or_sub(aligned_pc, offset, hw1 & 0xfbff == 0xf20f);
+        Some((target, 0x04a0, 0x70ff))
+    } else {
+        None
+    }
+}
+
+fn normalise_a32(fn_bytes: &[u8], address: u64) -> Normalised {
+    // Reads as the instruction executes are 8 bytes ahead of it.
+    normalise_words(fn_bytes, address, true, |word, address| {
+        a32_reference(word, address + 8)
+    })
+}
+
+/// If the A32 instruction `word` is PC-relative, returns its target and the bits that hold its
+/// offset.
+fn a32_reference(word: u32, pc: u64) -> Option<(u64, u32)> {
+    let condition = word >> 28;
+    if word & 0x0e00_0000 == 0x0a00_0000 {
+        if condition == 0xf {
+            // `blx` to Thumb code, which has an extra bit of offset to allow halfword alignment.
+            let offset = (word & 0xff_ffff) << 2 | (word >> 23) & 2;
+            Some((pc.wrapping_add_signed(sign_extend(offset, 26)), 0x01ff_ffff))
+        } else {
+            // `b` and `bl`.
+            let offset = (word & 0xff_ffff) << 2;
+            Some((pc.wrapping_add_signed(sign_extend(offset, 26)), 0x00ff_ffff))
+        }
+    } else if condition != 0xf && word & 0x0e1f_0000 == 0x041f_0000 {
+        // `ldr` and `ldrb` from a literal.
+        let offset = u64::from(word & 0xfff);
+        let target = add_or_sub(pc, offset, word & 0x0080_0000 != 0);
+        Some((target, 0x0080_0fff))
+    } else if condition != 0xf && word & 0x0f3f_0e00 == 0x0d1f_0a00 {
+        // `vldr` from a literal.
+        let offset = u64::from(word & 0xff) * 4;
+        let target = add_or_sub(pc, offset, word & 0x0080_0000 != 0);
+        Some((target, 0x0080_00ff))
+    } else if condition != 0xf
+        && (word & 0x0fff_0000 == 0x028f_0000 || word & 0x0fff_0000 == 0x024f_0000)
+    {
+        // `adr`, which is encoded as adding to or subtracting from the PC.
+        let rotation = (word >> 8) & 0xf;
+        let offset = u64::from((word & 0xff).rotate_right(rotation * 2));
+        let target = add_or_sub(pc, offset, word & 0x0080_0000 != 0);
+        Some((target, 0x00c0_0fff))
+    } else {
+        None
+    }
+}
+
+fn strip_trailing_nops(bytes: &mut Vec<u8>, thumb: bool) {
+    if thumb {
+        while bytes.len() >= 2 && bytes[bytes.len() - 2..] == THUMB_NOP.to_le_bytes() {
+            bytes.truncate(bytes.len() - 2);
+        }
+    } else {
+        strip_trailing_words(bytes, &A32_NOPS, true);
+    }
+}
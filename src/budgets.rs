@@ -0,0 +1,105 @@
+//! Per-crate allowances for excess bytes, so that the owners of each crate can be held to their own
+//! limit rather than everyone sharing one for the whole binary. A budgets file is TOML that maps
+//! crate names to the maximum excess bytes attributed to each matching crate:
+//!
+//! ```toml
+//! my_service = 8192
+//! "serde*" = 4096
+//! "*" = 1024
+//! ```
+//!
+//! Names may contain `*`, which matches any sequence of characters. Functions whose crate isn't
+//! known are attributed to `<unknown>`. Crates that don't match any name aren't limited.
+
+use crate::pretty_size;
+use anyhow::bail;
+use anyhow::Context;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+use regex::Regex;
+use std::fmt::Write as _;
+use std::path::Path;
+
+pub(crate) struct Budgets {
+    /// Sorted so that the first that matches a crate is the most specific.
+    budgets: Vec<Budget>,
+}
+
+struct Budget {
+    pattern: String,
+    regex: Regex,
+    max_excess_bytes: u64,
+}
+
+impl Budgets {
+    pub(crate) fn load(path: &Path) -> Result<Budgets> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read budgets file `{}`", path.display()))?;
+        let table = text
+            .parse::<toml::Table>()
+            .with_context(|| format!("Failed to parse budgets file `{}`", path.display()))?;
+        let mut budgets = table
+            .iter()
+            .map(|(pattern, value)| {
+                let max_excess_bytes = value
+                    .as_integer()
+                    .and_then(|bytes| u64::try_from(bytes).ok())
+                    .with_context(|| {
+                        format!(
+                            "Budget for `{pattern}` in `{}` should be a non-negative integer",
+                            path.display()
+                        )
+                    })?;
+                let regex = Regex::new(&format!(
+                    "^{}$",
+                    regex::escape(pattern).replace(r"\*", ".*")
+                ))?;
+                Ok(Budget {
+                    pattern: pattern.clone(),
+                    regex,
+                    max_excess_bytes,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Exact names take precedence over patterns, then longer patterns over shorter ones, so
+        // that e.g. `serde_json*` applies rather than `serde*`.
+        budgets.sort_by_key(|budget| {
+            (
+                budget.pattern.contains('*'),
+                std::cmp::Reverse(budget.pattern.len()),
+            )
+        });
+        Ok(Budgets { budgets })
+    }
+
+    /// Returns an error listing each crate that exceeds its budget.
+    pub(crate) fn check(&self, report: &DuplicationReport) -> Result {
+        let mut over = String::new();
+        for (crate_name, excess_bytes) in report.excess_bytes_by_crate() {
+            let crate_name = crate_name.as_deref().unwrap_or("<unknown>");
+            let Some(budget) = self.find(crate_name) else {
+                continue;
+            };
+            if excess_bytes > budget.max_excess_bytes {
+                writeln!(
+                    over,
+                    "  {crate_name}: {} excess, budget {} from `{}`, over by {}",
+                    pretty_size(excess_bytes),
+                    pretty_size(budget.max_excess_bytes),
+                    budget.pattern,
+                    pretty_size(excess_bytes - budget.max_excess_bytes)
+                )?;
+            }
+        }
+        if !over.is_empty() {
+            bail!("Some crates exceed their budgets for excess bytes:\n{over}");
+        }
+        Ok(())
+    }
+
+    fn find(&self, crate_name: &str) -> Option<&Budget> {
+        self.budgets
+            .iter()
+            .find(|budget| budget.regex.is_match(crate_name))
+    }
+}
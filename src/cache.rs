@@ -0,0 +1,124 @@
+//! A persistent cache of analysis results, so that analysing an unchanged binary again, e.g. in
+//! diff mode or when CI is re-run, doesn't have to key every function again.
+//!
+//! Results are keyed by a hash of the binary's contents together with everything that affects
+//! the result, so there's no need to invalidate entries. We don't key by build ID, since stripped
+//! and unstripped copies of a binary share one.
+
+use crate::DuplicationReport;
+use crate::Options;
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Incremented whenever the format of cache entries, or how functions are keyed, changes.
+const CACHE_VERSION: u64 = 15;
+
+/// Returns the directory that results are cached in by default, which is under
+/// `$XDG_CACHE_HOME`, or `~/.cache` if that isn't set.
+pub fn default_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(base.join("duplicate-function-checker"))
+}
+
+/// Returns the path of the cache entry for analysing `data` with `options`.
+pub(crate) fn entry_path(dir: &Path, data: &[u8], options: &Options) -> Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    data.hash(&mut hasher);
+    format!("{:?}", options.key).hash(&mut hasher);
+    options.demangle.hash(&mut hasher);
+    options.demangle_no_hash.hash(&mut hasher);
+    options
+        .filter
+        .as_ref()
+        .map(|r| r.as_str())
+        .hash(&mut hasher);
+    options
+        .exclude
+        .as_ref()
+        .map(|r| r.as_str())
+        .hash(&mut hasher);
+    options.ignore_std.hash(&mut hasher);
+    options.ignore_compiler_builtins.hash(&mut hasher);
+    format!("{:?}", options.sections).hash(&mut hasher);
+    options.address_range.hash(&mut hasher);
+    options.keep_padding.hash(&mut hasher);
+    options.keep_aliases.hash(&mut hasher);
+    options.include_plt.hash(&mut hasher);
+    options.infer_sizes.hash(&mut hasher);
+    format!("{:?}", options.symbols).hash(&mut hasher);
+    // Hash collisions are astronomically unlikely, but would change the result.
+    options.hash_keys.hash(&mut hasher);
+    options.verify_hashes.hash(&mut hasher);
+    // Functions can come from these instead of the binary, so their contents matter too.
+    for path in [&options.pdb, &options.debug_file, &options.symbols_file] {
+        let contents = path
+            .as_ref()
+            .map(|path| {
+                std::fs::read(path).with_context(|| format!("Failed to read `{}`", path.display()))
+            })
+            .transpose()?;
+        contents.hash(&mut hasher);
+    }
+    Ok(dir.join(format!("{:016x}.json", hasher.finish())))
+}
+
+/// Returns the cached report at `path`, if there is one. Entries that can't be read, e.g. because
+/// they were written by a different version, are treated as missing.
+pub(crate) fn load(path: &Path) -> Option<DuplicationReport> {
+    let text = std::fs::read_to_string(path).ok()?;
+    from_json(&text).ok()
+}
+
+/// Writes `report` to `path`, creating its directory if necessary.
+pub(crate) fn store(path: &Path, report: &DuplicationReport) -> Result {
+    let dir = path.parent().context("Cache entry has no directory")?;
+    std::fs::create_dir_all(dir)?;
+    // Write to a temporary name, so that concurrent or interrupted runs don't leave a truncated
+    // entry that we'd later read.
+    let partial = path.with_extension(format!("json.{}", std::process::id()));
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+    write_json(&mut out, report)?;
+    out.flush()?;
+    drop(out);
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// A cache entry, with the version first so that entries from other versions can be recognised.
+#[derive(Serialize, Deserialize)]
+struct Entry<R> {
+    version: u64,
+    report: R,
+}
+
+fn write_json(out: &mut impl std::io::Write, report: &DuplicationReport) -> Result {
+    let entry = Entry {
+        version: CACHE_VERSION,
+        report,
+    };
+    serde_json::to_writer(out, &entry)?;
+    Ok(())
+}
+
+fn from_json(text: &str) -> Result<DuplicationReport> {
+    let Entry { version, .. } = serde_json::from_str::<Entry<IgnoredAny>>(text)?;
+    if version != CACHE_VERSION {
+        bail!("Unsupported cache version {version}");
+    }
+    let entry = serde_json::from_str::<Entry<DuplicationReport>>(text)?;
+    Ok(entry.report)
+}
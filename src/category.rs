@@ -0,0 +1,83 @@
+//! Classification of functions by what produced them, based on their demangled names. This helps
+//! tell whether duplication would be fixed by the compiler or linker, e.g. via `-Zshare-generics`
+//! or ICF, or needs source changes.
+
+use crate::demangle;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum Category {
+    /// `core::ptr::drop_in_place`, which the compiler generates for each type that needs dropping.
+    DropGlue,
+
+    /// Shims that adapt a method for calling through a vtable, e.g. `FnOnce::call_once` on a boxed
+    /// closure.
+    VtableShim,
+
+    /// Other shims that the compiler generates for trait object methods, e.g. reify shims that
+    /// turn a method into a function pointer.
+    TraitObjectShim,
+
+    /// Implementations of `Clone::clone`, which are usually derived.
+    Clone,
+
+    /// Closure bodies.
+    Closure,
+
+    /// Everything else.
+    UserCode,
+}
+
+impl Category {
+    pub const ALL: [Category; 6] = [
+        Category::DropGlue,
+        Category::VtableShim,
+        Category::TraitObjectShim,
+        Category::Clone,
+        Category::Closure,
+        Category::UserCode,
+    ];
+
+    /// A short human-readable description.
+    pub fn description(self) -> &'static str {
+        match self {
+            Category::DropGlue => "drop glue",
+            Category::VtableShim => "vtable shims",
+            Category::TraitObjectShim => "trait object shims",
+            Category::Clone => "Clone::clone",
+            Category::Closure => "closures",
+            Category::UserCode => "user code",
+        }
+    }
+
+    /// A stable identifier for machine-readable output.
+    pub fn id(self) -> &'static str {
+        match self {
+            Category::DropGlue => "drop_glue",
+            Category::VtableShim => "vtable_shim",
+            Category::TraitObjectShim => "trait_object_shim",
+            Category::Clone => "clone",
+            Category::Closure => "closure",
+            Category::UserCode => "user_code",
+        }
+    }
+}
+
+/// Classifies a function by its name, which may be either mangled or already demangled. Legacy
+/// and v0 mangling spell shims differently, e.g. `{{vtable.shim}}` and `{shim:vtable#0}`.
+pub fn classify(symbol_name: &str) -> Category {
+    let name = demangle::demangle(symbol_name, true);
+    // A closure's vtable shim is a shim first and foremost, so shims are checked first.
+    if name.contains("{{vtable.shim}}") || name.contains("{shim:vtable") {
+        Category::VtableShim
+    } else if name.contains("{{reify.shim}}") || name.contains("{shim:reify") {
+        Category::TraitObjectShim
+    } else if name.contains("core::ptr::drop_in_place") {
+        Category::DropGlue
+    } else if name.ends_with("as core::clone::Clone>::clone") {
+        Category::Clone
+    } else if name.contains("{{closure}}") || name.contains("{closure#") {
+        Category::Closure
+    } else {
+        Category::UserCode
+    }
+}
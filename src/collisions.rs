@@ -0,0 +1,92 @@
+//! Detection of functions that share a name, ignoring rustc's hash, but not their contents. This is
+//! the inverse of finding duplicates and shows distinct monomorphisations of what looks like the
+//! same function, or unrelated static functions that happen to have the same name.
+
+use crate::KeyType;
+use crate::Options;
+use crate::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A name shared by functions whose contents differ.
+#[derive(Serialize)]
+pub struct Collision {
+    /// The demangled name, without rustc's hash.
+    pub name: String,
+
+    /// Each distinct function with this name, largest first.
+    pub variants: Vec<Variant>,
+}
+
+/// A set of identical functions with a colliding name.
+#[derive(Serialize)]
+pub struct Variant {
+    pub function_size: u64,
+    pub copies: u64,
+}
+
+impl Collision {
+    /// The total size of all copies of all variants.
+    pub fn total_bytes(&self) -> u64 {
+        self.variants
+            .iter()
+            .map(|variant| variant.function_size * variant.copies)
+            .sum()
+    }
+}
+
+/// Finds names shared by functions that `options.key` considers to be different. Keying by name
+/// isn't useful here, so in that case functions are keyed by instructions instead. Collisions are
+/// sorted by total size, smallest first.
+pub fn find_collisions(data: &[u8], options: &Options) -> Result<Vec<Collision>> {
+    let key = match options.key {
+        KeyType::NameAndSize | KeyType::NameWithoutRustHash => KeyType::Instructions,
+        key => key,
+    };
+    let report = crate::analyze_data(
+        data,
+        &Options {
+            key,
+            demangle: false,
+            demangle_no_hash: true,
+            ..options.clone()
+        },
+    )?;
+
+    let mut variants_by_name: HashMap<&str, Vec<Variant>> = HashMap::new();
+    for group in &report.groups {
+        for name in group.names.keys() {
+            variants_by_name
+                .entry(name.as_str())
+                .or_default()
+                .push(Variant {
+                    function_size: group.function_size,
+                    copies: group.copies,
+                });
+        }
+    }
+    let mut collisions = variants_by_name
+        .into_iter()
+        .filter(|(_, variants)| variants.len() > 1)
+        .map(|(name, mut variants)| {
+            variants.sort_by_key(|variant| {
+                (
+                    std::cmp::Reverse(variant.function_size),
+                    std::cmp::Reverse(variant.copies),
+                )
+            });
+            Collision {
+                name: name.to_owned(),
+                variants,
+            }
+        })
+        .collect::<Vec<_>>();
+    collisions.sort_by(|a, b| {
+        (a.total_bytes(), a.variants.len(), &a.name).cmp(&(
+            b.total_bytes(),
+            b.variants.len(),
+            &b.name,
+        ))
+    });
+    Ok(collisions)
+}
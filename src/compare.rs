@@ -0,0 +1,235 @@
+//! Comparison of a single function across two binaries, e.g. to check that a change to generics or
+//! to ICF settings merged the code that was expected.
+//!
+//! Both copies are disassembled with branch targets and RIP-relative addresses replaced by the
+//! names of the symbols that they refer to, since the addresses themselves will usually differ
+//! between binaries even when the code is the same.
+
+use crate::bitness;
+use crate::decode_asm;
+use crate::display_name;
+use crate::functions;
+use crate::get_fn_bytes;
+use crate::require_x86;
+use crate::strip_trailing_padding;
+use crate::Options;
+use crate::Result;
+use anyhow::bail;
+use iced_x86::Formatter as _;
+use object::Object as _;
+use object::ObjectSymbol as _;
+
+/// The normalised disassembly of a function from each binary.
+pub struct Comparison {
+    pub old: Listing,
+    pub new: Listing,
+}
+
+pub struct Listing {
+    pub function_size: u64,
+
+    /// The number of distinct functions with the requested name. Only the one with the lowest
+    /// address is listed.
+    pub matches: usize,
+
+    /// One instruction per line, without addresses.
+    pub lines: Vec<String>,
+}
+
+/// A row of a side-by-side diff of two listings.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Row<'a> {
+    Same(&'a str),
+    Changed(&'a str, &'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl Comparison {
+    pub fn is_identical(&self) -> bool {
+        self.old.lines == self.new.lines
+    }
+
+    /// Returns the rows of a side-by-side diff from the old listing to the new one. Runs of removed
+    /// lines that are followed by added lines are paired up as changes.
+    pub fn rows(&self) -> Vec<Row<'_>> {
+        let old = &self.old.lines;
+        let new = &self.new.lines;
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_middle = &old[prefix..old.len() - suffix];
+        let new_middle = &new[prefix..new.len() - suffix];
+
+        let mut rows = old[..prefix]
+            .iter()
+            .map(|line| Row::Same(line))
+            .collect::<Vec<_>>();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for (old_line, new_line) in longest_common_subsequence(old_middle, new_middle) {
+            match (old_line, new_line) {
+                (Some(old_line), Some(_)) => {
+                    flush_changes(&mut rows, &mut removed, &mut added);
+                    rows.push(Row::Same(old_line));
+                }
+                (Some(old_line), None) => removed.push(old_line.as_str()),
+                (None, Some(new_line)) => added.push(new_line.as_str()),
+                (None, None) => {}
+            }
+        }
+        flush_changes(&mut rows, &mut removed, &mut added);
+        rows.extend(old[old.len() - suffix..].iter().map(|line| Row::Same(line)));
+        rows
+    }
+}
+
+/// Finds the function called `name` in the binary `data` and disassembles it.
+pub fn listing(data: &[u8], name: &str, options: &Options) -> Result<Listing> {
+    let object = object::File::parse(data)?;
+    require_x86(&object, "Comparing functions")?;
+    let mut functions = functions(&object, options)?;
+    functions.retain(|function| {
+        function
+            .name
+            .as_deref()
+            .is_some_and(|n| n == name || display_name(n, options) == name)
+    });
+    // Aliases share a body, so they're a single match.
+    functions.sort_by_key(|function| (function.address, function.section_index.map(|i| i.0)));
+    functions.dedup_by_key(|function| (function.address, function.section_index.map(|i| i.0)));
+    let Some(function) = functions.first() else {
+        bail!("No function is called `{name}`");
+    };
+    let Some(fn_bytes) = get_fn_bytes(function, &object) else {
+        bail!("Failed to read the code of `{name}`");
+    };
+
+    let mut instructions = decode_asm(fn_bytes, function.address, bitness(&object));
+    if !options.keep_padding {
+        strip_trailing_padding(&mut instructions);
+    }
+    let symbols = SymbolNames::new(&object, options);
+    let mut formatter = iced_x86::IntelFormatter::with_options(Some(Box::new(symbols)), None);
+    let lines = instructions
+        .iter()
+        .map(|instruction| {
+            let mut text = String::new();
+            formatter.format(instruction, &mut text);
+            text
+        })
+        .collect();
+    Ok(Listing {
+        function_size: function.size,
+        matches: functions.len(),
+        lines,
+    })
+}
+
+/// Names the addresses that instructions refer to after the symbol at or before them.
+struct SymbolNames {
+    /// Sorted by address.
+    symbols: Vec<(u64, String)>,
+}
+
+impl SymbolNames {
+    fn new(object: &object::File, options: &Options) -> Self {
+        let mut symbols = object
+            .symbols()
+            .filter(|symbol| symbol.is_definition())
+            .filter_map(|symbol| {
+                Some((symbol.address(), display_name(symbol.name().ok()?, options)))
+            })
+            .collect::<Vec<_>>();
+        symbols.sort();
+        Self { symbols }
+    }
+}
+
+impl iced_x86::SymbolResolver for SymbolNames {
+    fn symbol(
+        &mut self,
+        instruction: &iced_x86::Instruction,
+        _operand: u32,
+        instruction_operand: Option<u32>,
+        address: u64,
+        _address_size: u32,
+    ) -> Option<iced_x86::SymbolResult<'_>> {
+        // Immediates that happen to look like addresses are left alone.
+        let refers_to_address = instruction_operand.is_some_and(|operand| {
+            matches!(
+                instruction.op_kind(operand),
+                iced_x86::OpKind::NearBranch16
+                    | iced_x86::OpKind::NearBranch32
+                    | iced_x86::OpKind::NearBranch64
+                    | iced_x86::OpKind::Memory
+            )
+        });
+        if !refers_to_address {
+            return None;
+        }
+        let index = self
+            .symbols
+            .partition_point(|(symbol_address, _)| *symbol_address <= address)
+            .checked_sub(1)?;
+        let (symbol_address, name) = &self.symbols[index];
+        Some(iced_x86::SymbolResult::with_str(*symbol_address, name))
+    }
+}
+
+/// Pairs each line of `old` and `new` with the same line of the other, if it's part of their
+/// longest common subsequence, or with `None`.
+fn longest_common_subsequence<'a>(
+    old: &'a [String],
+    new: &'a [String],
+) -> Vec<(Option<&'a String>, Option<&'a String>)> {
+    // `lengths[i][j]` is the length of the longest common subsequence of `old[i..]` and `new[j..]`.
+    let mut lengths = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            pairs.push((Some(&old[i]), Some(&new[j])));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            pairs.push((Some(&old[i]), None));
+            i += 1;
+        } else {
+            pairs.push((None, Some(&new[j])));
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn flush_changes<'a>(
+    rows: &mut Vec<Row<'a>>,
+    removed: &mut Vec<&'a str>,
+    added: &mut Vec<&'a str>,
+) {
+    let paired = removed.len().min(added.len());
+    rows.extend(
+        removed
+            .iter()
+            .zip(added.iter())
+            .map(|(old, new)| Row::Changed(old, new)),
+    );
+    rows.extend(removed[paired..].iter().map(|line| Row::Removed(line)));
+    rows.extend(added[paired..].iter().map(|line| Row::Added(line)));
+    removed.clear();
+    added.clear();
+}
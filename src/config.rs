@@ -0,0 +1,174 @@
+//! Default settings read from a TOML file, so that a shared configuration can be checked into a
+//! repository rather than repeated as flags in CI scripts. For example:
+//!
+//! ```toml
+//! key = "name-without-rust-hash"
+//! sort = "copies"
+//! exclude = "^core::fmt"
+//! min-size = 32
+//! max-excess-percent = 5.0
+//! ```
+//!
+//! Settings are named after the corresponding flags. Flags given on the command line take
+//! precedence over the file.
+
+use crate::Args;
+use anyhow::bail;
+use anyhow::Context;
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use clap::ValueEnum;
+use duplicate_function_checker::Result;
+use regex::Regex;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The file that's used if `--config` isn't given and it exists in the current directory.
+const DEFAULT_PATH: &str = "duplicate-function-checker.toml";
+
+/// The settings that may appear in a config file, each with the ID of the argument that clap
+/// uses for the corresponding flag, which is needed to tell whether the flag was given.
+const SETTINGS: &[(&str, &str)] = &[
+    ("key", "key"),
+    ("key-cmd", "key_cmd"),
+    ("denominator", "denominator"),
+    ("sort", "sort"),
+    ("format", "format"),
+    ("bytes", "bytes"),
+    ("size-precision", "size_precision"),
+    ("filter", "filter"),
+    ("exclude", "exclude"),
+    ("ignore-std", "ignore_std"),
+    ("ignore-compiler-builtins", "ignore_compiler_builtins"),
+    ("sections", "sections"),
+    ("range", "range"),
+    ("symbols", "symbols"),
+    ("top", "top"),
+    ("min-size", "min_size"),
+    ("min-copies", "min_copies"),
+    ("min-applies-to-totals", "min_applies_to_totals"),
+    ("where", "query"),
+    ("max-excess-percent", "max_excess_percent"),
+    ("max-excess-bytes", "max_excess_bytes"),
+    ("budgets", "budgets"),
+    ("ignore-file", "ignore_file"),
+    ("demangle", "demangle"),
+    ("demangle-no-hash", "demangle_no_hash"),
+    ("verbose", "verbose"),
+    ("stats", "stats"),
+    ("strict", "strict"),
+    ("no-cache", "no_cache"),
+    ("hash-keys", "hash_keys"),
+    ("verify-hashes", "verify_hashes"),
+];
+
+/// Applies the settings from the config file to `args`, except for those that were given on the
+/// command line.
+pub(crate) fn apply(args: &mut Args, matches: &ArgMatches) -> Result {
+    let path = match &args.config {
+        Some(path) => path.clone(),
+        None if Path::new(DEFAULT_PATH).exists() => PathBuf::from(DEFAULT_PATH),
+        None => return Ok(()),
+    };
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file `{}`", path.display()))?;
+    let table = text
+        .parse::<toml::Table>()
+        .with_context(|| format!("Failed to parse config file `{}`", path.display()))?;
+    for (name, value) in &table {
+        let Some(&(_, id)) = SETTINGS.iter().find(|(setting, _)| setting == name) else {
+            let names = SETTINGS
+                .iter()
+                .map(|(setting, _)| *setting)
+                .collect::<Vec<_>>();
+            bail!(
+                "Unknown setting `{name}` in `{}`. Supported settings are: {}",
+                path.display(),
+                names.join(", ")
+            );
+        };
+        if matches.value_source(id) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        set(args, name, value)
+            .with_context(|| format!("Invalid `{name}` in `{}`", path.display()))?;
+    }
+    Ok(())
+}
+
+fn set(args: &mut Args, name: &str, value: &toml::Value) -> Result {
+    match name {
+        "key" => args.key = value_enum(value)?,
+        "key-cmd" => args.key_cmd = Some(PathBuf::from(string(value)?)),
+        "denominator" => args.denominator = value_enum(value)?,
+        "sort" => args.sort = value_enum(value)?,
+        "format" => args.format = value_enum(value)?,
+        "bytes" => args.bytes = boolean(value)?,
+        "size-precision" => args.size_precision = usize::try_from(integer(value)?)?,
+        "filter" => args.filter = Some(Regex::new(string(value)?)?),
+        "exclude" => args.exclude = Some(Regex::new(string(value)?)?),
+        "ignore-std" => args.ignore_std = boolean(value)?,
+        "ignore-compiler-builtins" => args.ignore_compiler_builtins = boolean(value)?,
+        "sections" => args.sections = Some(string(value)?.parse()?),
+        "range" => args.range = Some(crate::parse_range(string(value)?)?),
+        "symbols" => args.symbols = value_enum(value)?,
+        "top" => args.top = Some(usize::try_from(integer(value)?)?),
+        "min-size" => args.min_size = Some(integer(value)?),
+        "min-copies" => args.min_copies = Some(integer(value)?),
+        "min-applies-to-totals" => args.min_applies_to_totals = boolean(value)?,
+        "where" => args.query = Some(string(value)?.parse()?),
+        "max-excess-percent" => args.max_excess_percent = Some(float(value)?),
+        "max-excess-bytes" => args.max_excess_bytes = Some(integer(value)?),
+        "budgets" => args.budgets = Some(PathBuf::from(string(value)?)),
+        "ignore-file" => args.ignore_file = Some(PathBuf::from(string(value)?)),
+        "demangle" => args.demangle = boolean(value)?,
+        "demangle-no-hash" => args.demangle_no_hash = boolean(value)?,
+        "verbose" => args.verbose = boolean(value)?,
+        "stats" => args.stats = boolean(value)?,
+        "strict" => args.strict = boolean(value)?,
+        "no-cache" => args.no_cache = boolean(value)?,
+        "hash-keys" => args.hash_keys = boolean(value)?,
+        "verify-hashes" => args.verify_hashes = boolean(value)?,
+        _ => unreachable!("`{name}` is missing from SETTINGS"),
+    }
+    Ok(())
+}
+
+fn string(value: &toml::Value) -> Result<&str> {
+    value
+        .as_str()
+        .with_context(|| format!("Expected a string, got {}", value.type_str()))
+}
+
+fn integer(value: &toml::Value) -> Result<u64> {
+    let integer = value
+        .as_integer()
+        .with_context(|| format!("Expected an integer, got {}", value.type_str()))?;
+    u64::try_from(integer).context("Expected a non-negative integer")
+}
+
+/// Accepts integers too, since `5` is a reasonable way to write a percentage.
+fn float(value: &toml::Value) -> Result<f64> {
+    value
+        .as_float()
+        .or_else(|| value.as_integer().map(|i| i as f64))
+        .with_context(|| format!("Expected a number, got {}", value.type_str()))
+}
+
+fn boolean(value: &toml::Value) -> Result<bool> {
+    value
+        .as_bool()
+        .with_context(|| format!("Expected true or false, got {}", value.type_str()))
+}
+
+fn value_enum<T: ValueEnum>(value: &toml::Value) -> Result<T> {
+    let text = string(value)?;
+    T::from_str(text, false).map_err(|_| {
+        let possible = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_owned())
+            .collect::<Vec<_>>();
+        anyhow::anyhow!("`{text}` isn't one of: {}", possible.join(", "))
+    })
+}
@@ -0,0 +1,69 @@
+//! Finding functions that are present in more than one binary, e.g. a helper that's statically
+//! linked into many services.
+
+use crate::DuplicationReport;
+use std::collections::HashMap;
+
+/// A function that appears in several binaries.
+pub struct SharedFunction {
+    /// The representative name of the function's group in each binary.
+    pub name: String,
+
+    pub function_size: u64,
+
+    /// Indexes into the reports passed to `shared_functions` of the binaries that contain the
+    /// function, in ascending order.
+    pub binaries: Vec<usize>,
+
+    /// The total number of copies across all binaries.
+    pub copies: u64,
+}
+
+impl SharedFunction {
+    /// The bytes that would be saved if all binaries shared a single copy, e.g. via a dynamic
+    /// library. Duplicates within each binary are already counted in that binary's report.
+    pub fn excess_bytes(&self) -> u64 {
+        self.function_size * (self.binaries.len() as u64).saturating_sub(1)
+    }
+}
+
+/// Returns the functions that appear in more than one of `reports`, with the worst last.
+///
+/// Functions are matched by their group's representative name and size rather than by key, since
+/// instruction keys depend on the addresses of whatever the function references, which differ
+/// between binaries. As with `diff::diff`, names should usually have been produced with
+/// `demangle_no_hash` if the binaries come from different builds.
+pub fn shared_functions(reports: &[DuplicationReport]) -> Vec<SharedFunction> {
+    let mut by_name: HashMap<(&str, u64), SharedFunction> = HashMap::new();
+    for (index, report) in reports.iter().enumerate() {
+        for group in &report.groups {
+            let Some(name) = group.representative_name() else {
+                continue;
+            };
+            let shared = by_name
+                .entry((name, group.function_size))
+                .or_insert_with(|| SharedFunction {
+                    name: name.to_owned(),
+                    function_size: group.function_size,
+                    binaries: Vec::new(),
+                    copies: 0,
+                });
+            if shared.binaries.last() != Some(&index) {
+                shared.binaries.push(index);
+            }
+            shared.copies += group.copies;
+        }
+    }
+    let mut shared = by_name
+        .into_values()
+        .filter(|f| f.binaries.len() > 1)
+        .collect::<Vec<_>>();
+    shared.sort_by(|a, b| {
+        (a.excess_bytes(), a.binaries.len(), &a.name).cmp(&(
+            b.excess_bytes(),
+            b.binaries.len(),
+            &b.name,
+        ))
+    });
+    shared
+}
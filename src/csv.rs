@@ -0,0 +1,43 @@
+//! Output as comma-separated values, with a row for each duplicate group, for loading into a
+//! spreadsheet or a script that doesn't want to parse JSON.
+
+use crate::sorted_duplicates;
+use crate::split_top;
+use crate::Args;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+
+pub(crate) fn write_groups(
+    out: &mut impl std::io::Write,
+    report: &DuplicationReport,
+    args: &Args,
+) -> Result {
+    let mut symbols = sorted_duplicates(report, args);
+    split_top(&mut symbols, args.top);
+    writeln!(out, "name,function_size,copies,excess_bytes,names,id")?;
+    // As for HTML, the worst groups come first, since that's where people start reading.
+    for group in symbols.iter().rev() {
+        write_field(out, group.representative_name().unwrap_or_default())?;
+        writeln!(
+            out,
+            ",{},{},{},{},{}",
+            group.function_size,
+            group.copies,
+            group.excess_bytes(),
+            group.names.len(),
+            group.id()
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes `value`, quoted if it contains anything that would otherwise end the field. Quotes are
+/// escaped by doubling them, as RFC 4180 specifies.
+fn write_field(out: &mut impl std::io::Write, value: &str) -> Result {
+    if value.contains([',', '"', '\n', '\r']) {
+        write!(out, "\"{}\"", value.replace('"', "\"\""))?;
+    } else {
+        write!(out, "{value}")?;
+    }
+    Ok(())
+}
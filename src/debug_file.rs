@@ -0,0 +1,168 @@
+//! Reading of functions from separate debug info files, for binaries that have been stripped.
+//!
+//! Distributions and many production builds strip binaries and ship their symbols separately.
+//! We look for the symbols in the same places that gdb does, and optionally ask a debuginfod
+//! server.
+
+use crate::linkage::Linkage;
+use crate::symbols_with_files;
+use crate::text_section_ranges;
+use crate::Function;
+use crate::Result;
+use anyhow::Context;
+use object::BinaryFormat;
+use object::Object as _;
+use object::ObjectSection as _;
+use object::ObjectSymbol as _;
+use object::SymbolKind;
+use std::borrow::Cow;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Where distributions install separate debug info files.
+const DEBUG_DIR: &str = "/usr/lib/debug";
+
+/// Returns the functions with non-zero sizes in the symbol table of the debug info file at `path`,
+/// which must have been split from `object`.
+pub(crate) fn functions<'data>(
+    path: &Path,
+    object: &object::File<'data, &'data [u8]>,
+) -> Result<Vec<Function<'data>>> {
+    let data = crate::read_input(path)
+        .with_context(|| format!("Failed to read debug file `{}`", path.display()))?;
+    let debug_object = object::File::parse(&*data)
+        .with_context(|| format!("Failed to parse debug file `{}`", path.display()))?;
+    Ok(symbol_table_functions(&debug_object, object))
+}
+
+/// Returns the functions in the MiniDebugInfo of `object`, if it has any. This is an xz-compressed
+/// ELF file in the `.gnu_debugdata` section that holds the symbols of functions that aren't
+/// exported. Fedora and RHEL ship binaries like this, so that they have useful backtraces despite
+/// being stripped.
+pub(crate) fn mini_debug_info_functions<'data>(
+    object: &object::File<'data, &'data [u8]>,
+) -> Result<Option<Vec<Function<'data>>>> {
+    let Some(section) = object.section_by_name(".gnu_debugdata") else {
+        return Ok(None);
+    };
+    let mut data = Vec::new();
+    lzma_rs::xz_decompress(&mut section.data()?, &mut data)
+        .context("Failed to decompress `.gnu_debugdata`")?;
+    let debug_object = object::File::parse(data.as_slice())
+        .context("Failed to parse the ELF file in `.gnu_debugdata`")?;
+    Ok(Some(symbol_table_functions(&debug_object, object)))
+}
+
+/// Returns the functions with non-zero sizes in the symbol table of `debug_object`, which describes
+/// `object`.
+fn symbol_table_functions<'data>(
+    debug_object: &object::File,
+    object: &object::File<'data, &'data [u8]>,
+) -> Vec<Function<'data>> {
+    // The debug file has the same section layout as the binary, but its code sections have no
+    // data, so we take the sections from the binary.
+    let text_sections = text_section_ranges(object);
+    symbols_with_files(debug_object)
+        .filter(|(sym, _)| sym.kind() == SymbolKind::Text && sym.size() != 0)
+        .map(|(sym, file)| {
+            let address = sym.address();
+            Function {
+                name: sym.name().ok().map(|name| Cow::Owned(name.to_owned())),
+                address,
+                size: sym.size(),
+                section_index: text_sections
+                    .iter()
+                    .find(|(_, range)| range.contains(&address))
+                    .map(|(index, _)| *index),
+                size_inferred: false,
+                thumb: false,
+                linkage: Linkage::of_symbol(&sym),
+                file: file.map(|file| Cow::Owned(file.to_owned())),
+            }
+        })
+        .collect()
+}
+
+/// Looks for the separate debug info of `data`, the contents of `path`, if it's a stripped ELF
+/// binary. The places searched are, in order:
+///
+/// * `/usr/lib/debug/.build-id/ab/cdef.debug`, based on the build ID.
+/// * The file named by `.gnu_debuglink`, next to the binary, in `.debug` next to the binary, or in
+///   the same directory under `/usr/lib/debug`.
+/// * `path` with `.debug` appended.
+/// * The debuginfod server at `debuginfod_url`, if set. Downloaded files are cached in the
+///   temporary directory.
+pub fn find(path: &Path, data: &[u8], debuginfod_url: Option<&str>) -> Result<Option<PathBuf>> {
+    let Ok(object) = object::File::parse(data) else {
+        return Ok(None);
+    };
+    if object.format() != BinaryFormat::Elf || object.symbol_table().is_some() {
+        return Ok(None);
+    }
+    let build_id = object.build_id()?.map(hex);
+    let mut candidates = Vec::new();
+    if let Some(build_id) = &build_id {
+        if build_id.len() > 2 {
+            let (dir, file) = build_id.split_at(2);
+            candidates.push(Path::new(DEBUG_DIR).join(format!(".build-id/{dir}/{file}.debug")));
+        }
+    }
+    if let Some((name, _crc)) = object.gnu_debuglink()? {
+        let name = String::from_utf8_lossy(name);
+        let dir = path.parent().unwrap_or(Path::new("."));
+        candidates.push(dir.join(&*name));
+        candidates.push(dir.join(".debug").join(&*name));
+        if let Ok(dir) = dir.canonicalize() {
+            let relative = dir.strip_prefix("/").unwrap_or(&dir);
+            candidates.push(Path::new(DEBUG_DIR).join(relative).join(&*name));
+        }
+    }
+    let mut with_suffix = path.as_os_str().to_owned();
+    with_suffix.push(".debug");
+    candidates.push(PathBuf::from(with_suffix));
+
+    // The debuglink can name the binary itself, which doesn't help.
+    if let Some(found) = candidates
+        .into_iter()
+        .find(|candidate| candidate.is_file() && !is_same_file(candidate, path))
+    {
+        return Ok(Some(found));
+    }
+    match (debuginfod_url, &build_id) {
+        (Some(url), Some(build_id)) => download(url, build_id).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Fetches the debug info for `build_id` from a debuginfod server, unless we already have it.
+fn download(url: &str, build_id: &str) -> Result<PathBuf> {
+    let cache_dir = std::env::temp_dir().join("duplicate-function-checker");
+    let path = cache_dir.join(format!("{build_id}.debug"));
+    if path.is_file() {
+        return Ok(path);
+    }
+    let url = format!("{}/buildid/{build_id}/debuginfo", url.trim_end_matches('/'));
+    let mut response = ureq::get(&url)
+        .call()
+        .with_context(|| format!("Failed to download debug info from `{url}`"))?
+        .into_reader();
+    std::fs::create_dir_all(&cache_dir)?;
+    // Download to a temporary name, so that an interrupted download doesn't leave a truncated file
+    // that we'd later use.
+    let partial = cache_dir.join(format!("{build_id}.debug.{}", std::process::id()));
+    let mut file = std::fs::File::create(&partial)?;
+    std::io::copy(&mut response, &mut file)
+        .with_context(|| format!("Failed to download debug info from `{url}`"))?;
+    file.flush()?;
+    std::fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
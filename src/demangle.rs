@@ -0,0 +1,42 @@
+//! Demangling of symbol names. The mangling scheme is detected from the name, so binaries that mix
+//! Rust and C++ get readable names for both.
+
+use std::borrow::Cow;
+
+/// Returns the demangled form of `name`, or `None` if it isn't mangled using a scheme that we
+/// understand. If `without_hash` is set, rustc's hash is dropped from Rust symbols.
+pub(crate) fn try_demangle(name: &str, without_hash: bool) -> Option<String> {
+    // Rust's legacy mangling is also valid Itanium C++ mangling, so Rust needs to be tried first,
+    // otherwise we'd get names like `core::fmt::write::h0123456789abcdef()`.
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        return Some(if without_hash {
+            format!("{demangled:#}")
+        } else {
+            demangled.to_string()
+        });
+    }
+    demangle_cpp(name).or_else(|| demangle_msvc(name))
+}
+
+/// Like `try_demangle`, but returns `name` unchanged if it isn't mangled.
+pub(crate) fn demangle(name: &str, without_hash: bool) -> Cow<'_, str> {
+    try_demangle(name, without_hash).map_or(Cow::Borrowed(name), Cow::Owned)
+}
+
+/// Demangles Itanium C++ ABI names, which are used by everything other than MSVC. Mach-O adds an
+/// extra leading underscore.
+fn demangle_cpp(name: &str) -> Option<String> {
+    if !(name.starts_with("_Z") || name.starts_with("__Z")) {
+        return None;
+    }
+    let symbol = cpp_demangle::Symbol::new(name).ok()?;
+    Some(symbol.to_string())
+}
+
+/// Demangles names produced by MSVC, which look like `?foo@@YAHH@Z`.
+fn demangle_msvc(name: &str) -> Option<String> {
+    if !name.starts_with('?') {
+        return None;
+    }
+    msvc_demangler::demangle(name, msvc_demangler::DemangleFlags::llvm()).ok()
+}
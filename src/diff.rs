@@ -0,0 +1,335 @@
+//! Comparison of the duplicate groups found in two binaries, or in a binary and a previously saved
+//! baseline.
+
+use crate::DuplicationReport;
+use crate::Result;
+use anyhow::bail;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Describes how duplication changed between two binaries.
+pub struct ReportDiff {
+    /// Groups that have duplicates in the new binary, but didn't in the old one.
+    pub appeared: Vec<GroupChange>,
+
+    /// Groups that had duplicates in the old binary, but don't in the new one.
+    pub disappeared: Vec<GroupChange>,
+
+    /// Groups that have duplicates in both binaries, but with a different number of copies.
+    pub changed: Vec<GroupChange>,
+
+    pub old_excess_bytes: u64,
+    pub new_excess_bytes: u64,
+}
+
+/// A group of functions, identified by its ID and representative name, together with its copy count
+/// and excess bytes in each binary. Counts are zero when the group had no duplicates.
+#[derive(Serialize)]
+pub struct GroupChange {
+    /// The group's ID in the new binary, or if it's not there, the old one. Baselines written
+    /// before IDs were recorded don't have them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// The representative name of the group in the new binary, or if it's not there, the old one.
+    pub name: String,
+    pub old_copies: u64,
+    pub new_copies: u64,
+    pub old_excess_bytes: u64,
+    pub new_excess_bytes: u64,
+}
+
+/// The duplicate groups of a binary, reduced to what's needed to compare against another binary.
+/// This can be saved as a baseline and compared against later runs.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub groups: Vec<SnapshotGroup>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotGroup {
+    /// The group's ID. This is `None` for baselines written before IDs were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// The representative name of the group.
+    pub name: String,
+    pub copies: u64,
+    pub excess_bytes: u64,
+}
+
+const SNAPSHOT_VERSION: u64 = 2;
+
+/// The first version that records group IDs. Earlier baselines can still be read, but their groups
+/// are only matched by name.
+const FIRST_VERSION_WITH_IDS: u64 = 2;
+
+impl Snapshot {
+    /// Records the groups with duplicates in `report`.
+    pub fn from_report(report: &DuplicationReport) -> Snapshot {
+        let mut groups = report
+            .duplicates()
+            .filter_map(|group| {
+                Some(SnapshotGroup {
+                    id: Some(group.id()),
+                    name: group.representative_name()?.to_owned(),
+                    copies: group.copies,
+                    excess_bytes: group.excess_bytes(),
+                })
+            })
+            .collect::<Vec<_>>();
+        groups.sort_by(|a, b| (&a.name, &a.id).cmp(&(&b.name, &b.id)));
+        Snapshot { groups }
+    }
+
+    pub fn excess_bytes(&self) -> u64 {
+        self.groups.iter().map(|g| g.excess_bytes).sum()
+    }
+
+    pub fn write_json(&self, out: &mut impl std::io::Write) -> Result {
+        let baseline = Baseline {
+            version: SNAPSHOT_VERSION,
+            snapshot: self,
+        };
+        // Pretty-printed, since baselines are often checked in and diffed.
+        serde_json::to_writer_pretty(&mut *out, &baseline)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    pub fn from_json(text: &str) -> Result<Snapshot> {
+        let Version { version } = serde_json::from_str(text)?;
+        if !(1..=SNAPSHOT_VERSION).contains(&version) {
+            bail!("Unsupported baseline version {version}");
+        }
+        let mut snapshot = serde_json::from_str::<Baseline<Snapshot>>(text)?.snapshot;
+        if version < FIRST_VERSION_WITH_IDS {
+            for group in &mut snapshot.groups {
+                group.id = None;
+            }
+        } else if snapshot.groups.iter().any(|group| group.id.is_none()) {
+            bail!("Baseline group without an `id`");
+        }
+        Ok(snapshot)
+    }
+}
+
+/// A saved snapshot, which has the version of the format alongside the snapshot's fields.
+#[derive(Serialize, Deserialize)]
+struct Baseline<S> {
+    version: u64,
+    #[serde(flatten)]
+    snapshot: S,
+}
+
+/// Just the version of a saved snapshot, which is read first to know how to read the rest.
+#[derive(Deserialize)]
+struct Version {
+    version: u64,
+}
+
+impl ReportDiff {
+    pub fn excess_bytes_delta(&self) -> i64 {
+        self.new_excess_bytes as i64 - self.old_excess_bytes as i64
+    }
+
+    /// Returns groups that are new or have more copies than before.
+    pub fn regressions(&self) -> impl Iterator<Item = &GroupChange> {
+        self.appeared
+            .iter()
+            .chain(self.changed.iter().filter(|c| c.new_copies > c.old_copies))
+    }
+}
+
+/// Adds `group` from the new snapshot to `change`, whose ID and name become the group's current
+/// ones.
+fn add_new(change: &mut GroupChange, group: &SnapshotGroup) {
+    if group.id.is_some() {
+        change.id.clone_from(&group.id);
+    }
+    change.name.clone_from(&group.name);
+    change.new_copies += group.copies;
+    change.new_excess_bytes += group.excess_bytes;
+}
+
+impl GroupChange {
+    pub fn excess_bytes_delta(&self) -> i64 {
+        self.new_excess_bytes as i64 - self.old_excess_bytes as i64
+    }
+}
+
+/// Matches groups in `old` and `new` by their ID, falling back to their representative name for
+/// groups whose ID changed or isn't known. Since rustc's symbol hashes generally differ between
+/// builds, names should usually have been produced with `demangle_no_hash` for the fallback to be
+/// useful.
+pub fn diff(old: &DuplicationReport, new: &DuplicationReport) -> ReportDiff {
+    diff_snapshots(&Snapshot::from_report(old), &Snapshot::from_report(new))
+}
+
+pub fn diff_snapshots(old: &Snapshot, new: &Snapshot) -> ReportDiff {
+    let mut changes = old
+        .groups
+        .iter()
+        .map(|group| GroupChange {
+            id: group.id.clone(),
+            name: group.name.clone(),
+            old_copies: group.copies,
+            new_copies: 0,
+            old_excess_bytes: group.excess_bytes,
+            new_excess_bytes: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut by_id: HashMap<&str, usize> = HashMap::new();
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, group) in old.groups.iter().enumerate() {
+        if let Some(id) = &group.id {
+            by_id.insert(id, index);
+        }
+        by_name.entry(&group.name).or_default().push(index);
+    }
+    let mut matched = vec![false; old.groups.len()];
+    // Match by ID first, so that a name can't claim a group that another group's ID refers to.
+    let mut unmatched = Vec::new();
+    for group in &new.groups {
+        let index = group
+            .id
+            .as_deref()
+            .and_then(|id| by_id.get(id))
+            .filter(|index| !matched[**index]);
+        match index {
+            Some(&index) => {
+                matched[index] = true;
+                add_new(&mut changes[index], group);
+            }
+            None => unmatched.push(group),
+        }
+    }
+    for group in unmatched {
+        let index = by_name
+            .get(group.name.as_str())
+            .and_then(|indexes| indexes.iter().copied().find(|index| !matched[*index]));
+        match index {
+            Some(index) => {
+                // Baselines without IDs combined groups with the same name, so those can match
+                // several groups.
+                matched[index] = old.groups[index].id.is_some();
+                add_new(&mut changes[index], group);
+            }
+            None => changes.push(GroupChange {
+                id: group.id.clone(),
+                name: group.name.clone(),
+                old_copies: 0,
+                new_copies: group.copies,
+                old_excess_bytes: 0,
+                new_excess_bytes: group.excess_bytes,
+            }),
+        }
+    }
+
+    let mut diff = ReportDiff {
+        appeared: Vec::new(),
+        disappeared: Vec::new(),
+        changed: Vec::new(),
+        old_excess_bytes: old.excess_bytes(),
+        new_excess_bytes: new.excess_bytes(),
+    };
+    for change in changes {
+        if change.old_copies == 0 {
+            diff.appeared.push(change);
+        } else if change.new_copies == 0 {
+            diff.disappeared.push(change);
+        } else if change.old_copies != change.new_copies {
+            diff.changed.push(change);
+        }
+    }
+    for changes in [&mut diff.appeared, &mut diff.disappeared, &mut diff.changed] {
+        changes.sort_by(|a, b| {
+            let key = |c: &GroupChange| (c.excess_bytes_delta().abs(), c.name.clone());
+            key(a).cmp(&key(b))
+        });
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(id: Option<&str>, name: &str, copies: u64) -> SnapshotGroup {
+        SnapshotGroup {
+            id: id.map(str::to_owned),
+            name: name.to_owned(),
+            copies,
+            excess_bytes: (copies - 1) * 10,
+        }
+    }
+
+    fn summary(changes: &[GroupChange]) -> Vec<(Option<&str>, &str, u64, u64)> {
+        changes
+            .iter()
+            .map(|c| (c.id.as_deref(), c.name.as_str(), c.old_copies, c.new_copies))
+            .collect()
+    }
+
+    #[test]
+    fn groups_are_matched_by_id_before_name() {
+        let old = Snapshot {
+            groups: vec![group(Some("aaa"), "foo", 2)],
+        };
+        // The group was renamed, and another group took its old name.
+        let new = Snapshot {
+            groups: vec![group(Some("bbb"), "foo", 2), group(Some("aaa"), "bar", 3)],
+        };
+        let diff = diff_snapshots(&old, &new);
+        assert_eq!(summary(&diff.changed), [(Some("aaa"), "bar", 2, 3)]);
+        assert_eq!(summary(&diff.appeared), [(Some("bbb"), "foo", 0, 2)]);
+        assert!(diff.disappeared.is_empty());
+    }
+
+    #[test]
+    fn groups_are_matched_by_name_when_their_id_changed() {
+        let old = Snapshot {
+            groups: vec![group(Some("aaa"), "foo", 2), group(Some("ccc"), "baz", 2)],
+        };
+        let new = Snapshot {
+            groups: vec![group(Some("bbb"), "foo", 3)],
+        };
+        let diff = diff_snapshots(&old, &new);
+        assert_eq!(summary(&diff.changed), [(Some("bbb"), "foo", 2, 3)]);
+        assert_eq!(summary(&diff.disappeared), [(Some("ccc"), "baz", 2, 0)]);
+        assert!(diff.appeared.is_empty());
+    }
+
+    #[test]
+    fn baselines_without_ids_are_matched_by_name() {
+        let old = Snapshot::from_json(
+            r#"{"version":1,"groups":[{"name":"foo","copies":4,"excess_bytes":30}]}"#,
+        )
+        .unwrap();
+        assert_eq!(old.groups[0].id, None);
+        // Version 1 combined groups with the same name, so both new groups match it.
+        let new = Snapshot {
+            groups: vec![group(Some("aaa"), "foo", 2), group(Some("bbb"), "foo", 3)],
+        };
+        let diff = diff_snapshots(&old, &new);
+        assert!(diff.appeared.is_empty());
+        assert!(diff.disappeared.is_empty());
+        assert_eq!(summary(&diff.changed), [(Some("bbb"), "foo", 4, 5)]);
+    }
+
+    #[test]
+    fn baselines_are_read_back() {
+        let snapshot = Snapshot {
+            groups: vec![group(Some("aaa"), "foo\u{1f980}", 3)],
+        };
+        let mut json = Vec::new();
+        snapshot.write_json(&mut json).unwrap();
+        let read = Snapshot::from_json(std::str::from_utf8(&json).unwrap()).unwrap();
+        assert_eq!(read.groups.len(), 1);
+        assert_eq!(read.groups[0].id.as_deref(), Some("aaa"));
+        assert_eq!(read.groups[0].name, "foo\u{1f980}");
+        assert_eq!(read.groups[0].copies, 3);
+        assert_eq!(read.groups[0].excess_bytes, 20);
+    }
+}
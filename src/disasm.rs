@@ -0,0 +1,79 @@
+//! Disassembly of functions, so that it's possible to see what a group of duplicates actually does.
+
+use crate::bitness;
+use crate::is_executable;
+use crate::require_x86;
+use crate::DuplicateGroup;
+use crate::FunctionInstance;
+use crate::Result;
+use iced_x86::Formatter as _;
+use object::Object as _;
+use object::ObjectSection as _;
+
+pub struct Disassembler<'data> {
+    object: object::File<'data, &'data [u8]>,
+    bitness: u32,
+}
+
+impl<'data> Disassembler<'data> {
+    /// Returns `None` if `data` isn't a single object, e.g. because it's an archive.
+    pub fn from_data(data: &'data [u8]) -> Result<Option<Disassembler<'data>>> {
+        let Ok(object) = object::File::parse(data) else {
+            return Ok(None);
+        };
+        require_x86(&object, "Disassembly")?;
+        let bitness = bitness(&object);
+        Ok(Some(Disassembler { object, bitness }))
+    }
+
+    /// Returns the disassembly of the `size` bytes of code at `instance`, one instruction per line.
+    pub fn disassemble(&self, instance: &FunctionInstance, size: u64) -> Option<Vec<String>> {
+        let address = instance.address;
+        let bytes = function_bytes(&self.object, instance, size)?;
+
+        let options = iced_x86::DecoderOptions::NONE;
+        let decoder = iced_x86::Decoder::with_ip(self.bitness, bytes, address, options);
+        let mut formatter = iced_x86::IntelFormatter::new();
+        Some(
+            decoder
+                .into_iter()
+                .map(|instruction| {
+                    let mut text = String::new();
+                    formatter.format(&instruction, &mut text);
+                    format!("{:8x}  {text}", instruction.ip())
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the disassembly of the first copy in `group` that we can find. Since the copies are
+    /// identical, it doesn't matter which one we pick.
+    pub fn disassemble_group(&self, group: &DuplicateGroup) -> Option<Vec<String>> {
+        group
+            .instances
+            .iter()
+            .find_map(|instance| self.disassemble(instance, group.function_size))
+    }
+}
+
+/// Returns the `size` bytes of code at `instance` in `object`.
+pub(crate) fn function_bytes<'data>(
+    object: &object::File<'data, &'data [u8]>,
+    instance: &FunctionInstance,
+    size: u64,
+) -> Option<&'data [u8]> {
+    let address = instance.address;
+    // In relocatable objects, addresses are relative to the section, so the section name is needed
+    // to tell which function is meant. In linked binaries, the address is enough.
+    let section = object.sections().find(|section| {
+        is_executable(section)
+            && instance
+                .section
+                .as_ref()
+                .is_none_or(|name| section.name().is_ok_and(|n| n == name))
+            && (section.address()..section.address() + section.size()).contains(&address)
+    })?;
+    let data = section.data().ok()?;
+    let offset = (address - section.address()) as usize;
+    data.get(offset..offset.checked_add(size as usize)?)
+}
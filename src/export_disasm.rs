@@ -0,0 +1,81 @@
+//! Export of each of the largest duplicate groups to its own JSON file, with the disassembly of one
+//! copy, so that what's duplicated can be reviewed or attached to an issue without re-running the
+//! analysis.
+
+use crate::group_fields;
+use crate::sorted_duplicates;
+use crate::split_top;
+use crate::Args;
+use crate::GroupDetails;
+use anyhow::bail;
+use anyhow::Context as _;
+use duplicate_function_checker::disasm::Disassembler;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+use serde_json::json;
+use serde_json::Map;
+use std::io::Write as _;
+use std::path::Path;
+
+/// The longest that the name part of a file name can be. Mangled names can be far longer than file
+/// systems allow.
+const MAX_NAME_LEN: usize = 80;
+
+/// Writes a file to `dir` for each duplicate group that makes the `--top` cut, named after its rank
+/// and its name, e.g. `001-core..fmt..write.json` for the group with the most excess bytes.
+pub(crate) fn export_groups(
+    dir: &Path,
+    data: &[u8],
+    report: &DuplicationReport,
+    args: &Args,
+    details: GroupDetails,
+) -> Result {
+    let Some(disassembler) = Disassembler::from_data(data)? else {
+        bail!("`--export-disasm` needs a single object, not an archive");
+    };
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create `{}`", dir.display()))?;
+    let mut symbols = sorted_duplicates(report, args);
+    split_top(&mut symbols, args.top);
+    for (i, group) in symbols.iter().rev().enumerate() {
+        let rank = i + 1;
+        let name = group.representative_name().unwrap_or("unknown");
+        let path = dir.join(format!("{rank:03}-{}.json", file_name_part(name)));
+        let mut file = std::io::BufWriter::new(
+            std::fs::File::create(&path)
+                .with_context(|| format!("Failed to create `{}`", path.display()))?,
+        );
+        let mut json = Map::new();
+        json.insert("rank".to_owned(), json!(rank));
+        json.extend(group_fields(group, details));
+        // The copies are identical, so the first that we can disassemble represents them all.
+        let representative = group.instances.iter().find_map(|instance| {
+            disassembler
+                .disassemble(instance, group.function_size)
+                .map(|lines| (instance, lines))
+        });
+        if let Some((instance, lines)) = representative {
+            json.insert("representative_address".to_owned(), json!(instance.address));
+            json.insert("disassembly".to_owned(), json!(lines));
+        }
+        serde_json::to_writer(&mut file, &json)?;
+        writeln!(file)?;
+        file.flush()?;
+    }
+    Ok(())
+}
+
+/// Replaces characters that aren't safe in file names on all platforms, e.g. `<` and `:`, and
+/// truncates long names.
+fn file_name_part(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '.'
+            }
+        })
+        .take(MAX_NAME_LEN)
+        .collect()
+}
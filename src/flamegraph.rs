@@ -0,0 +1,39 @@
+//! Output in the folded stack format used by flamegraph tools such as inferno and
+//! `flamegraph.pl`, so that where duplication is concentrated can be shown visually. Each line is
+//! a path from crate through modules to a function, followed by its excess bytes.
+
+use crate::sorted_duplicates;
+use crate::split_top;
+use crate::Args;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+use std::collections::BTreeMap;
+
+pub(crate) fn write_folded(
+    out: &mut impl std::io::Write,
+    report: &DuplicationReport,
+    args: &Args,
+) -> Result {
+    let mut symbols = sorted_duplicates(report, args);
+    split_top(&mut symbols, args.top);
+    // Groups with the same name, e.g. because they have different sizes, are combined, since
+    // tools expect each stack to appear once.
+    let mut stacks: BTreeMap<String, u64> = BTreeMap::new();
+    for group in symbols {
+        let path = group.representative_name().map_or_else(
+            || vec!["<unknown>".to_owned()],
+            duplicate_function_checker::symbol_path,
+        );
+        // Semicolons separate frames, so can't appear within them, e.g. in `[u8; 4]`.
+        let stack = path
+            .iter()
+            .map(|frame| frame.replace(';', ","))
+            .collect::<Vec<_>>()
+            .join(";");
+        *stacks.entry(stack).or_default() += group.excess_bytes();
+    }
+    for (stack, excess_bytes) in stacks {
+        writeln!(out, "{stack} {excess_bytes}")?;
+    }
+    Ok(())
+}
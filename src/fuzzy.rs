@@ -0,0 +1,201 @@
+//! Detection of functions that are similar, but not identical.
+//!
+//! Each distinct function body is reduced to its sequence of instruction codes, which ignores
+//! registers, constants and displacements. We estimate the similarity of these sequences with
+//! MinHash over short runs of consecutive instructions, then use locality-sensitive hashing to
+//! avoid comparing every pair of functions.
+
+use crate::decode_asm;
+use crate::display_name;
+use crate::functions;
+use crate::get_fn_bytes;
+use crate::passes_filters;
+use crate::require_x86;
+use crate::InstructionsKey;
+use crate::Key as _;
+use crate::KeyBuilderInputs;
+use crate::Options;
+use crate::Result;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+/// The number of consecutive instructions that are hashed together.
+const SHINGLE_LEN: usize = 3;
+
+const NUM_HASHES: usize = 64;
+
+/// The number of hashes in each locality-sensitive hashing band. Functions that share all hashes
+/// in at least one band are compared.
+const BAND_SIZE: usize = 4;
+
+/// A group of functions that are similar to each other, but not identical.
+pub struct SimilarCluster {
+    pub variants: Vec<Variant>,
+}
+
+/// A set of identical functions within a cluster.
+#[derive(Serialize)]
+pub struct Variant {
+    pub function_size: u64,
+    pub copies: u64,
+    pub names: Vec<String>,
+}
+
+impl SimilarCluster {
+    /// The bytes that would be saved if all variants could share a single implementation, not
+    /// counting savings from removing exact duplicates.
+    pub fn potential_savings(&self) -> u64 {
+        let total: u64 = self.variants.iter().map(|v| v.function_size).sum();
+        let largest = self
+            .variants
+            .iter()
+            .map(|v| v.function_size)
+            .max()
+            .unwrap_or(0);
+        total - largest
+    }
+}
+
+/// Finds clusters of functions whose instruction sequences are estimated to be at least
+/// `threshold` similar, where `threshold` is between 0 and 1. Clusters are sorted by potential
+/// savings, smallest first.
+pub fn find_similar(data: &[u8], options: &Options, threshold: f64) -> Result<Vec<SimilarCluster>> {
+    let object = object::File::parse(data)?;
+    require_x86(&object, "Finding similar functions")?;
+
+    let mut functions = functions(&object, options)?;
+    functions.retain(|function| passes_filters(function, options));
+    // Aliases would otherwise appear as exact duplicates. In relocatable objects, addresses are
+    // only unique within a section.
+    functions.sort_by_key(|function| (function.section_index.map(|i| i.0), function.address));
+    functions.dedup_by_key(|function| (function.section_index.map(|i| i.0), function.address));
+    let inputs = KeyBuilderInputs::new(&object, options, &functions);
+
+    let keyed = functions
+        .par_iter()
+        .filter_map(|function| {
+            let key = InstructionsKey::from_fn(function, &inputs)?;
+            let fn_bytes = get_fn_bytes(function, inputs.object)?;
+            let codes = decode_asm(fn_bytes, function.address, inputs.bitness)
+                .iter()
+                .map(|i| i.code() as u32)
+                .collect::<Vec<_>>();
+            if codes.is_empty() {
+                return None;
+            }
+            let name = function.name.as_ref().map(|n| display_name(n, options));
+            Some((key, function.size, codes, name))
+        })
+        .collect::<Vec<_>>();
+
+    // Exact duplicates are reported elsewhere, so we only cluster distinct bodies.
+    let mut variants: Vec<(Variant, Vec<u32>)> = Vec::new();
+    let mut variant_by_key: HashMap<InstructionsKey, usize> = HashMap::new();
+    for (key, size, codes, name) in keyed {
+        let index = *variant_by_key.entry(key).or_insert_with(|| {
+            variants.push((
+                Variant {
+                    function_size: size,
+                    copies: 0,
+                    names: Vec::new(),
+                },
+                codes,
+            ));
+            variants.len() - 1
+        });
+        let variant = &mut variants[index].0;
+        variant.copies += 1;
+        variant.names.extend(name);
+    }
+
+    let signatures = variants
+        .par_iter()
+        .map(|(_, codes)| min_hash(codes))
+        .collect::<Vec<_>>();
+
+    let mut clusters = UnionFind::new(variants.len());
+    for band in 0..NUM_HASHES / BAND_SIZE {
+        let mut buckets: HashMap<&[u64], usize> = HashMap::new();
+        for (index, signature) in signatures.iter().enumerate() {
+            let rows = &signature[band * BAND_SIZE..(band + 1) * BAND_SIZE];
+            // Rather than comparing all members of a bucket with each other, which could be
+            // quadratic, we just compare with the first. This might miss a few pairs.
+            let first = *buckets.entry(rows).or_insert(index);
+            if first != index && similarity(&signatures[first], signature) >= threshold {
+                clusters.union(first, index);
+            }
+        }
+    }
+
+    let mut by_root: BTreeMap<usize, Vec<Variant>> = BTreeMap::new();
+    for (index, (variant, _)) in variants.into_iter().enumerate() {
+        by_root
+            .entry(clusters.find(index))
+            .or_default()
+            .push(variant);
+    }
+    let mut result = by_root
+        .into_values()
+        .filter(|variants| variants.len() > 1)
+        .map(|variants| SimilarCluster { variants })
+        .collect::<Vec<_>>();
+    result.sort_by_key(|c| c.potential_savings());
+    Ok(result)
+}
+
+fn min_hash(codes: &[u32]) -> Vec<u64> {
+    let mut signature = vec![u64::MAX; NUM_HASHES];
+    for shingle in codes.windows(SHINGLE_LEN.min(codes.len()).max(1)) {
+        let mut hasher = DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let base = hasher.finish();
+        for (seed, min) in signature.iter_mut().enumerate() {
+            *min = (*min).min(mix(base ^ (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)));
+        }
+    }
+    signature
+}
+
+/// The finaliser from SplitMix64, used to derive independent hashes from a single hash.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Estimates the Jaccard similarity of the sets that produced two MinHash signatures.
+fn similarity(a: &[u64], b: &[u64]) -> f64 {
+    let matching = a.iter().zip(b).filter(|(a, b)| a == b).count();
+    matching as f64 / NUM_HASHES as f64
+}
+
+struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+        self.parents[a] = b;
+    }
+}
@@ -0,0 +1,159 @@
+//! Suggestions for how a duplicate group might be fixed, for readers who don't know why the
+//! compiler produced it. These are guesses from the group's names and how many distinct functions
+//! it holds, so may be wrong, but they say where to start looking.
+
+use crate::category;
+use crate::category::Category;
+use crate::demangle::demangle;
+use crate::generic_root;
+use crate::DuplicateGroup;
+use std::collections::BTreeSet;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Hint {
+    /// Different functions that compiled to the same code, which the linker can fold.
+    LinkerIcf { what: &'static str },
+
+    /// One instantiation of a generic function that was emitted by several crates or codegen units.
+    ShareGenerics { function: String },
+
+    /// Several instantiations of a generic function whose code doesn't depend on the type.
+    NonGenericInner {
+        function: String,
+        instantiations: usize,
+    },
+
+    /// A closure that was emitted several times, e.g. because its enclosing function is generic.
+    ExtractClosure { copies: u64 },
+
+    /// A Rust function that was emitted more than once under the same path. Legacy symbol mangling
+    /// leaves out generic arguments, so these may be different instantiations.
+    SamePath { function: String, copies: u64 },
+
+    /// A non-generic function that was compiled more than once, e.g. because it's `#[inline]` or
+    /// a `static` function in a C header.
+    DefineOnce { function: String, copies: u64 },
+}
+
+impl Hint {
+    /// A stable identifier for machine-readable output.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Hint::LinkerIcf { .. } => "linker_icf",
+            Hint::ShareGenerics { .. } => "share_generics",
+            Hint::NonGenericInner { .. } => "non_generic_inner",
+            Hint::ExtractClosure { .. } => "extract_closure",
+            Hint::SamePath { .. } => "same_path",
+            Hint::DefineOnce { .. } => "define_once",
+        }
+    }
+}
+
+impl std::fmt::Display for Hint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hint::LinkerIcf { what } => write!(
+                f,
+                "{what} with identical code — enable linker ICF, e.g. `-C link-arg=-Wl,--icf=all`"
+            ),
+            Hint::ShareGenerics { function } => write!(
+                f,
+                "the same instantiation of `{function}` in several codegen units — consider \
+                 `-Zshare-generics` or fewer codegen units"
+            ),
+            Hint::NonGenericInner {
+                function,
+                instantiations,
+            } => write!(
+                f,
+                "{instantiations} identical monomorphisations of `{function}` — consider moving \
+                 code that doesn't depend on the type parameters into a non-generic inner fn, or \
+                 dyn dispatch"
+            ),
+            Hint::ExtractClosure { copies } => write!(
+                f,
+                "closure duplicated {copies} times — consider extracting a non-generic inner fn"
+            ),
+            Hint::SamePath { function, copies } => write!(
+                f,
+                "`{function}` emitted {copies} times, as identical monomorphisations or in several \
+                 codegen units — consider a non-generic inner fn or `-Zshare-generics`"
+            ),
+            Hint::DefineOnce { function, copies } => write!(
+                f,
+                "`{function}` compiled {copies} times — if it's `#[inline]` or `static` in a \
+                 header, consider defining it once"
+            ),
+        }
+    }
+}
+
+/// Returns a hint for fixing `group`, or `None` if it isn't duplicated or has no names to go by.
+pub fn suggest(group: &DuplicateGroup) -> Option<Hint> {
+    let name = group.representative_name()?;
+    if group.copies < 2 {
+        return None;
+    }
+    // Names that differ only in rustc's hash are the same function from different crates.
+    let instantiations = group
+        .names
+        .keys()
+        .map(|name| demangle(name, true))
+        .collect::<BTreeSet<_>>()
+        .len();
+    let roots = group
+        .names
+        .keys()
+        .map(|name| generic_root(name))
+        .collect::<BTreeSet<_>>();
+    let root = generic_root(name);
+    let hint = match category::classify(name) {
+        Category::Closure => Hint::ExtractClosure {
+            copies: group.copies,
+        },
+        category @ (Category::DropGlue
+        | Category::VtableShim
+        | Category::TraitObjectShim
+        | Category::Clone) => {
+            if instantiations > 1 {
+                Hint::LinkerIcf {
+                    what: category.description(),
+                }
+            } else {
+                Hint::ShareGenerics { function: root }
+            }
+        }
+        Category::UserCode if roots.len() > 1 => Hint::LinkerIcf {
+            what: "different functions",
+        },
+        Category::UserCode if root != demangle(name, true) => {
+            if instantiations > 1 {
+                Hint::NonGenericInner {
+                    function: root,
+                    instantiations,
+                }
+            } else {
+                Hint::ShareGenerics { function: root }
+            }
+        }
+        Category::UserCode if is_rust(group) => Hint::SamePath {
+            function: root,
+            copies: group.copies,
+        },
+        Category::UserCode => Hint::DefineOnce {
+            function: root,
+            copies: group.copies,
+        },
+    };
+    Some(hint)
+}
+
+/// Returns whether any of the group's names, or the symbols of its copies, are Rust symbols.
+fn is_rust(group: &DuplicateGroup) -> bool {
+    group
+        .names
+        .keys()
+        .map(|name| name.as_str())
+        .chain(group.instances.iter().filter_map(|i| i.name.as_deref()))
+        .any(|name| rustc_demangle::try_demangle(name).is_ok())
+}
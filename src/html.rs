@@ -0,0 +1,365 @@
+//! A standalone HTML report, for people who want to browse results without running the tool.
+
+use crate::pretty_size;
+use crate::pretty_size_delta;
+use crate::sorted_duplicates;
+use crate::split_top;
+use crate::Args;
+use crate::Extras;
+use duplicate_function_checker::hint;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+use std::path::Path;
+
+/// The maximum number of bars in each chart.
+const CHART_ROWS: usize = 10;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { padding: 4px 8px; border-bottom: 1px solid #ddd; text-align: left; vertical-align: top; }
+th { cursor: pointer; background: #f4f4f4; user-select: none; }
+td.num, th.num { text-align: right; }
+code { font-size: 90%; word-break: break-all; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 4px 16px; }
+dt { font-weight: bold; }
+.chart { display: grid; grid-template-columns: minmax(10em, 30em) auto; gap: 2px 8px; }
+.label { overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+.bar { background: #c0504d; color: white; padding: 0 4px; white-space: nowrap; }
+";
+
+/// Sorts the table by the clicked column. Cells with a `data-value` attribute sort numerically.
+const SCRIPT: &str = "\
+document.querySelectorAll('#groups th').forEach((th, column) => {
+  th.addEventListener('click', () => {
+    const body = document.querySelector('#groups tbody');
+    const descending = th.dataset.order !== 'desc';
+    th.dataset.order = descending ? 'desc' : 'asc';
+    const key = row => {
+      const cell = row.children[column];
+      return cell.dataset.value !== undefined ? Number(cell.dataset.value) : cell.textContent;
+    };
+    const rows = Array.from(body.rows);
+    rows.sort((a, b) => {
+      const [x, y] = descending ? [key(b), key(a)] : [key(a), key(b)];
+      return x < y ? -1 : x > y ? 1 : 0;
+    });
+    rows.forEach(row => body.appendChild(row));
+  });
+});
+";
+
+pub(crate) fn write_report(
+    out: &mut impl std::io::Write,
+    bin: &Path,
+    report: &DuplicationReport,
+    extras: &Extras,
+    args: &Args,
+) -> Result {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html><head><meta charset=\"utf-8\">")?;
+    write!(out, "<title>Duplicate functions in ")?;
+    write_escaped(out, &bin.display().to_string())?;
+    writeln!(out, "</title>")?;
+    writeln!(out, "<style>\n{STYLE}</style></head><body>")?;
+
+    writeln!(out, "<h1>Duplicate functions</h1>")?;
+    writeln!(out, "<dl>")?;
+    write_summary_item(out, "Original binary", &pretty_size(report.text_size))?;
+    write_summary_item(
+        out,
+        "Excess bytes",
+        &format!(
+            "{} ({:.1}% of {})",
+            pretty_size(report.excess_bytes()),
+            report.excess_fraction() * 100.0,
+            report.denominator.description()
+        ),
+    )?;
+    write_summary_item(
+        out,
+        "Functions",
+        &format!(
+            "{} with dupes, {} excess instances",
+            report.duplicated_functions(),
+            report.excess_instances()
+        ),
+    )?;
+    write_summary_item(
+        out,
+        "Coverage",
+        &format!(
+            "{:.1}% of executable code ({}) is in functions that were checked",
+            report.coverage_fraction() * 100.0,
+            pretty_size(report.checked_bytes)
+        ),
+    )?;
+    let comdat_excess_bytes = report.comdat_excess_bytes();
+    if comdat_excess_bytes > 0 {
+        write_summary_item(
+            out,
+            "COMDAT",
+            &format!(
+                "{} of excess bytes will be deduplicated by the linker, leaving {}",
+                pretty_size(comdat_excess_bytes),
+                pretty_size(report.excess_bytes() - comdat_excess_bytes)
+            ),
+        )?;
+    }
+    if report.filtered_functions > 0 {
+        write_summary_item(
+            out,
+            "Filtered",
+            &format!(
+                "{} fns ({}) excluded by filters",
+                report.filtered_functions,
+                pretty_size(report.filtered_bytes)
+            ),
+        )?;
+    }
+    if !report.key_failures.is_empty() {
+        write_summary_item(
+            out,
+            "Not checked",
+            &format!(
+                "{} fns ({}) couldn't be decoded or re-encoded",
+                report.key_failures.len(),
+                pretty_size(report.key_failure_bytes())
+            ),
+        )?;
+    }
+    if let Some(baseline_diff) = &extras.baseline_diff {
+        write_summary_item(
+            out,
+            "From baseline",
+            &format!(
+                "{} -> {} excess bytes ({}), {} new or grown groups",
+                pretty_size(baseline_diff.old_excess_bytes),
+                pretty_size(baseline_diff.new_excess_bytes),
+                pretty_size_delta(baseline_diff.excess_bytes_delta()),
+                baseline_diff.regressions().count()
+            ),
+        )?;
+    }
+    if let Some(icf) = &extras.icf {
+        write_summary_item(
+            out,
+            "ICF could remove",
+            &format!(
+                "{} ({} from byte-identical fns)",
+                pretty_size(icf.icf_excess_bytes),
+                pretty_size(icf.byte_identical_excess_bytes)
+            ),
+        )?;
+    }
+    if let Some(register_only_bytes) = extras.register_only_bytes {
+        write_summary_item(
+            out,
+            "Register-only",
+            &format!(
+                "{} of excess bytes differ in register allocation, so need outlining rather \
+                 than ICF",
+                pretty_size(register_only_bytes)
+            ),
+        )?;
+    }
+    if let Some(reachability) = &extras.reachability {
+        write_summary_item(
+            out,
+            "Unreachable",
+            &format!(
+                "{} of excess bytes are in fns that `--gc-sections` would remove",
+                pretty_size(reachability.unreachable_excess_bytes(report))
+            ),
+        )?;
+    }
+    if let Some(similar) = &extras.similar {
+        write_summary_item(
+            out,
+            "Near-dupes",
+            &format!(
+                "{} clusters of similar fns, {} could be saved by merging",
+                similar.len(),
+                pretty_size(similar.iter().map(|c| c.potential_savings()).sum())
+            ),
+        )?;
+    }
+    if let Some(collisions) = &extras.collisions {
+        write_summary_item(
+            out,
+            "Collisions",
+            &format!(
+                "{} names shared by fns with different contents",
+                collisions.len()
+            ),
+        )?;
+    }
+    if let Some(profile) = &extras.profile {
+        let hot_groups = profile.hot_groups(report);
+        write_summary_item(
+            out,
+            "Hot duplicates",
+            &format!(
+                "{} groups sampled, {:.1}% of samples in duplicated fns",
+                hot_groups.len(),
+                hot_groups.iter().map(|g| g.samples).sum::<u64>() as f64
+                    / profile.total_samples() as f64
+                    * 100.0
+            ),
+        )?;
+    }
+    for estimate in extras.what_if.iter().flatten() {
+        write_summary_item(
+            out,
+            &format!("What if: {}", estimate.strategy.description()),
+            &format!(
+                "{} ({:.1}% of {})",
+                pretty_size(estimate.saved_bytes),
+                report.fraction_of_denominator(estimate.saved_bytes) * 100.0,
+                report.denominator.description()
+            ),
+        )?;
+    }
+    writeln!(out, "</dl>")?;
+
+    let mut symbols = sorted_duplicates(report, args);
+
+    let by_crate = report
+        .excess_bytes_by_crate()
+        .into_iter()
+        .map(|(crate_name, bytes)| (crate_name.unwrap_or_else(|| "<unknown>".to_owned()), bytes))
+        .collect::<Vec<_>>();
+    write_chart(out, "Excess bytes by crate", &by_crate)?;
+
+    let by_category = report
+        .excess_bytes_by_category()
+        .into_iter()
+        .map(|(category, bytes)| (category.description().to_owned(), bytes))
+        .collect::<Vec<_>>();
+    write_chart(out, "Excess bytes by category", &by_category)?;
+
+    let by_section = report
+        .excess_bytes_by_section()
+        .into_iter()
+        .map(|section| {
+            let name = section.name.unwrap_or_else(|| "<unknown>".to_owned());
+            (name, section.excess_bytes)
+        })
+        .collect::<Vec<_>>();
+    write_chart(out, "Excess bytes by section", &by_section)?;
+
+    let by_linkage = report
+        .excess_bytes_by_linkage()
+        .into_iter()
+        .map(|(linkage, bytes)| (linkage.description(), bytes))
+        .collect::<Vec<_>>();
+    write_chart(out, "Excess bytes by linkage", &by_linkage)?;
+
+    let mut largest = symbols
+        .iter()
+        .map(|v| {
+            let name = v.representative_name().unwrap_or_default().to_owned();
+            (name, v.excess_bytes())
+        })
+        .collect::<Vec<_>>();
+    largest.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+    write_chart(out, "Largest duplicate groups", &largest)?;
+
+    let omitted = split_top(&mut symbols, args.top);
+    writeln!(out, "<h2>Duplicate groups</h2>")?;
+    if !omitted.is_empty() {
+        writeln!(
+            out,
+            "<p>{} more groups omitted ({} excess bytes)</p>",
+            omitted.len(),
+            pretty_size(omitted.iter().map(|v| v.excess_bytes()).sum())
+        )?;
+    }
+    writeln!(out, "<table id=\"groups\"><thead><tr>")?;
+    writeln!(
+        out,
+        "<th>Name</th><th class=\"num\">Function size</th><th class=\"num\">Copies</th>\
+         <th class=\"num\">Excess bytes</th><th>Source</th><th>Hint</th><th>Group</th>"
+    )?;
+    writeln!(out, "</tr></thead><tbody>")?;
+    // Groups are sorted with the worst last, which suits a terminal, but not a page that's read
+    // from the top.
+    for v in symbols.iter().rev() {
+        write!(out, "<tr><td><details><summary><code>")?;
+        write_escaped(out, v.representative_name().unwrap_or_default())?;
+        write!(out, "</code> ({} names)</summary><ul>", v.names.len())?;
+        for (name, count) in &v.names {
+            write!(out, "<li>{count}x <code>")?;
+            write_escaped(out, name)?;
+            write!(out, "</code></li>")?;
+        }
+        write!(out, "</ul></details></td>")?;
+        for value in [v.function_size, v.copies, v.excess_bytes()] {
+            write!(out, "<td class=\"num\" data-value=\"{value}\">{value}</td>")?;
+        }
+        write!(out, "<td>")?;
+        if let Some(location) = extras.sources.as_ref().and_then(|s| s.find_for_group(v)) {
+            write_escaped(out, &location.to_string())?;
+        }
+        write!(out, "</td><td>")?;
+        if let Some(hint) = hint::suggest(v) {
+            write_escaped(out, &hint.to_string())?;
+        }
+        writeln!(out, "</td><td><code>{}</code></td></tr>", v.id())?;
+    }
+    writeln!(out, "</tbody></table>")?;
+    writeln!(out, "<script>\n{SCRIPT}</script>")?;
+    writeln!(out, "</body></html>")?;
+    Ok(())
+}
+
+fn write_summary_item(out: &mut impl std::io::Write, label: &str, value: &str) -> Result {
+    write!(out, "<dt>{label}</dt><dd>")?;
+    write_escaped(out, value)?;
+    writeln!(out, "</dd>")?;
+    Ok(())
+}
+
+/// Writes a horizontal bar chart of the first `CHART_ROWS` entries, which should be sorted with the
+/// largest first.
+fn write_chart(out: &mut impl std::io::Write, title: &str, entries: &[(String, u64)]) -> Result {
+    let entries = &entries[..entries.len().min(CHART_ROWS)];
+    let Some(max) = entries
+        .iter()
+        .map(|(_, bytes)| *bytes)
+        .max()
+        .filter(|max| *max > 0)
+    else {
+        return Ok(());
+    };
+    writeln!(out, "<h2>{title}</h2>")?;
+    writeln!(out, "<div class=\"chart\">")?;
+    for (label, bytes) in entries {
+        write!(out, "<div class=\"label\" title=\"")?;
+        write_escaped(out, label)?;
+        write!(out, "\"><code>")?;
+        write_escaped(out, label)?;
+        writeln!(
+            out,
+            "</code></div><div><span class=\"bar\" style=\"display: inline-block; \
+             width: {:.1}%\">{}</span></div>",
+            *bytes as f64 / max as f64 * 100.0,
+            pretty_size(*bytes)
+        )?;
+    }
+    writeln!(out, "</div>")?;
+    Ok(())
+}
+
+fn write_escaped(out: &mut impl std::io::Write, s: &str) -> Result {
+    for c in s.chars() {
+        match c {
+            '<' => write!(out, "&lt;")?,
+            '>' => write!(out, "&gt;")?,
+            '&' => write!(out, "&amp;")?,
+            '"' => write!(out, "&quot;")?,
+            c => write!(out, "{c}")?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,232 @@
+//! Simulation of a linker's identical code folding (ICF) pass.
+//!
+//! Two functions that are identical except that they call different functions can still be folded
+//! if the functions that they call can themselves be folded. We find the largest set of foldings
+//! that's consistent by starting with all functions that have the same instructions (ignoring
+//! which functions they call) in the same class, then repeatedly splitting classes whose members
+//! call functions in different classes until nothing changes.
+
+use crate::decode_asm;
+use crate::encode_asm;
+use crate::functions;
+use crate::get_fn_bytes;
+use crate::passes_filters;
+use crate::require_x86;
+use crate::set_near_branch_target;
+use crate::Function;
+use crate::KeyBuilderInputs;
+use crate::Options;
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context as _;
+use object::Object as _;
+use object::ObjectKind;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// How much could be saved by folding identical functions.
+pub struct IcfSummary {
+    /// Excess bytes from functions that are byte-for-byte identical once normalised for their
+    /// address.
+    pub byte_identical_excess_bytes: u64,
+
+    /// Excess bytes from functions that could be folded if call targets are themselves folded.
+    pub icf_excess_bytes: u64,
+
+    /// The number of refinement passes needed before the classes stopped changing.
+    pub iterations: u32,
+}
+
+/// A function's instructions with the targets of calls and jumps to other functions removed.
+struct Body {
+    size: u64,
+    bytes: Vec<u8>,
+
+    /// The index of each instruction that branches to another function, together with the index
+    /// of the function that it branches to.
+    targets: Vec<(usize, usize)>,
+
+    /// As for `targets`, but with the address that was branched to.
+    raw_targets: Vec<(usize, u64)>,
+}
+
+pub fn simulate_icf(data: &[u8], options: &Options) -> Result<IcfSummary> {
+    let object = object::File::parse(data)?;
+    let (_, bodies) = function_bodies(&object, options, "ICF simulation")?;
+
+    // Byte-identical functions are those with identical bodies that call exactly the same
+    // functions.
+    let byte_identical_excess_bytes = excess_bytes_by_class(
+        bodies
+            .iter()
+            .flatten()
+            .map(|body| ((&body.bytes, &body.raw_targets), body.size)),
+    );
+
+    // Functions that we couldn't decode each get their own class.
+    let mut initial: HashMap<(&[u8], Vec<usize>), usize> = HashMap::new();
+    let mut next_unique = 0;
+    let mut classes = bodies
+        .iter()
+        .map(|body| match body {
+            Some(body) => {
+                let indexes = body.targets.iter().map(|(i, _)| *i).collect();
+                let next = initial.len();
+                *initial.entry((&body.bytes, indexes)).or_insert(next)
+            }
+            None => {
+                next_unique += 1;
+                usize::MAX - next_unique
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut num_classes = initial.len() + next_unique;
+
+    let mut iterations = 0;
+    loop {
+        iterations += 1;
+        let mut refined: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+        let new_classes = bodies
+            .iter()
+            .zip(&classes)
+            .map(|(body, &class)| {
+                let target_classes = body
+                    .iter()
+                    .flat_map(|body| body.targets.iter().map(|&(_, target)| classes[target]))
+                    .collect();
+                let next = refined.len();
+                *refined.entry((class, target_classes)).or_insert(next)
+            })
+            .collect();
+        classes = new_classes;
+        // Refinement only ever splits classes, so if the number of classes didn't change, then
+        // nothing changed.
+        if refined.len() == num_classes {
+            break;
+        }
+        num_classes = refined.len();
+    }
+
+    let icf_excess_bytes = excess_bytes_by_class(
+        bodies
+            .iter()
+            .zip(&classes)
+            .filter_map(|(body, class)| Some((*class, body.as_ref()?.size))),
+    );
+
+    Ok(IcfSummary {
+        byte_identical_excess_bytes,
+        icf_excess_bytes,
+        iterations,
+    })
+}
+
+/// Returns the symbol names of each group of functions that are byte-identical once normalised for
+/// their address, so could be folded without also folding the functions that they call. The first
+/// name in each group is the copy with the lowest address. Groups with the most excess bytes come
+/// first.
+pub fn byte_identical_groups(data: &[u8], options: &Options) -> Result<Vec<Vec<String>>> {
+    let object = object::File::parse(data)?;
+    let (functions, bodies) = function_bodies(&object, options, "Finding foldable functions")?;
+    let mut groups: HashMap<_, (u64, Vec<String>)> = HashMap::new();
+    for (function, body) in functions.iter().zip(&bodies) {
+        let (Some(body), Some(name)) = (body, &function.name) else {
+            continue;
+        };
+        groups
+            .entry((&body.bytes, &body.raw_targets))
+            .or_insert_with(|| (body.size, Vec::new()))
+            .1
+            .push(name.to_string());
+    }
+    let mut groups = groups
+        .into_values()
+        .filter(|(_, names)| names.len() > 1)
+        .collect::<Vec<_>>();
+    groups.sort_by(|(a_size, a_names), (b_size, b_names)| {
+        let excess = |size: u64, names: &[String]| size * (names.len() as u64 - 1);
+        excess(*b_size, b_names)
+            .cmp(&excess(*a_size, a_names))
+            .then_with(|| a_names.cmp(b_names))
+    });
+    Ok(groups.into_iter().map(|(_, names)| names).collect())
+}
+
+/// Returns the functions in `object` that pass the filters in `options`, with one alias of each,
+/// sorted by address, together with their bodies, or `None` for those that we couldn't decode.
+/// `what` describes what they're needed for, for errors.
+fn function_bodies<'data>(
+    object: &object::File<'data, &'data [u8]>,
+    options: &Options,
+    what: &str,
+) -> Result<(Vec<Function<'data>>, Vec<Option<Body>>)> {
+    if object.kind() == ObjectKind::Relocatable {
+        bail!("{what} requires a linked binary");
+    }
+    require_x86(object, what)?;
+
+    // Aliases are a single function as far as folding is concerned.
+    let mut functions = functions(object, options)?;
+    functions.retain(|function| passes_filters(function, options));
+    functions.sort_by_key(|function| function.address);
+    functions.dedup_by_key(|function| function.address);
+    let index_by_address: HashMap<u64, usize> = functions
+        .iter()
+        .enumerate()
+        .map(|(index, function)| (function.address, index))
+        .collect();
+    let inputs = KeyBuilderInputs::new(object, options, &functions);
+
+    let new_address = inputs
+        .max_fn_address
+        .context("Only x86 code can be re-encoded")?;
+    let bodies = functions
+        .par_iter()
+        .map(|function| {
+            let fn_bytes = get_fn_bytes(function, inputs.object)?;
+            let fn_range = function.address..function.address + function.size;
+            let mut instructions = decode_asm(fn_bytes, function.address, inputs.bitness);
+            let mut targets = Vec::new();
+            let mut raw_targets = Vec::new();
+            for (index, instruction) in instructions.iter_mut().enumerate() {
+                if !(instruction.is_call_near()
+                    || instruction.is_jmp_near()
+                    || instruction.is_jcc_short_or_near())
+                {
+                    continue;
+                }
+                let target = instruction.near_branch_target();
+                if fn_range.contains(&target) {
+                    continue;
+                }
+                if let Some(&target_index) = index_by_address.get(&target) {
+                    set_near_branch_target(instruction, new_address);
+                    raw_targets.push((index, target));
+                    targets.push((index, target_index));
+                }
+            }
+            let bytes = encode_asm(&instructions, new_address, inputs.bitness).ok()?;
+            Some(Body {
+                size: function.size,
+                bytes,
+                targets,
+                raw_targets,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok((functions, bodies))
+}
+
+/// Given the class and size of each function, returns the bytes that would be saved by keeping
+/// only one function from each class.
+fn excess_bytes_by_class<C: Hash + Eq>(functions: impl Iterator<Item = (C, u64)>) -> u64 {
+    let mut classes: HashMap<C, (u64, u64)> = HashMap::new();
+    for (class, size) in functions {
+        classes.entry(class).or_insert((0, size)).0 += 1;
+    }
+    classes
+        .values()
+        .map(|(copies, size)| copies.saturating_sub(1) * size)
+        .sum()
+}
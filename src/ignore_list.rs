@@ -0,0 +1,81 @@
+//! Duplication that's known to be acceptable, e.g. copies of a function that are compiled for each
+//! CPU feature level and picked between at run time. Groups matching an ignore file are left out of
+//! the totals and limits, so that CI only fails on new duplication. Each line of the file is a
+//! pattern matched against the names of each group:
+//!
+//! ```text
+//! # Per-CPU dispatch.
+//! my_crate::simd::*::checksum
+//! regex:^core::fmt::num::
+//! id:3f9a0c27d1e4
+//! ```
+//!
+//! Patterns match whole names and may contain `*`, which matches any sequence of characters, and
+//! `?`, which matches any one character. Lines starting with `regex:` are regular expressions,
+//! which match anywhere in a name. Lines starting with `id:` match the group with that ID, as shown
+//! in reports. Blank lines and lines starting with `#` are ignored.
+
+use anyhow::Context;
+use duplicate_function_checker::DuplicateGroup;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+use regex::Regex;
+use std::path::Path;
+
+pub(crate) struct IgnoreList {
+    patterns: Vec<Regex>,
+    ids: Vec<String>,
+}
+
+impl IgnoreList {
+    pub(crate) fn load(path: &Path) -> Result<IgnoreList> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ignore file `{}`", path.display()))?;
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let (ids, patterns): (Vec<_>, Vec<_>) =
+            lines.partition(|(_, line)| line.starts_with("id:"));
+        let ids = ids
+            .into_iter()
+            .map(|(_, line)| line["id:".len()..].trim().to_ascii_lowercase())
+            .collect();
+        let patterns = patterns
+            .into_iter()
+            .map(|(i, line)| {
+                let regex = match line.strip_prefix("regex:") {
+                    Some(regex) => regex.to_owned(),
+                    None => format!(
+                        "^{}$",
+                        regex::escape(line).replace(r"\*", ".*").replace(r"\?", ".")
+                    ),
+                };
+                Regex::new(&regex).with_context(|| {
+                    format!("Invalid pattern on line {} of `{}`", i + 1, path.display())
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(IgnoreList { patterns, ids })
+    }
+
+    /// Removes the groups with duplicates that have a name matching one of our patterns from
+    /// `report` and returns them, smallest first.
+    pub(crate) fn apply(&self, report: &mut DuplicationReport) -> Vec<DuplicateGroup> {
+        let (mut ignored, kept) = std::mem::take(&mut report.groups)
+            .into_iter()
+            .partition::<Vec<_>, _>(|group| group.copies > 1 && self.matches(group));
+        report.groups = kept;
+        ignored.sort_by_key(|group| group.excess_bytes());
+        ignored
+    }
+
+    fn matches(&self, group: &DuplicateGroup) -> bool {
+        self.ids.contains(&group.id())
+            || group
+                .names
+                .keys()
+                .any(|name| self.patterns.iter().any(|pattern| pattern.is_match(name)))
+    }
+}
@@ -0,0 +1,113 @@
+//! Inputs that are made up of several files: directories of object files and archives, such as
+//! `target/release/deps`, and thin archives, whose members are separate files.
+
+use crate::read_input;
+use crate::InputData;
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context;
+use object::read::archive::ArchiveFile;
+use object::FileKind;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// One of the files that make up an input.
+pub(crate) struct InputFile {
+    /// The path of the file relative to the directory, or for members of thin archives,
+    /// `archive(member)`.
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+    pub(crate) data: InputData,
+}
+
+pub fn is_thin_archive(data: &[u8]) -> bool {
+    data.starts_with(b"!<thin>\n")
+}
+
+/// Returns every object file and archive under `dir`, recursively and sorted by path, with members
+/// of thin archives in place of the archives themselves. Other files, such as dep-info files, are
+/// skipped.
+pub(crate) fn read_directory(dir: &Path) -> Result<Vec<InputFile>> {
+    let mut paths = Vec::new();
+    walk(dir, &mut paths)?;
+    paths.sort();
+    let mut files = Vec::new();
+    // A thin archive's members are often in the same directory, in which case we only analyse
+    // them once.
+    let mut seen = HashSet::new();
+    for path in paths {
+        if !seen.insert(canonical(&path)) {
+            continue;
+        }
+        let data =
+            read_input(&path).with_context(|| format!("Failed to read `{}`", path.display()))?;
+        if FileKind::parse(&*data).is_err() {
+            continue;
+        }
+        let name = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        if is_thin_archive(&data) {
+            for member in read_thin_archive(&path, &name, &data)? {
+                if seen.insert(canonical(&member.path)) {
+                    files.push(member);
+                }
+            }
+        } else {
+            files.push(InputFile { name, path, data });
+        }
+    }
+    if files.is_empty() {
+        bail!("No object files or archives found");
+    }
+    Ok(files)
+}
+
+/// Returns the members of the thin archive at `path`, the contents of which are `data`. Member
+/// names are relative to the archive's directory. `name` is used for the archive in the names of
+/// the members.
+pub(crate) fn read_thin_archive(path: &Path, name: &str, data: &[u8]) -> Result<Vec<InputFile>> {
+    let archive = ArchiveFile::parse(data)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut members = Vec::new();
+    for member in archive.members() {
+        let member = member?;
+        let member_name = String::from_utf8_lossy(member.name()).into_owned();
+        let member_path = dir.join(&member_name);
+        let data = read_input(&member_path).with_context(|| {
+            format!(
+                "Failed to read `{}`, a member of thin archive `{}`",
+                member_path.display(),
+                path.display()
+            )
+        })?;
+        members.push(InputFile {
+            name: format!("{name}({member_name})"),
+            path: member_path,
+            data,
+        });
+    }
+    Ok(members)
+}
+
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> Result {
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read `{}`", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Resolves symlinks and relative components, so that each file is only analysed once.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
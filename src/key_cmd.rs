@@ -0,0 +1,142 @@
+//! Keying functions with an external program, so that custom canonicalisations can be tried out
+//! without changing this crate.
+//!
+//! The program is started once and sent one line of JSON for each function on its stdin:
+//!
+//! ```json
+//! {"name":"_ZN3foo3bar17h0123456789abcdefE","address":"401000","size":8,"section":".text",
+//!  "architecture":"X86_64","bytes":"60e8f3ffffff61c3"}
+//! ```
+//!
+//! For each line, it must print one line on its stdout, which is the function's key. Functions with
+//! the same key and size are grouped together. An empty line leaves the function out. The program
+//! needs to flush its output after each line, since the next function isn't sent until the key of
+//! the previous one is read.
+
+use crate::Function;
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context as _;
+use object::Object as _;
+use object::ObjectSection as _;
+use serde::Serialize;
+use std::io::BufRead as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::Stdio;
+
+/// The line that's sent for each function. Addresses are hex strings, so that they aren't rounded
+/// by programs that read JSON numbers as doubles.
+#[derive(Serialize)]
+struct Request<'a> {
+    name: Option<&'a str>,
+    address: String,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    section: Option<&'a str>,
+    architecture: String,
+    bytes: String,
+}
+
+pub(crate) struct KeyCommand {
+    program: PathBuf,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: std::io::BufReader<ChildStdout>,
+
+    /// The first thing that went wrong. Once set, no more functions are sent.
+    error: Option<anyhow::Error>,
+}
+
+impl KeyCommand {
+    pub(crate) fn spawn(program: &Path) -> Result<Self> {
+        let mut child = std::process::Command::new(program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run `{}`", program.display()))?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().context("Key command has no stdout")?;
+        Ok(Self {
+            program: program.to_owned(),
+            child,
+            stdin,
+            stdout: std::io::BufReader::new(stdout),
+            error: None,
+        })
+    }
+
+    /// Returns the key of `function`, whose code is `fn_bytes`, or `None` if it should be left out
+    /// or something went wrong. Errors are reported by `finish`.
+    pub(crate) fn key(
+        &mut self,
+        function: &Function,
+        fn_bytes: &[u8],
+        object: &object::File,
+    ) -> Option<String> {
+        if self.error.is_some() {
+            return None;
+        }
+        match self.request(function, fn_bytes, object) {
+            Ok(key) => key,
+            Err(error) => {
+                self.error = Some(error.context(format!(
+                    "Failed to get a key from `{}`",
+                    self.program.display()
+                )));
+                None
+            }
+        }
+    }
+
+    fn request(
+        &mut self,
+        function: &Function,
+        fn_bytes: &[u8],
+        object: &object::File,
+    ) -> Result<Option<String>> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .context("Key command's stdin is closed")?;
+        let section = function
+            .section_index
+            .and_then(|index| object.section_by_index(index).ok());
+        let request = Request {
+            name: function.name.as_deref(),
+            address: format!("{:x}", function.address),
+            size: function.size,
+            section: section.as_ref().and_then(|section| section.name().ok()),
+            architecture: format!("{:?}", object.architecture()),
+            bytes: fn_bytes.iter().map(|byte| format!("{byte:02x}")).collect(),
+        };
+        let mut line = serde_json::to_vec(&request)?;
+        line.push(b'\n');
+        stdin.write_all(&line)?;
+        stdin.flush()?;
+
+        let mut key = String::new();
+        if self.stdout.read_line(&mut key)? == 0 {
+            bail!("It exited before printing a key");
+        }
+        let key = key.trim_end_matches(['\r', '\n']);
+        Ok((!key.is_empty()).then(|| key.to_owned()))
+    }
+
+    /// Closes the program's stdin, waits for it to exit and returns the first error, if any.
+    pub(crate) fn finish(mut self) -> Result {
+        drop(self.stdin.take());
+        let status = self.child.wait()?;
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if !status.success() {
+            bail!("`{}` failed with {status}", self.program.display());
+        }
+        Ok(())
+    }
+}
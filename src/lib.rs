@@ -0,0 +1,2791 @@
+//! Determines what percentage of a binary's functions are excess duplicates. A symbol table is
+//! needed and functions in the symbol table need to have non-zero sizes.
+
+use anyhow::bail;
+use anyhow::Context as _;
+use category::Category;
+use input_files::InputFile;
+use key_cmd::KeyCommand;
+use linkage::Binding;
+use linkage::Linkage;
+use object::read::archive::ArchiveFile;
+use object::Architecture;
+use object::BinaryFormat;
+use object::FileKind;
+use object::Object as _;
+use object::ObjectComdat as _;
+use object::ObjectKind;
+use object::ObjectSection;
+use object::ObjectSegment as _;
+use object::ObjectSymbol;
+use object::Relocation;
+use object::RelocationEncoding;
+use object::RelocationKind;
+use object::RelocationTarget;
+use object::SectionFlags;
+use object::SectionIndex;
+use object::SectionKind;
+use object::SymbolFlags;
+use object::SymbolKind;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
+use stats::Phase;
+use stats::Skip;
+use stats::Stats;
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::hash::Hasher as _;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+mod arch;
+mod arm;
+pub mod cache;
+pub mod category;
+pub mod collisions;
+pub mod compare;
+pub mod cross_binary;
+pub mod debug_file;
+mod demangle;
+pub mod diff;
+pub mod disasm;
+pub mod fuzzy;
+pub mod hint;
+pub mod icf;
+pub mod input_files;
+mod key_cmd;
+pub mod linkage;
+pub mod linker_map;
+pub mod metrics;
+mod mips;
+mod pdb_symbols;
+mod powerpc;
+pub mod profile;
+pub mod reachability;
+pub mod rodata;
+pub mod shared_libs;
+pub mod source;
+pub mod stats;
+pub mod symbol_checks;
+pub mod symbol_index;
+mod symbols_file;
+pub mod trend;
+pub mod universal;
+mod unpack;
+mod unwind;
+pub mod what_if;
+
+pub type Result<T = (), E = anyhow::Error> = core::result::Result<T, E>;
+
+/// Controls how functions are grouped and named.
+#[derive(Clone)]
+pub struct Options {
+    /// What to key functions by.
+    pub key: KeyType,
+
+    /// Whether to demangle symbol names.
+    pub demangle: bool,
+
+    /// Whether to demangle symbol names and drop rust's hashes. Names in a group that only differ
+    /// in their hash are then counted together in `DuplicateGroup::names`.
+    pub demangle_no_hash: bool,
+
+    /// If set, only functions whose demangled name matches are considered.
+    pub filter: Option<Regex>,
+
+    /// If set, functions whose demangled name matches are ignored.
+    pub exclude: Option<Regex>,
+
+    /// Whether to ignore functions from `core`, `alloc` and `std`, according to `crate_name`.
+    /// Instantiations of their generics, such as `core::ptr::drop_in_place<T>`, are ignored too.
+    pub ignore_std: bool,
+
+    /// Whether to ignore functions from `compiler_builtins`, according to `crate_name`. Its
+    /// unmangled exports, such as `memcpy`, can't be told apart from other C functions, so aren't
+    /// ignored.
+    pub ignore_compiler_builtins: bool,
+
+    /// A PDB to read functions from, instead of the symbol table. Only valid for PE binaries. If
+    /// not set, `analyze` uses a PDB next to the binary if there is one.
+    pub pdb: Option<PathBuf>,
+
+    /// A separate debug info file to read functions from, for stripped binaries. If not set,
+    /// `analyze` looks for one in the usual places if the binary has no symbol table.
+    pub debug_file: Option<PathBuf>,
+
+    /// An `nm -S` or `objdump -t` listing to read functions from, instead of the symbol table.
+    pub symbols_file: Option<PathBuf>,
+
+    /// Which of the binary's symbol tables to read functions from.
+    pub symbols: SymbolSource,
+
+    /// Which executable sections to count towards the total size and to look for functions in.
+    pub sections: SectionFilter,
+
+    /// Only look for functions that lie entirely within this range of addresses, e.g. a region of
+    /// firmware placed by a linker script. Only the part of each section within the range is
+    /// counted towards the total size.
+    pub address_range: Option<Range<u64>>,
+
+    /// For Mach-O universal binaries, which architecture's binary to analyse, e.g. `arm64` or
+    /// `aarch64-apple-darwin`. Universal binaries can't be analysed without it.
+    pub arch: Option<String>,
+
+    /// Whether to keep trailing `int3` and `nop` padding and the exact encoding of NOPs when keying
+    /// by instructions. By default, functions that differ only in these are considered identical.
+    pub keep_padding: bool,
+
+    /// Whether to count symbols with the same address and size, such as aliases, as separate
+    /// copies. By default, they're treated as a single function with several names.
+    pub keep_aliases: bool,
+
+    /// Whether to check PLT stubs and IFUNC resolvers and count PLT sections towards the total
+    /// size. By default, they're left out, since they're tiny and often look alike.
+    pub include_plt: bool,
+
+    /// Whether to infer the sizes of functions whose symbols have a size of zero from the address
+    /// of the next symbol or the end of the section. This is always done for Mach-O, whose symbols
+    /// don't have sizes.
+    pub infer_sizes: bool,
+
+    /// Whether to key functions by a 128 bit hash of their normalised instructions rather than the
+    /// instructions themselves, which uses much less memory for huge binaries. Only affects the
+    /// instructions keys.
+    pub hash_keys: bool,
+
+    /// Whether to check that functions with the same hash really have the same instructions when
+    /// `hash_keys` is set. Only copies within the same object, or archive member, are checked.
+    pub verify_hashes: bool,
+
+    /// If set, reports are cached in this directory, so that analysing the same binary again with
+    /// the same options doesn't need to key its functions again. See `cache::default_dir`.
+    pub cache_dir: Option<PathBuf>,
+
+    /// Told how many functions there are to key and when each one is done.
+    pub progress: Option<Arc<dyn Progress>>,
+
+    /// If set, how long each phase takes and how many functions are skipped are recorded here.
+    pub stats: Option<Arc<Stats>>,
+
+    /// What `DuplicationReport::excess_fraction` is relative to.
+    pub denominator: Denominator,
+
+    /// If set, functions are keyed by running this program instead of according to `key`. See the
+    /// `key_cmd` module for how it's run. Results aren't cached, since the program could change.
+    pub key_cmd: Option<PathBuf>,
+}
+
+/// Receives progress updates while functions are being keyed, which can take minutes for huge
+/// binaries.
+pub trait Progress: Send + Sync {
+    /// Called with the number of functions that are about to be keyed. May be called more than
+    /// once, e.g. for each member of an archive.
+    fn add_total(&self, functions: u64);
+
+    /// Called as functions finish being keyed.
+    fn advance(&self, functions: u64);
+}
+
+/// Selects sections by name. A name ending in `*` matches any section whose name starts with the
+/// rest of it.
+#[derive(Clone, Default, Debug)]
+pub struct SectionFilter {
+    /// If non-empty, only sections matching one of these are selected.
+    include: Vec<String>,
+
+    /// Sections matching any of these aren't selected, even if they match `include`.
+    exclude: Vec<String>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq, Debug)]
+pub enum KeyType {
+    /// Group by normalised instruction bytes. For relocatable objects, the names of relocation
+    /// targets are also part of the key.
+    Instructions,
+
+    /// Group by normalised instruction bytes, but with the targets of direct calls and jumps to
+    /// other functions replaced by the target's name without rustc's hash. This groups functions
+    /// that only differ in which copy of an otherwise identical function they call.
+    InstructionsWithCalls,
+
+    /// Group by instructions with registers renamed in the order that they're first used, so that
+    /// functions that only differ in register allocation, e.g. because they were compiled in
+    /// different codegen units, are grouped. ICF can't fold these, only outlining can. For
+    /// architectures other than x86, this is the same as `instructions`.
+    InstructionShape,
+
+    /// Group by decoded instructions, i.e. each instruction's opcode and operands, without
+    /// re-encoding them. Branches within the function are described by which instruction they
+    /// branch to and other addresses are kept as they are. This is faster than `instructions` and
+    /// can't fail to re-encode. For architectures other than x86, this is the same as
+    /// `instructions`.
+    DecodedInstructions,
+
+    /// Key by function name and size.
+    NameAndSize,
+
+    /// Key by function name and size, but drop the hash added by rustc. This may group
+    /// monomorphisations that are fundamentally different, so isn't recommended.
+    NameWithoutRustHash,
+}
+
+/// Which symbol table functions are read from. A PDB, debug file or symbols file that's given
+/// explicitly takes precedence over all of these, but those that are found automatically are only
+/// used with `Auto`.
+#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq, Debug)]
+pub enum SymbolSource {
+    /// The symbol table, falling back to debug files, MiniDebugInfo, unwind tables and then the
+    /// dynamic symbol table when there isn't one.
+    Auto,
+
+    /// Only the symbol table.
+    Symtab,
+
+    /// Only the dynamic symbol table, e.g. because post-processing left the symbol table stale.
+    Dynsym,
+
+    /// Both tables, with functions from the dynamic symbol table only added if no function in the
+    /// symbol table has the same address.
+    Both,
+}
+
+/// What excess bytes are given as a percentage of. Each answers a different question, e.g. how much
+/// of the code is duplicated, versus how much smaller the download could be.
+#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq, Debug, Default)]
+pub enum Denominator {
+    /// The total size of executable sections.
+    #[default]
+    Text,
+
+    /// The size of the input on disk, including data, symbols and debug info.
+    FileSize,
+
+    /// The total size in memory of loadable segments, i.e. roughly how much of the program's
+    /// address space is duplicated code. Only linked binaries have segments.
+    LoadedSegments,
+}
+
+impl Denominator {
+    /// The name that's used in JSON output, which matches the command line.
+    pub fn id(self) -> &'static str {
+        match self {
+            Denominator::Text => "text",
+            Denominator::FileSize => "file-size",
+            Denominator::LoadedSegments => "loaded-segments",
+        }
+    }
+
+    /// Describes the size, for saying what a percentage is of.
+    pub fn description(self) -> &'static str {
+        match self {
+            Denominator::Text => "executable code",
+            Denominator::FileSize => "file size",
+            Denominator::LoadedSegments => "loaded segments",
+        }
+    }
+}
+
+/// The result of analysing a binary.
+#[derive(Serialize, Deserialize)]
+pub struct DuplicationReport {
+    /// Total size of all executable sections.
+    pub text_size: u64,
+
+    /// The size of the input on disk. For a directory or thin archive, this is the total of the
+    /// objects that were analysed.
+    pub file_size: u64,
+
+    /// The total size in memory of loadable segments. This is zero for relocatable objects.
+    pub loaded_size: u64,
+
+    /// What `excess_fraction` is relative to. This is taken from `Options::denominator`.
+    #[serde(skip)]
+    pub denominator: Denominator,
+
+    /// All groups of functions that were considered identical, including groups with only a
+    /// single copy.
+    pub groups: Vec<DuplicateGroup>,
+
+    /// The number of functions that were skipped due to `filter`, `exclude` or the crates that
+    /// they're from.
+    pub filtered_functions: u64,
+
+    /// The total size of functions that were skipped due to `filter`, `exclude` or the crates that
+    /// they're from.
+    pub filtered_bytes: u64,
+
+    /// The number of functions whose symbols had no size, so whose size was inferred from the
+    /// symbols that follow them.
+    pub inferred_sizes: u64,
+
+    /// The number of bytes of executable code that are in functions that were checked for
+    /// duplication. Bytes that are in more than one function, e.g. due to aliases, count once. Code
+    /// without symbols, such as stripped static functions, isn't covered.
+    pub checked_bytes: u64,
+
+    /// Whether functions came from the dynamic symbol table, because there was no symbol table or
+    /// debug info. Only exported functions are in it, so duplication is understated.
+    pub dynamic_symbols_only: bool,
+
+    /// The number of functions whose bounds came from unwind tables, because no symbols had sizes.
+    /// Those without a symbol at the same address have no name.
+    pub unwind_functions: u64,
+
+    /// The size of PLT sections and IFUNC resolvers, which were left out because
+    /// `Options::include_plt` wasn't set. PLT sections aren't counted in `text_size`.
+    pub plt_bytes: u64,
+
+    /// Functions that were left out because their instructions couldn't be normalised, e.g.
+    /// because they contain data that doesn't decode. Sorted by member, then address.
+    pub key_failures: Vec<KeyFailure>,
+
+    /// The size of each executable section, by name. Sections with the same name in different
+    /// archive members are combined.
+    pub section_sizes: HashMap<String, u64>,
+}
+
+/// A function whose key couldn't be built, so it wasn't checked for duplication.
+#[derive(Serialize, Deserialize)]
+pub struct KeyFailure {
+    pub name: Option<String>,
+    pub address: u64,
+    pub size: u64,
+
+    /// The archive member or object file that the function is in, if there's more than one.
+    pub member: Option<String>,
+
+    /// Why the key couldn't be built.
+    pub error: String,
+}
+
+/// The size of an executable section and the excess bytes from copies of functions in it.
+pub struct SectionStats {
+    /// `None` for copies whose section isn't known.
+    pub name: Option<String>,
+
+    pub size: u64,
+    pub excess_bytes: u64,
+}
+
+/// Duplicate groups whose function size is in `min_size..max_size`.
+pub struct SizeBucket {
+    pub min_size: u64,
+
+    /// `None` for the last bucket, which has no upper bound.
+    pub max_size: Option<u64>,
+
+    pub groups: u64,
+    pub excess_bytes: u64,
+}
+
+/// Upper bounds of all but the last size bucket.
+const SIZE_BUCKET_BOUNDS: [u64; 6] = [64, 256, 1024, 4096, 16384, 65536];
+
+/// Functions that have the same name once generic arguments are removed, e.g. all instantiations
+/// of `core::ptr::drop_in_place`.
+pub struct GenericRoot {
+    pub name: String,
+
+    /// The number of distinct groups with this root.
+    pub instantiations: u64,
+
+    /// The total number of functions with this root.
+    pub copies: u64,
+
+    /// The total size of all functions with this root.
+    pub total_bytes: u64,
+
+    /// The bytes that would be saved by removing exact duplicates with this root.
+    pub excess_bytes: u64,
+}
+
+/// Excess bytes of the duplicates in a module, including those in modules inside it.
+pub struct ModuleStats {
+    /// The last segment of the module's path, e.g. `vec` for `alloc::vec`. For the top level, this
+    /// is the crate, or `None` for functions without a Rust path.
+    pub name: Option<String>,
+
+    pub excess_bytes: u64,
+
+    /// The modules inside this one, with the most excess bytes first.
+    pub children: Vec<ModuleStats>,
+}
+
+/// The number of hex digits in `DuplicateGroup::id`.
+pub const GROUP_ID_LEN: usize = 12;
+
+/// A group of functions that were considered identical according to the chosen key.
+#[derive(Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub function_size: u64,
+
+    /// The number of functions in the group.
+    pub copies: u64,
+
+    /// The (possibly demangled) names of functions in the group and how many times each occurred.
+    pub names: BTreeMap<String, u32>,
+
+    /// Information about each copy.
+    pub instances: Vec<FunctionInstance>,
+
+    /// A hash of what the group's functions have in common according to the key, described so that
+    /// it doesn't depend on where functions are. It's computed with a fixed algorithm, so it's the
+    /// same in every run with the same key type, and can be used to refer to the group.
+    pub key_hash: u64,
+}
+
+/// A single copy of a function.
+#[derive(Serialize, Deserialize)]
+pub struct FunctionInstance {
+    pub address: u64,
+
+    /// The symbol name of this copy, as it appears in the binary. If it has several aliases, this
+    /// is the first.
+    pub name: Option<String>,
+
+    /// The name of the section that contains this copy.
+    pub section: Option<String>,
+
+    /// The input file that contributed this copy. This is the archive member when the input is an
+    /// archive, or comes from a linker map.
+    pub member: Option<String>,
+
+    /// The linkage of this copy's symbol. If it has several, e.g. a local symbol and a global
+    /// alias, this is the most visible.
+    pub linkage: Linkage,
+
+    /// The source or object file that this copy came from, according to the `STT_FILE` symbols of
+    /// an ELF symbol table. Only known for local symbols.
+    pub file: Option<String>,
+
+    /// In relocatable objects, the COMDAT group that this copy is in, or for Mach-O, the name of
+    /// its weak definition. The linker keeps only one copy with each name, from whichever object
+    /// it sees first.
+    pub comdat: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            key: KeyType::Instructions,
+            demangle: false,
+            demangle_no_hash: false,
+            filter: None,
+            exclude: None,
+            ignore_std: false,
+            ignore_compiler_builtins: false,
+            pdb: None,
+            debug_file: None,
+            symbols_file: None,
+            symbols: SymbolSource::Auto,
+            sections: SectionFilter::default(),
+            address_range: None,
+            arch: None,
+            keep_padding: false,
+            keep_aliases: false,
+            include_plt: false,
+            infer_sizes: false,
+            hash_keys: false,
+            verify_hashes: false,
+            cache_dir: None,
+            progress: None,
+            stats: None,
+            denominator: Denominator::Text,
+            key_cmd: None,
+        }
+    }
+}
+
+impl SectionFilter {
+    pub fn matches(&self, name: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        };
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    /// Returns whether every section is selected.
+    pub fn is_all(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+}
+
+/// Parses a comma-separated list of section names, where names prefixed with `!` are excluded, e.g.
+/// `.text*,!.text.unlikely*`.
+impl FromStr for SectionFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut filter = SectionFilter::default();
+        for name in s.split(',').map(str::trim) {
+            match name.strip_prefix('!') {
+                Some("") => bail!("Empty section name in `{s}`"),
+                Some(name) => filter.exclude.push(name.to_owned()),
+                None if name.is_empty() => bail!("Empty section name in `{s}`"),
+                None => filter.include.push(name.to_owned()),
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl DuplicationReport {
+    /// Returns groups that have more than one copy.
+    pub fn duplicates(&self) -> impl Iterator<Item = &DuplicateGroup> {
+        self.groups.iter().filter(|g| g.copies > 1)
+    }
+
+    /// The total size of functions in `key_failures`.
+    pub fn key_failure_bytes(&self) -> u64 {
+        self.key_failures.iter().map(|failure| failure.size).sum()
+    }
+
+    /// Total bytes that would be saved if all duplicates were removed.
+    pub fn excess_bytes(&self) -> u64 {
+        self.groups.iter().map(|g| g.excess_bytes()).sum()
+    }
+
+    /// The bytes that removing all duplicates would save if every function started on an
+    /// `alignment` byte boundary. This differs from `excess_bytes`, since each copy that's removed
+    /// also takes the padding after it, so the functions that follow move by its size rounded up to
+    /// the alignment.
+    pub fn aligned_excess_bytes(&self, alignment: u64) -> u64 {
+        self.groups
+            .iter()
+            .map(|g| g.copies.saturating_sub(1) * g.function_size.next_multiple_of(alignment))
+            .sum()
+    }
+
+    /// `checked_bytes` as a fraction of `text_size`. When this is low, duplication is understated,
+    /// since duplicates among the code that wasn't checked aren't found.
+    pub fn coverage_fraction(&self) -> f64 {
+        if self.text_size == 0 {
+            return 0.0;
+        }
+        self.checked_bytes as f64 / self.text_size as f64
+    }
+
+    /// Excess bytes as a fraction of `denominator_size`.
+    pub fn excess_fraction(&self) -> f64 {
+        self.fraction_of_denominator(self.excess_bytes())
+    }
+
+    /// `bytes` as a fraction of `denominator_size`, or zero if that's zero, e.g. because
+    /// `Options::address_range` didn't cover any code.
+    pub fn fraction_of_denominator(&self, bytes: u64) -> f64 {
+        let size = self.denominator_size();
+        if size == 0 {
+            return 0.0;
+        }
+        bytes as f64 / size as f64
+    }
+
+    /// The size that percentages are of, according to `denominator`.
+    pub fn denominator_size(&self) -> u64 {
+        match self.denominator {
+            Denominator::Text => self.text_size,
+            Denominator::FileSize => self.file_size,
+            Denominator::LoadedSegments => self.loaded_size,
+        }
+    }
+
+    /// The number of distinct functions that have at least one duplicate.
+    pub fn duplicated_functions(&self) -> u64 {
+        self.duplicates().count() as u64
+    }
+
+    /// The number of copies beyond the first across all groups.
+    pub fn excess_instances(&self) -> u64 {
+        self.groups.iter().map(|g| g.copies.saturating_sub(1)).sum()
+    }
+
+    /// Returns the excess bytes from copies with the same COMDAT name, which the linker will
+    /// deduplicate anyway. The rest of the excess bytes remain once objects are linked.
+    pub fn comdat_excess_bytes(&self) -> u64 {
+        self.duplicates()
+            .map(|group| {
+                let mut copies_by_comdat: HashMap<&str, u64> = HashMap::new();
+                for comdat in group.instances.iter().filter_map(|i| i.comdat.as_deref()) {
+                    *copies_by_comdat.entry(comdat).or_default() += 1;
+                }
+                let folded: u64 = copies_by_comdat.values().map(|copies| copies - 1).sum();
+                folded * group.function_size
+            })
+            .sum()
+    }
+
+    /// Returns excess bytes attributed to each crate, largest first. Each group is attributed to
+    /// the crate of its representative name. Groups whose name doesn't look like a Rust path are
+    /// attributed to `None`.
+    pub fn excess_bytes_by_crate(&self) -> Vec<(Option<String>, u64)> {
+        let mut by_crate: HashMap<Option<String>, u64> = HashMap::new();
+        for group in self.duplicates() {
+            let crate_name = group.representative_name().and_then(crate_name);
+            *by_crate.entry(crate_name).or_default() += group.excess_bytes();
+        }
+        let mut by_crate = by_crate.into_iter().collect::<Vec<_>>();
+        by_crate.sort_by(|(a_name, a_bytes), (b_name, b_bytes)| {
+            b_bytes.cmp(a_bytes).then(a_name.cmp(b_name))
+        });
+        by_crate
+    }
+
+    /// Returns excess bytes attributed to each crate and the modules inside it, down to `depth`
+    /// segments of their paths, with the most excess bytes first at each level. A depth of 1 is
+    /// equivalent to `excess_bytes_by_crate`. For methods, the self type counts as a module. Each
+    /// group is attributed to the module of its representative name.
+    pub fn excess_bytes_by_module(&self, depth: usize) -> Vec<ModuleStats> {
+        #[derive(Default)]
+        struct Node {
+            excess_bytes: u64,
+            children: HashMap<Option<String>, Node>,
+        }
+
+        fn into_stats(children: HashMap<Option<String>, Node>) -> Vec<ModuleStats> {
+            let mut stats = children
+                .into_iter()
+                .map(|(name, node)| ModuleStats {
+                    name,
+                    excess_bytes: node.excess_bytes,
+                    children: into_stats(node.children),
+                })
+                .collect::<Vec<_>>();
+            stats.sort_by(|a, b| {
+                b.excess_bytes
+                    .cmp(&a.excess_bytes)
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+            stats
+        }
+
+        let mut root = Node::default();
+        for group in self.duplicates() {
+            // The last segment of the path is the function itself.
+            let mut path = group
+                .representative_name()
+                .map(symbol_path)
+                .unwrap_or_default();
+            path.pop();
+            // With `--demangle`, v0 crate names are followed by a disambiguator, e.g. `core[1a2b]`.
+            let mut segments = path
+                .into_iter()
+                .map(|segment| match segment.split_once('[') {
+                    Some((name, _)) => Some(name.to_owned()),
+                    None => Some(segment),
+                })
+                .collect::<Vec<_>>();
+            if segments.is_empty() {
+                segments.push(None);
+            }
+            let mut node = &mut root;
+            for segment in segments.into_iter().take(depth) {
+                node = node.children.entry(segment).or_default();
+                node.excess_bytes += group.excess_bytes();
+            }
+        }
+        into_stats(root.children)
+    }
+
+    /// Returns the size and excess bytes of each executable section, with the most excess bytes
+    /// first. In each group, the copy with the lowest address is considered to be the original and
+    /// the others are attributed to their sections as excess.
+    pub fn excess_bytes_by_section(&self) -> Vec<SectionStats> {
+        let mut by_section: HashMap<Option<&str>, SectionStats> = self
+            .section_sizes
+            .iter()
+            .map(|(name, size)| {
+                let stats = SectionStats {
+                    name: Some(name.clone()),
+                    size: *size,
+                    excess_bytes: 0,
+                };
+                (Some(name.as_str()), stats)
+            })
+            .collect();
+        for group in self.duplicates() {
+            let mut instances = group.instances.iter().collect::<Vec<_>>();
+            instances.sort_by_key(|instance| instance.address);
+            for instance in instances.iter().skip(1) {
+                let name = instance.section.as_deref();
+                by_section
+                    .entry(name)
+                    .or_insert_with(|| SectionStats {
+                        name: name.map(|name| name.to_owned()),
+                        size: 0,
+                        excess_bytes: 0,
+                    })
+                    .excess_bytes += group.function_size;
+            }
+        }
+        let mut sections = by_section.into_values().collect::<Vec<_>>();
+        sections.sort_by(|a, b| {
+            (b.excess_bytes, b.size, &a.name).cmp(&(a.excess_bytes, a.size, &b.name))
+        });
+        sections
+    }
+
+    /// Returns excess bytes attributed to each category of function, largest first. Each group is
+    /// classified by its representative name. Categories without duplicates are omitted.
+    pub fn excess_bytes_by_category(&self) -> Vec<(Category, u64)> {
+        let mut by_category: HashMap<Category, u64> = HashMap::new();
+        for group in self.duplicates() {
+            let category = group
+                .representative_name()
+                .map_or(Category::UserCode, category::classify);
+            *by_category.entry(category).or_default() += group.excess_bytes();
+        }
+        let mut by_category = by_category.into_iter().collect::<Vec<_>>();
+        by_category.sort_by_key(|(category, bytes)| (std::cmp::Reverse(*bytes), *category));
+        by_category
+    }
+
+    /// Returns excess bytes attributed to each linkage, largest first. As for sections, the copy
+    /// with the lowest address in each group is considered to be the original and the others are
+    /// attributed to their own linkage.
+    pub fn excess_bytes_by_linkage(&self) -> Vec<(Linkage, u64)> {
+        let mut by_linkage: HashMap<Linkage, u64> = HashMap::new();
+        for group in self.duplicates() {
+            let mut instances = group.instances.iter().collect::<Vec<_>>();
+            instances.sort_by_key(|instance| instance.address);
+            for instance in instances.iter().skip(1) {
+                *by_linkage.entry(instance.linkage).or_default() += group.function_size;
+            }
+        }
+        let mut by_linkage = by_linkage.into_iter().collect::<Vec<_>>();
+        by_linkage.sort_by_key(|(linkage, bytes)| (std::cmp::Reverse(*bytes), *linkage));
+        by_linkage
+    }
+
+    /// Returns duplicate groups bucketed by function size, smallest first. Every bucket is
+    /// included, even if empty.
+    pub fn excess_bytes_by_size(&self) -> Vec<SizeBucket> {
+        let mut buckets = std::iter::once(0)
+            .chain(SIZE_BUCKET_BOUNDS)
+            .zip(SIZE_BUCKET_BOUNDS.map(Some).into_iter().chain([None]))
+            .map(|(min_size, max_size)| SizeBucket {
+                min_size,
+                max_size,
+                groups: 0,
+                excess_bytes: 0,
+            })
+            .collect::<Vec<_>>();
+        for group in self.duplicates() {
+            let index = SIZE_BUCKET_BOUNDS.partition_point(|bound| *bound <= group.function_size);
+            buckets[index].groups += 1;
+            buckets[index].excess_bytes += group.excess_bytes();
+        }
+        buckets
+    }
+
+    /// Returns the functions of all groups aggregated by generic root, with the largest total size
+    /// first. Each group is attributed to the root of its representative name.
+    pub fn bytes_by_generic(&self) -> Vec<GenericRoot> {
+        group_by_generic_root(&self.groups)
+    }
+}
+
+impl DuplicateGroup {
+    pub fn excess_bytes(&self) -> u64 {
+        self.copies.saturating_sub(1) * self.function_size
+    }
+
+    /// Returns the lexicographically smallest name in the group, which is used to identify the
+    /// group when comparing binaries.
+    pub fn representative_name(&self) -> Option<&str> {
+        self.names.keys().next().map(|name| name.as_str())
+    }
+
+    /// Returns a short identifier for the group, the first 12 hex digits of `key_hash`. For the
+    /// instruction keys, references to other functions are described by the function's name
+    /// without rustc's hash and other references outside the function are left out, so this stays
+    /// the same across builds for as long as the functions and what they call do. It can be used in
+    /// issues and ignore files to refer to the group. With `name-and-size`, rustc's hash is part of
+    /// the ID, so it changes whenever the hash does. IDs are unique within a report: groups that
+    /// only differ in which data they reference, or in absolute addresses that aren't IP-relative,
+    /// would hash the same, so all but one of them get a hash that includes their name.
+    pub fn id(&self) -> String {
+        format!("{:016x}", self.key_hash)[..GROUP_ID_LEN].to_owned()
+    }
+
+    /// Returns the smallest of the group's names once demangled without rustc's hash. Unlike
+    /// `representative_name`, this doesn't change when a rebuild changes the hashes.
+    pub fn demangled_name(&self) -> Option<String> {
+        self.names
+            .keys()
+            .map(|name| demangle::demangle(name, true).into_owned())
+            .min()
+    }
+}
+
+/// Returns the name of the crate that a symbol comes from, which is the first segment of its
+/// demangled path. For trait impls such as `<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop`,
+/// that's the crate of the self type. `symbol_name` may be either mangled or already demangled.
+pub fn crate_name(symbol_name: &str) -> Option<String> {
+    let demangled = demangle::demangle(symbol_name, true);
+    let path = strip_type_prefixes(&demangled);
+    let end = path
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(path.len());
+    if end == 0 || !path[end..].starts_with("::") {
+        return None;
+    }
+    Some(path[..end].to_owned())
+}
+
+/// Strips the punctuation and keywords that can come before the path of a type, e.g. `<&dyn `.
+fn strip_type_prefixes(mut path: &str) -> &str {
+    loop {
+        let trimmed = path
+            .trim_start_matches(['<', '&', '(', '[', '*'])
+            .trim_start_matches("const ")
+            .trim_start_matches("mut ")
+            .trim_start_matches("dyn ");
+        if trimmed == path {
+            return path;
+        }
+        path = trimmed;
+    }
+}
+
+/// Returns where a symbol sits in its crate, outermost first, ending with its demangled name
+/// without rustc's hash. The crate and modules come first and for methods, the path of the type
+/// that they're implemented for is used. For example `<alloc::vec::Vec<u8> as
+/// core::ops::drop::Drop>::drop` becomes `alloc`, `vec`, `Vec`, then the full name. Symbols that
+/// don't have a Rust path, e.g. C functions, just have their name.
+pub fn symbol_path(symbol_name: &str) -> Vec<String> {
+    let name = demangle::demangle(symbol_name, true).into_owned();
+    let root = generic_root(symbol_name);
+    let container = if root.starts_with('<') {
+        // The self type of `<T as Trait>::method` or `<T>::method`.
+        strip_type_prefixes(&root)
+            .split([' ', '>', ')', ']', ';', ','])
+            .next()
+            .filter(|self_type| self_type.contains("::"))
+    } else {
+        root.rsplit_once("::").map(|(container, _)| container)
+    };
+    let Some(container) = container else {
+        return vec![name];
+    };
+    let mut path = container
+        .split("::")
+        .map(|segment| segment.to_owned())
+        .collect::<Vec<_>>();
+    path.push(name);
+    path
+}
+
+/// Totals `groups` by the generic function that each is an instantiation of, largest first.
+/// Groups without a name are left out.
+pub fn group_by_generic_root<'a>(
+    groups: impl IntoIterator<Item = &'a DuplicateGroup>,
+) -> Vec<GenericRoot> {
+    let mut by_root: HashMap<String, GenericRoot> = HashMap::new();
+    for group in groups {
+        let Some(name) = group.representative_name() else {
+            continue;
+        };
+        let name = generic_root(name);
+        let root = by_root.entry(name.clone()).or_insert_with(|| GenericRoot {
+            name,
+            instantiations: 0,
+            copies: 0,
+            total_bytes: 0,
+            excess_bytes: 0,
+        });
+        root.instantiations += 1;
+        root.copies += group.copies;
+        root.total_bytes += group.copies * group.function_size;
+        root.excess_bytes += group.excess_bytes();
+    }
+    let mut roots = by_root.into_values().collect::<Vec<_>>();
+    roots.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then(a.name.cmp(&b.name)));
+    roots
+}
+
+/// Returns `symbol_name`, demangled and without rustc's hash, with all generic arguments removed.
+/// For example `core::ptr::drop_in_place<alloc::string::String>` becomes `core::ptr::drop_in_place`
+/// and `<alloc::vec::Vec<u8> as core::ops::drop::Drop>::drop` becomes
+/// `<alloc::vec::Vec as core::ops::drop::Drop>::drop`.
+pub fn generic_root(symbol_name: &str) -> String {
+    let demangled = demangle::demangle(symbol_name, true);
+    let mut root = String::with_capacity(demangled.len());
+    let mut depth = 0;
+    let mut previous = None;
+    for c in demangled.chars() {
+        if depth > 0 {
+            match c {
+                '<' => depth += 1,
+                // The `>` of `->` in e.g. `Fn() -> u8` doesn't close anything.
+                '>' if previous != Some('-') => depth -= 1,
+                _ => {}
+            }
+        } else if c == '<' && previous.is_some_and(|p: char| p.is_alphanumeric() || p == '_') {
+            depth = 1;
+        } else if c == '<' && root.ends_with("::") {
+            // A turbofish, e.g. `foo::<T>`.
+            root.truncate(root.len() - 2);
+            depth = 1;
+        } else {
+            root.push(c);
+        }
+        previous = Some(c);
+    }
+    root
+}
+
+/// Reads the binary at `path` and groups its functions according to `options`. If `path` is a
+/// directory, the functions of all object files and archives under it are grouped together, as are
+/// those of the members of a thin archive. These aren't cached.
+pub fn analyze(path: &Path, options: &Options) -> Result<DuplicationReport> {
+    if path.is_dir() {
+        let files = input_files::read_directory(path)?;
+        return analyze_input_files(&files, options);
+    }
+    let data = timed(options, Phase::Read, || read_input(path))?;
+    if input_files::is_thin_archive(&data) {
+        let name = path.display().to_string();
+        let files = input_files::read_thin_archive(path, &name, &data)?;
+        return analyze_input_files(&files, options);
+    }
+    let data = universal::select(&data, options.arch.as_deref())?;
+    let mut options = options.clone();
+    if options.symbols == SymbolSource::Auto {
+        if options.pdb.is_none() {
+            options.pdb = sibling_pdb(path, data);
+        }
+        if options.debug_file.is_none() {
+            options.debug_file = debug_file::find(path, data, None)?;
+        }
+    }
+    analyze_data(data, &options)
+}
+
+fn analyze_input_files(files: &[InputFile], options: &Options) -> Result<DuplicationReport> {
+    let files = files
+        .iter()
+        .map(|file| (Some(file.name.as_str()), &*file.data))
+        .collect::<Vec<_>>();
+    analyze_files(&files, options)
+}
+
+/// The contents of an input file.
+pub enum InputData {
+    Mapped(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+
+impl std::ops::Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            InputData::Mapped(mmap) => mmap,
+            InputData::Read(data) => data,
+        }
+    }
+}
+
+/// Memory-maps the file at `path`, so that huge binaries don't need to fit in memory. Falls back to
+/// reading the file if it can't be mapped, e.g. because it's a pipe. Compressed and wrapped
+/// binaries, such as kernel images, are unpacked.
+pub fn read_input(path: &Path) -> Result<InputData> {
+    let file = std::fs::File::open(path)?;
+    // Safety: We assume that the file isn't modified while we're analysing it. If it is, we may
+    // produce garbage results, as we could if it were modified while we were reading it.
+    let data = match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(mmap) => InputData::Mapped(mmap),
+        Err(_) => InputData::Read(std::fs::read(path)?),
+    };
+    match unpack::unpack(&data)? {
+        Some(unpacked) => Ok(InputData::Read(unpacked)),
+        None => Ok(data),
+    }
+}
+
+/// Windows binaries rarely have a usable symbol table, but often have a PDB alongside. Returns the
+/// path of that PDB if `data`, the contents of `path`, is a PE binary and the PDB exists.
+pub fn sibling_pdb(path: &Path, data: &[u8]) -> Option<PathBuf> {
+    let is_pe = matches!(FileKind::parse(data), Ok(FileKind::Pe32 | FileKind::Pe64));
+    let pdb = path.with_extension("pdb");
+    (is_pe && pdb.exists()).then_some(pdb)
+}
+
+/// Groups the functions of an already loaded binary according to `options`. If `data` is an
+/// archive, functions from all members are grouped together.
+pub fn analyze_data(data: &[u8], options: &Options) -> Result<DuplicationReport> {
+    let data = universal::select(data, options.arch.as_deref())?;
+    let Some(cache_dir) = options
+        .cache_dir
+        .as_ref()
+        .filter(|_| options.key_cmd.is_none())
+    else {
+        return analyze_uncached(data, options);
+    };
+    let entry = cache::entry_path(cache_dir, data, options)?;
+    if let Some(mut report) = cache::load(&entry) {
+        tracing::info!(entry = %entry.display(), "Using cached report");
+        set_denominator(&mut report, options)?;
+        return Ok(report);
+    }
+    let report = analyze_uncached(data, options)?;
+    // Failing to cache the report, e.g. because the cache directory is read-only, only means that
+    // we'll need to analyse the binary again next time.
+    if let Err(error) = cache::store(&entry, &report) {
+        tracing::debug!(entry = %entry.display(), "Failed to cache report: {error:#}");
+    }
+    Ok(report)
+}
+
+fn analyze_uncached(data: &[u8], options: &Options) -> Result<DuplicationReport> {
+    analyze_files(&[(None, data)], options)
+}
+
+fn analyze_files(files: &[(Option<&str>, &[u8])], options: &Options) -> Result<DuplicationReport> {
+    if options.key_cmd.is_some() {
+        return process::<CommandKey>(files, options);
+    }
+    match options.key {
+        KeyType::NameAndSize => process::<NameAndSizeKey>(files, options),
+        KeyType::NameWithoutRustHash => process::<NameAndSizeKey>(files, options),
+        KeyType::Instructions if options.hash_keys => {
+            process::<HashedKey<InstructionsKey>>(files, options)
+        }
+        KeyType::Instructions => process::<InstructionsKey>(files, options),
+        KeyType::InstructionsWithCalls if options.hash_keys => {
+            process::<HashedKey<InstructionsWithCallsKey>>(files, options)
+        }
+        KeyType::InstructionsWithCalls => process::<InstructionsWithCallsKey>(files, options),
+        KeyType::InstructionShape if options.hash_keys => {
+            process::<HashedKey<InstructionShapeKey>>(files, options)
+        }
+        KeyType::InstructionShape => process::<InstructionShapeKey>(files, options),
+        KeyType::DecodedInstructions if options.hash_keys => {
+            process::<HashedKey<DecodedInstructionsKey>>(files, options)
+        }
+        KeyType::DecodedInstructions => process::<DecodedInstructionsKey>(files, options),
+    }
+}
+
+trait Key: Hash + Eq + Sized + Send + Sync {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self>;
+
+    /// Returns the `DuplicateGroup::key_hash` of the group that `function`, whose key is `self`,
+    /// starts. Keys for instructions describe references outside the function by address, so
+    /// these instead hash a description of the code that doesn't depend on where anything is.
+    fn stable_hash(&self, function: &Function, inputs: &KeyBuilderInputs) -> u64 {
+        stable_code_hash(function, inputs)
+    }
+
+    /// Called with the key of each function body in an object if `Options::verify_hashes` is set.
+    /// Keys that are only hashes of the function's contents should check that functions with the
+    /// same hash really are the same.
+    fn verify(
+        keys: Vec<Option<Self>>,
+        _bodies: &[&[&Function]],
+        _inputs: &KeyBuilderInputs,
+    ) -> Vec<Option<Self>> {
+        keys
+    }
+}
+
+/// A function that we might check for duplication. Functions usually come from the symbol table,
+/// but for PE binaries, they can come from a PDB and for stripped binaries, from a separate debug
+/// info file or a symbols file.
+struct Function<'data> {
+    name: Option<Cow<'data, str>>,
+    address: u64,
+    size: u64,
+    section_index: Option<SectionIndex>,
+
+    /// Whether `size` was inferred because the symbol's size was zero.
+    size_inferred: bool,
+
+    /// Whether this is a 32 bit ARM function that uses the Thumb instruction set.
+    thumb: bool,
+
+    linkage: Linkage,
+
+    /// The file that the function came from, according to an `STT_FILE` symbol.
+    file: Option<Cow<'data, str>>,
+}
+
+/// State that's built up as we process one or more objects.
+struct Accumulator<K> {
+    symbols: HashMap<K, DuplicateGroup>,
+    section_sizes: HashMap<String, u64>,
+    file_size: u64,
+    loaded_size: u64,
+    filtered_functions: u64,
+    filtered_bytes: u64,
+    inferred_sizes: u64,
+    checked_bytes: u64,
+    dynamic_symbols_only: bool,
+    unwind_functions: u64,
+    plt_bytes: u64,
+    had_symbols: bool,
+    key_failures: Vec<KeyFailure>,
+
+    /// The program that keys functions if `Options::key_cmd` is set. It's shared by all objects.
+    key_command: Option<Mutex<KeyCommand>>,
+}
+
+/// Groups the functions of each of `files`. Each file is an object or an archive, optionally with a
+/// name, which is recorded as the member of each function instance.
+fn process<K: Key>(
+    files: &[(Option<&str>, &[u8])],
+    options: &Options,
+) -> Result<DuplicationReport> {
+    let start = std::time::Instant::now();
+    let mut acc = Accumulator::<K> {
+        symbols: HashMap::new(),
+        section_sizes: HashMap::new(),
+        file_size: 0,
+        loaded_size: 0,
+        filtered_functions: 0,
+        filtered_bytes: 0,
+        inferred_sizes: 0,
+        checked_bytes: 0,
+        dynamic_symbols_only: false,
+        unwind_functions: 0,
+        plt_bytes: 0,
+        had_symbols: false,
+        key_failures: Vec::new(),
+        key_command: options
+            .key_cmd
+            .as_deref()
+            .map(KeyCommand::spawn)
+            .transpose()?
+            .map(Mutex::new),
+    };
+
+    for &(file_name, data) in files {
+        acc.file_size += data.len() as u64;
+        if FileKind::parse(data)? == FileKind::Archive {
+            let archive = timed(options, Phase::Parse, || ArchiveFile::parse(data))?;
+            for member in archive.members() {
+                let member = member?;
+                let member_name = String::from_utf8_lossy(member.name()).into_owned();
+                let member_name = match file_name {
+                    Some(file_name) => format!("{file_name}({member_name})"),
+                    None => member_name,
+                };
+                // Archives can contain things other than objects, e.g. the metadata in an rlib, so
+                // we skip any members that we can't parse.
+                let Ok(object) = timed(options, Phase::Parse, || {
+                    member.data(data).and_then(object::File::parse)
+                }) else {
+                    continue;
+                };
+                process_object(&object, Some(&member_name), options, &mut acc)?;
+            }
+        } else {
+            let object = timed(options, Phase::Parse, || object::File::parse(data))?;
+            process_object(&object, file_name, options, &mut acc)?;
+        }
+    }
+    if let Some(key_command) = acc.key_command.take() {
+        key_command.into_inner().unwrap().finish()?;
+    }
+
+    let considered: u64 = acc.symbols.values().map(|info| info.copies).sum();
+    if considered == 0 {
+        if !acc.had_symbols {
+            bail!("Binary has no symbol table");
+        }
+        if acc.filtered_functions > 0 {
+            bail!("All functions were excluded by filters");
+        }
+        if let Some(failure) = acc.key_failures.first() {
+            bail!(
+                "No functions could be keyed, e.g. `{}`: {}",
+                failure.name.as_deref().unwrap_or("<unknown>"),
+                failure.error
+            );
+        }
+        bail!(
+            "No functions were checked for duplication, symbols may have zero sizes. \
+             Try `--infer-sizes`"
+        );
+    }
+
+    // Groups are collected from a `HashMap`, so they're sorted to make output the same every run.
+    let groups = timed(options, Phase::Aggregation, || {
+        let mut groups = acc.symbols.into_values().collect::<Vec<_>>();
+        for group in &mut groups {
+            group
+                .instances
+                .sort_by(|a, b| (&a.member, a.address).cmp(&(&b.member, b.address)));
+        }
+        groups.sort_by(compare_groups);
+        make_ids_unique(&mut groups);
+        groups
+    });
+    acc.key_failures
+        .sort_by(|a, b| (&a.member, a.address).cmp(&(&b.member, b.address)));
+    let mut report = DuplicationReport {
+        text_size: acc.section_sizes.values().sum(),
+        file_size: acc.file_size,
+        loaded_size: acc.loaded_size,
+        denominator: Denominator::Text,
+        groups,
+        filtered_functions: acc.filtered_functions,
+        filtered_bytes: acc.filtered_bytes,
+        inferred_sizes: acc.inferred_sizes,
+        checked_bytes: acc.checked_bytes,
+        dynamic_symbols_only: acc.dynamic_symbols_only,
+        unwind_functions: acc.unwind_functions,
+        plt_bytes: acc.plt_bytes,
+        key_failures: acc.key_failures,
+        section_sizes: acc.section_sizes,
+    };
+    set_denominator(&mut report, options)?;
+    tracing::info!(
+        files = files.len(),
+        elapsed = ?start.elapsed(),
+        "Checked {} functions",
+        report.groups.iter().map(|group| group.copies).sum::<u64>()
+    );
+    Ok(report)
+}
+
+/// Sets what `report`'s percentages are of. This is done separately from the analysis, so that
+/// cached reports can be used with any denominator.
+fn set_denominator(report: &mut DuplicationReport, options: &Options) -> Result {
+    report.denominator = options.denominator;
+    if report.denominator == Denominator::LoadedSegments && report.loaded_size == 0 {
+        bail!(
+            "`--denominator loaded-segments` needs a linked binary, since objects have no segments"
+        );
+    }
+    Ok(())
+}
+
+/// Orders groups by size, then name, then where their first copy is.
+fn compare_groups(a: &DuplicateGroup, b: &DuplicateGroup) -> std::cmp::Ordering {
+    let first = |group: &DuplicateGroup| -> Option<(Option<String>, u64)> {
+        let instance = group.instances.first()?;
+        Some((instance.member.clone(), instance.address))
+    };
+    a.function_size
+        .cmp(&b.function_size)
+        .then_with(|| a.representative_name().cmp(&b.representative_name()))
+        .then_with(|| first(a).cmp(&first(b)))
+}
+
+/// Changes the `key_hash` of groups whose ID is the same as another group's, e.g. because they
+/// only differ in which data they reference, so that each ID refers to one group. Of the groups that
+/// share an ID, the one with the smallest demangled name keeps it. The others get a hash of the ID
+/// and their demangled name, so that their IDs don't depend on where they are in the binary either.
+fn make_ids_unique(groups: &mut [DuplicateGroup]) {
+    let mut by_id: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, group) in groups.iter().enumerate() {
+        by_id.entry(group.id()).or_default().push(index);
+    }
+    let mut ids = by_id.keys().cloned().collect::<HashSet<_>>();
+    let mut collisions = by_id
+        .into_values()
+        .filter(|indices| indices.len() > 1)
+        .collect::<Vec<_>>();
+    // Groups are already sorted, so sorting by index makes the new IDs the same every run.
+    collisions.sort();
+    for mut indices in collisions {
+        indices.sort_by_cached_key(|&index| {
+            let group = &groups[index];
+            (group.demangled_name(), group.function_size, group.copies)
+        });
+        for &index in &indices[1..] {
+            let group = &mut groups[index];
+            let name = group.demangled_name().unwrap_or_default();
+            for attempt in 0u64.. {
+                let mut hasher = StableHasher::new();
+                hasher.write_u64(group.key_hash);
+                hasher.write_prefixed(name.as_bytes());
+                hasher.write_u64(attempt);
+                let key_hash = hasher.finish();
+                if ids.insert(format!("{key_hash:016x}")[..GROUP_ID_LEN].to_owned()) {
+                    group.key_hash = key_hash;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn process_object<K: Key>(
+    object: &object::File,
+    member: Option<&str>,
+    options: &Options,
+    acc: &mut Accumulator<K>,
+) -> Result {
+    check_key_supported(object, options)?;
+    let mut functions = timed(options, Phase::Parse, || functions(object, options))?;
+    if !options.include_plt {
+        acc.plt_bytes += remove_plt_functions(object, &mut functions);
+    }
+    let zero_sized = zero_size_symbols(object, options, &functions);
+    if zero_sized > 0 {
+        tracing::info!(
+            "Skipped {zero_sized} functions {}{}",
+            Skip::ZeroSize.description(),
+            in_member(member)
+        );
+    }
+    if let Some(stats) = &options.stats {
+        stats.add_skipped(Skip::ZeroSize, zero_sized);
+    }
+    let mut inputs = KeyBuilderInputs::new(object, options, &functions);
+    inputs.key_command = acc.key_command.as_ref();
+    let comdats = comdat_names(object);
+
+    let (mut candidates, filtered): (Vec<_>, Vec<_>) = functions
+        .iter()
+        .partition(|function| passes_filters(function, options));
+
+    // Symbols with the same address and size, e.g. aliases and weak duplicates, are a single
+    // function body, so they only count as one copy. Sorting by name too means that we key by the
+    // same alias each time.
+    candidates.sort_by(|a, b| {
+        (a.section_index.map(|i| i.0), a.address, a.size, &a.name).cmp(&(
+            b.section_index.map(|i| i.0),
+            b.address,
+            b.size,
+            &b.name,
+        ))
+    });
+    let bodies = if options.keep_aliases {
+        candidates.chunks(1).collect::<Vec<_>>()
+    } else {
+        candidates
+            .chunk_by(|a, b| {
+                (a.section_index, a.address, a.size) == (b.section_index, b.address, b.size)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Computing keys, especially for the instructions key, is the expensive part, so we do it in
+    // parallel.
+    if let Some(progress) = &options.progress {
+        progress.add_total(candidates.len() as u64);
+    }
+    let keys = timed(options, Phase::Keying, || {
+        let keys = bodies
+            .par_iter()
+            .map(|aliases| {
+                let key = K::from_fn(aliases[0], &inputs);
+                if let Some(progress) = &options.progress {
+                    progress.advance(aliases.len() as u64);
+                }
+                key
+            })
+            .collect::<Vec<_>>();
+        if options.verify_hashes {
+            K::verify(keys, &bodies, &inputs)
+        } else {
+            keys
+        }
+    });
+
+    // Only the first function of each group needs a stable hash, but computing one means decoding
+    // the function again, so that's done in parallel too.
+    let key_hashes = timed(options, Phase::Keying, || {
+        let mut seen = HashSet::new();
+        let starts_group = keys
+            .iter()
+            .map(|key| {
+                key.as_ref()
+                    .is_some_and(|key| !acc.symbols.contains_key(key) && seen.insert(key))
+            })
+            .collect::<Vec<_>>();
+        keys.par_iter()
+            .zip(&bodies)
+            .zip(starts_group)
+            .map(|((key, aliases), starts_group)| {
+                let key = key.as_ref().filter(|_| starts_group)?;
+                Some(key.stable_hash(aliases[0], &inputs))
+            })
+            .collect::<Vec<_>>()
+    });
+
+    acc.checked_bytes += covered_bytes(
+        keys.iter()
+            .zip(&bodies)
+            .filter(|(key, _)| key.is_some())
+            .map(|(_, aliases)| aliases[0]),
+    );
+    timed(options, Phase::Aggregation, || {
+        for ((key, aliases), key_hash) in keys.into_iter().zip(&bodies).zip(key_hashes) {
+            let Some(key) = key else {
+                let skip = match get_fn_bytes(aliases[0], object) {
+                    Some(_) => Skip::KeyFailure,
+                    None => Skip::OutsideSection,
+                };
+                // Key failures are logged with their reasons below.
+                if skip == Skip::OutsideSection {
+                    tracing::debug!(
+                        name = aliases[0].name.as_deref().unwrap_or("<unknown>"),
+                        address = format_args!("{:#x}", aliases[0].address),
+                        "Skipped function {}",
+                        skip.description()
+                    );
+                }
+                if let Some(stats) = &options.stats {
+                    stats.add_skipped(skip, 1);
+                }
+                continue;
+            };
+            let function = aliases[0];
+            let info = acc.symbols.entry(key).or_insert_with(|| DuplicateGroup {
+                function_size: function.size,
+                copies: 0,
+                names: Default::default(),
+                instances: Vec::new(),
+                key_hash: key_hash.unwrap_or_default(),
+            });
+            info.copies += 1;
+            info.instances.push(FunctionInstance {
+                address: function.address,
+                name: aliases
+                    .iter()
+                    .find_map(|alias| alias.name.as_deref().map(str::to_owned)),
+                section: function
+                    .section_index
+                    .and_then(|index| object.section_by_index(index).ok())
+                    .and_then(|section| section.name().ok().map(|name| name.to_owned())),
+                member: member.map(|m| m.to_owned()),
+                linkage: aliases
+                    .iter()
+                    .map(|alias| alias.linkage)
+                    .max()
+                    .unwrap_or(Linkage::UNKNOWN),
+                file: aliases
+                    .iter()
+                    .find_map(|alias| alias.file.as_deref().map(str::to_owned)),
+                comdat: comdat_name(object, &comdats, aliases),
+            });
+            for name in aliases.iter().filter_map(|alias| alias.name.as_ref()) {
+                *info.names.entry(display_name(name, options)).or_default() += 1;
+            }
+        }
+    });
+    for section in object
+        .sections()
+        .filter(|section| is_code_section(section, options))
+    {
+        let name = section.name().unwrap_or_default().to_owned();
+        let size = match &options.address_range {
+            Some(range) => {
+                let start = section.address().max(range.start);
+                let end = (section.address() + section.size()).min(range.end);
+                if end <= start {
+                    continue;
+                }
+                end - start
+            }
+            None => section.size(),
+        };
+        if !options.include_plt && is_plt_section(&name) {
+            acc.plt_bytes += size;
+            continue;
+        }
+        *acc.section_sizes.entry(name).or_default() += size;
+    }
+    // Mach-O's `__PAGEZERO` reserves the low 4GiB of address space, but nothing is loaded there.
+    acc.loaded_size += object
+        .segments()
+        .filter(|segment| segment.name() != Ok(Some("__PAGEZERO")))
+        .map(|segment| segment.size())
+        .sum::<u64>();
+    acc.filtered_functions += filtered.len() as u64;
+    acc.filtered_bytes += filtered.iter().map(|function| function.size).sum::<u64>();
+    acc.inferred_sizes += functions.iter().filter(|f| f.size_inferred).count() as u64;
+    acc.dynamic_symbols_only |= uses_dynamic_symbols(object, options);
+    if uses_unwind_tables(object, options) {
+        acc.unwind_functions += functions.len() as u64;
+    }
+    acc.had_symbols |= !functions.is_empty() || object.symbols().next().is_some();
+    let failures = inputs.failures.into_inner().unwrap();
+    if !failures.is_empty() {
+        tracing::info!(
+            "Skipped {} functions {}{}",
+            failures.len(),
+            Skip::KeyFailure.description(),
+            in_member(member)
+        );
+    }
+    acc.key_failures.extend(failures.into_iter().map(|failure| {
+        tracing::debug!(
+            name = failure.name.as_deref().unwrap_or("<unknown>"),
+            address = format_args!("{:#x}", failure.address),
+            "Skipped function {}: {}",
+            Skip::KeyFailure.description(),
+            failure.error
+        );
+        KeyFailure {
+            member: member.map(|m| m.to_owned()),
+            ..failure
+        }
+    }));
+    Ok(())
+}
+
+/// Returns the number of bytes that are in at least one of `functions`.
+fn covered_bytes<'a, 'data: 'a>(functions: impl Iterator<Item = &'a Function<'data>>) -> u64 {
+    let mut ranges = functions
+        .map(|f| (f.section_index.map(|i| i.0), f.address, f.address + f.size))
+        .collect::<Vec<_>>();
+    ranges.sort_unstable();
+    let mut total = 0;
+    let mut current: Option<(Option<usize>, u64, u64)> = None;
+    for (section, start, end) in ranges {
+        match &mut current {
+            Some((current_section, _, current_end))
+                if *current_section == section && start <= *current_end =>
+            {
+                *current_end = (*current_end).max(end);
+            }
+            _ => {
+                if let Some((_, start, end)) = current {
+                    total += end - start;
+                }
+                current = Some((section, start, end));
+            }
+        }
+    }
+    total + current.map_or(0, |(_, start, end)| end - start)
+}
+
+/// Returns the name of the COMDAT group that contains each section of `object`.
+fn comdat_names(object: &object::File) -> HashMap<SectionIndex, String> {
+    let mut names = HashMap::new();
+    for comdat in object.comdats() {
+        let Ok(name) = comdat.name() else {
+            continue;
+        };
+        for section in comdat.sections() {
+            names.insert(section, name.to_owned());
+        }
+    }
+    names
+}
+
+/// Returns the name that the linker deduplicates the function with `aliases` by, if any. ELF and
+/// COFF objects put such functions in COMDAT groups, whereas Mach-O objects mark them as weak
+/// definitions, which `ld64` coalesces.
+fn comdat_name(
+    object: &object::File,
+    comdats: &HashMap<SectionIndex, String>,
+    aliases: &[&Function],
+) -> Option<String> {
+    if let Some(name) = aliases[0].section_index.and_then(|i| comdats.get(&i)) {
+        return Some(name.clone());
+    }
+    if object.format() != BinaryFormat::MachO || object.kind() != ObjectKind::Relocatable {
+        return None;
+    }
+    aliases
+        .iter()
+        .filter(|alias| alias.linkage.binding == Binding::Weak)
+        .find_map(|alias| alias.name.as_deref().map(str::to_owned))
+}
+
+/// Returns the functions in `object` that have non-zero sizes and are in sections selected by
+/// `options`.
+fn functions<'data>(
+    object: &object::File<'data, &'data [u8]>,
+    options: &Options,
+) -> Result<Vec<Function<'data>>> {
+    let mut functions = unfiltered_functions(object, options)?;
+    if object.architecture() == Architecture::Arm {
+        // The addresses of Thumb functions have their lowest bit set, so that calls through
+        // pointers to them switch to Thumb.
+        for function in &mut functions {
+            function.thumb = function.address & 1 != 0;
+            function.address &= !1;
+        }
+    }
+    if !options.sections.is_all() {
+        functions.retain(|function| {
+            function
+                .section_index
+                .and_then(|index| object.section_by_index(index).ok())
+                .is_some_and(|section| is_code_section(&section, options))
+        });
+    }
+    if let Some(range) = &options.address_range {
+        if object.kind() == ObjectKind::Relocatable {
+            bail!("`--range` isn't supported for relocatable objects, which haven't been linked");
+        }
+        functions.retain(|function| {
+            range.start <= function.address && function.address + function.size <= range.end
+        });
+    }
+    Ok(functions)
+}
+
+fn unfiltered_functions<'data>(
+    object: &object::File<'data, &'data [u8]>,
+    options: &Options,
+) -> Result<Vec<Function<'data>>> {
+    // PDBs and debug files may have been found automatically, so an explicit symbols file takes
+    // precedence.
+    if let Some(symbols_file) = &options.symbols_file {
+        return symbols_file::functions(symbols_file, object);
+    }
+    if let Some(pdb) = &options.pdb {
+        if object.format() != BinaryFormat::Pe {
+            bail!("A PDB can only be used with a PE binary");
+        }
+        return pdb_symbols::functions(pdb, object);
+    }
+    if let Some(debug_file) = &options.debug_file {
+        return debug_file::functions(debug_file, object);
+    }
+    match options.symbols {
+        SymbolSource::Auto => {}
+        SymbolSource::Symtab => {
+            if object.symbol_table().is_none() {
+                bail!("Binary has no symbol table");
+            }
+            return Ok(symtab_functions(object, options));
+        }
+        SymbolSource::Dynsym => {
+            if object.dynamic_symbol_table().is_none() {
+                bail!("Binary has no dynamic symbol table");
+            }
+            return Ok(dynamic_symbol_functions(object));
+        }
+        SymbolSource::Both => {
+            let mut functions = symtab_functions(object, options);
+            let addresses = functions
+                .iter()
+                .map(|function| (function.section_index, function.address))
+                .collect::<HashSet<_>>();
+            functions.extend(
+                dynamic_symbol_functions(object)
+                    .into_iter()
+                    .filter(|function| {
+                        !addresses.contains(&(function.section_index, function.address))
+                    }),
+            );
+            return Ok(functions);
+        }
+    }
+    if object.symbol_table().is_none() {
+        if let Some(functions) = debug_file::mini_debug_info_functions(object)? {
+            return Ok(functions);
+        }
+    }
+    if uses_unwind_tables(object, options) {
+        return unwind::functions(object);
+    }
+    if uses_dynamic_symbols(object, options) {
+        return Ok(dynamic_symbol_functions(object));
+    }
+    Ok(symtab_functions(object, options))
+}
+
+/// Returns the functions in the symbol table of `object`, inferring sizes for Mach-O or if asked
+/// to, and otherwise leaving out those without a size.
+fn symtab_functions<'data>(
+    object: &object::File<'data, &'data [u8]>,
+    options: &Options,
+) -> Vec<Function<'data>> {
+    let inferred_sizes = (options.infer_sizes || object.format() == BinaryFormat::MachO)
+        .then(|| infer_symbol_sizes(object));
+    symbols_with_files(object)
+        .filter(|(sym, _)| sym.kind() == SymbolKind::Text)
+        .filter_map(|(sym, file)| {
+            let size_inferred = inferred_sizes.is_some() && sym.size() == 0;
+            let size = match &inferred_sizes {
+                Some(sizes) if size_inferred => {
+                    sizes.get(&(sym.section_index()?, sym.address())).copied()?
+                }
+                _ => sym.size(),
+            };
+            (size != 0).then(|| Function {
+                name: sym.name().ok().map(Cow::Borrowed),
+                address: sym.address(),
+                size,
+                section_index: sym.section_index(),
+                size_inferred,
+                thumb: false,
+                linkage: Linkage::of_symbol(&sym),
+                file: file.map(Cow::Borrowed),
+            })
+        })
+        .collect()
+}
+
+/// Runs `f`, recording how long it took as part of `phase` if `options` collects statistics.
+pub fn timed<T>(options: &Options, phase: Phase, f: impl FnOnce() -> T) -> T {
+    let start = std::time::Instant::now();
+    let result = match &options.stats {
+        Some(stats) => stats.time(phase, f),
+        None => f(),
+    };
+    tracing::debug!(
+        phase = phase.description(),
+        elapsed = ?start.elapsed(),
+        "Finished phase"
+    );
+    result
+}
+
+/// Describes where a function came from in log messages, e.g. `` in `foo.o` ``.
+fn in_member(member: Option<&str>) -> String {
+    member.map_or_else(String::new, |member| format!(" in `{member}`"))
+}
+
+/// Returns the number of functions in `object`'s symbol table that were left out of `functions`
+/// because their size was zero and couldn't be inferred. Other sources of functions don't have
+/// zero-sized symbols.
+fn zero_size_symbols(object: &object::File, options: &Options, functions: &[Function]) -> u64 {
+    let from_symbol_table = options.symbols_file.is_none()
+        && options.pdb.is_none()
+        && options.debug_file.is_none()
+        && object.symbol_table().is_some();
+    if !from_symbol_table {
+        return 0;
+    }
+    let zero_sized = object
+        .symbols()
+        .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.is_definition())
+        .filter(|symbol| symbol.size() == 0)
+        .count();
+    let inferred = functions.iter().filter(|f| f.size_inferred).count();
+    zero_sized.saturating_sub(inferred) as u64
+}
+
+/// Whether `object` has nothing better to read functions from than its dynamic symbol table, as is
+/// the case for stripped shared libraries.
+fn uses_dynamic_symbols(object: &object::File, options: &Options) -> bool {
+    options.symbols == SymbolSource::Auto
+        && options.symbols_file.is_none()
+        && options.pdb.is_none()
+        && options.debug_file.is_none()
+        && object.symbol_table().is_none()
+        && object.section_by_name(".gnu_debugdata").is_none()
+        && object.dynamic_symbol_table().is_some()
+        && !uses_unwind_tables(object, options)
+}
+
+/// Returns whether to take functions from the unwind tables of `object`, because none of its
+/// function symbols have a size, e.g. because it was stripped. Sizes are only inferred from the
+/// symbols that follow each function when asked, so unwind tables are preferred.
+fn uses_unwind_tables(object: &object::File, options: &Options) -> bool {
+    options.symbols == SymbolSource::Auto
+        && options.symbols_file.is_none()
+        && options.pdb.is_none()
+        && options.debug_file.is_none()
+        && !options.infer_sizes
+        && object.format() == BinaryFormat::Elf
+        && object.section_by_name(".gnu_debugdata").is_none()
+        && !object
+            .symbols()
+            .any(|sym| sym.kind() == SymbolKind::Text && sym.size() != 0)
+        && unwind::has_tables(object)
+}
+
+/// Returns the functions with non-zero sizes that `object` exports in its dynamic symbol table.
+fn dynamic_symbol_functions<'data>(
+    object: &object::File<'data, &'data [u8]>,
+) -> Vec<Function<'data>> {
+    object
+        .dynamic_symbols()
+        .filter(|sym| sym.kind() == SymbolKind::Text && !sym.is_undefined() && sym.size() != 0)
+        .map(|sym| Function {
+            name: sym.name().ok().map(Cow::Borrowed),
+            address: sym.address(),
+            size: sym.size(),
+            section_index: sym.section_index(),
+            size_inferred: false,
+            thumb: false,
+            linkage: Linkage::of_symbol(&sym),
+            file: None,
+        })
+        .collect()
+}
+
+/// Pairs each symbol of `object` with the file named by the `STT_FILE` symbol before it. Local
+/// symbols follow the `STT_FILE` symbol of the file that defined them, but global symbols come
+/// after all of the local ones, so we don't know which file they came from.
+fn symbols_with_files<'data, 'file>(
+    object: &'file object::File<'data>,
+) -> impl Iterator<Item = (object::Symbol<'data, 'file>, Option<&'data str>)> {
+    let mut file = None;
+    object.symbols().filter_map(move |sym| {
+        if sym.kind() == SymbolKind::File {
+            file = sym.name().ok().filter(|name| !name.is_empty());
+            return None;
+        }
+        let file = if sym.is_local() { file } else { None };
+        Some((sym, file))
+    })
+}
+
+/// Returns the address range of each executable section in `object`, for finding the sections of
+/// functions that didn't come from its symbol table.
+fn text_section_ranges(object: &object::File) -> Vec<(SectionIndex, std::ops::Range<u64>)> {
+    object
+        .sections()
+        .filter(is_executable)
+        .map(|section| {
+            let start = section.address();
+            (section.index(), start..start + section.size())
+        })
+        .collect()
+}
+
+fn passes_filters(function: &Function, options: &Options) -> bool {
+    let ignores_crates = options.ignore_std || options.ignore_compiler_builtins;
+    if options.filter.is_none() && options.exclude.is_none() && !ignores_crates {
+        return true;
+    }
+    let Some(name) = &function.name else {
+        return options.filter.is_none();
+    };
+    if ignores_crates && is_ignored_crate(name, options) {
+        return false;
+    }
+    let demangled = demangle::demangle(name, false);
+    options
+        .filter
+        .as_ref()
+        .is_none_or(|filter| filter.is_match(&demangled))
+        && !options
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(&demangled))
+}
+
+fn is_ignored_crate(name: &str, options: &Options) -> bool {
+    match crate_name(name).as_deref() {
+        Some("core" | "alloc" | "std") => options.ignore_std,
+        Some("compiler_builtins") => options.ignore_compiler_builtins,
+        _ => false,
+    }
+}
+
+fn display_name(name: &str, options: &Options) -> String {
+    if options.demangle || options.demangle_no_hash {
+        demangle::demangle(name, options.demangle_no_hash).into_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+fn get_fn_bytes<'data>(
+    function: &Function,
+    object: &object::File<'data, &'data [u8]>,
+) -> Option<&'data [u8]> {
+    let section = object.section_by_index(function.section_index?).ok()?;
+    let section_data = section.data().ok()?;
+    let offset = function.address.checked_sub(section.address())? as usize;
+    let end = offset + function.size as usize;
+    if end > section_data.len() {
+        return None;
+    }
+    Some(&section_data[offset..end])
+}
+
+/// Returns whether `section` contains code. This is based on the section's flags rather than on its
+/// kind, since `object` only classifies sections with well-known names or types as text, which
+/// misses e.g. `.init` and custom-named sections.
+fn is_executable<'data>(section: &impl ObjectSection<'data>) -> bool {
+    match section.flags() {
+        SectionFlags::Elf { sh_flags } => sh_flags & u64::from(object::elf::SHF_EXECINSTR) != 0,
+        SectionFlags::Coff { characteristics } => {
+            characteristics & (object::pe::IMAGE_SCN_MEM_EXECUTE | object::pe::IMAGE_SCN_CNT_CODE)
+                != 0
+        }
+        SectionFlags::MachO { flags } => {
+            flags
+                & (object::macho::S_ATTR_PURE_INSTRUCTIONS
+                    | object::macho::S_ATTR_SOME_INSTRUCTIONS)
+                != 0
+        }
+        _ => section.kind() == SectionKind::Text,
+    }
+}
+
+/// Returns whether a section with `name` holds PLT stubs, which jump to functions in other modules
+/// or to the implementations chosen by IFUNC resolvers.
+fn is_plt_section(name: &str) -> bool {
+    matches!(
+        name,
+        ".plt" | ".plt.got" | ".plt.sec" | ".plt.bnd" | ".iplt"
+    )
+}
+
+/// Removes functions that are in PLT sections or that are IFUNC resolvers, which pick an
+/// implementation of a function when the program is loaded. Returns the size of the resolvers,
+/// since PLT sections are counted as a whole.
+fn remove_plt_functions(object: &object::File, functions: &mut Vec<Function>) -> u64 {
+    // Sections in relocatable objects all start at zero, so there, addresses are only unique
+    // within a section. Elsewhere, functions from debug files don't necessarily know their section.
+    let relocatable = object.kind() == ObjectKind::Relocatable;
+    let location = |section_index: Option<SectionIndex>, address: u64| {
+        (section_index.filter(|_| relocatable), address)
+    };
+    let resolvers = object
+        .symbols()
+        .chain(object.dynamic_symbols())
+        .filter(|sym| {
+            matches!(sym.flags(), SymbolFlags::Elf { st_info, .. }
+                if st_info & 0xf == object::elf::STT_GNU_IFUNC)
+        })
+        .map(|sym| location(sym.section_index(), sym.address()))
+        .collect::<HashSet<_>>();
+    let mut resolver_sizes = HashMap::new();
+    functions.retain(|function| {
+        let in_plt = function
+            .section_index
+            .and_then(|index| object.section_by_index(index).ok())
+            .is_some_and(|section| section.name().is_ok_and(is_plt_section));
+        let location = location(function.section_index, function.address);
+        if resolvers.contains(&location) {
+            resolver_sizes.insert(location, function.size);
+            return false;
+        }
+        !in_plt
+    });
+    resolver_sizes.values().sum()
+}
+
+/// Returns whether `section` is executable and selected by `options`.
+fn is_code_section<'data>(section: &impl ObjectSection<'data>, options: &Options) -> bool {
+    is_executable(section)
+        && section
+            .name()
+            .is_ok_and(|name| options.sections.matches(name))
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct NameAndSizeKey {
+    demangled_name: String,
+    function_size: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct InstructionsKey {
+    function_bytes: Vec<u8>,
+
+    /// Relocations that apply to the function. Only populated for relocatable objects.
+    relocations: Vec<RelocationKey>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct InstructionsWithCallsKey {
+    function_bytes: Vec<u8>,
+
+    /// The index of each instruction whose branch target was replaced, together with the name of
+    /// the target. For relocatable objects, this is instead the offset of each relocation.
+    call_targets: Vec<(usize, String)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct InstructionShapeKey {
+    /// The function's instructions as described by `instruction_shape`.
+    shape: Vec<u8>,
+
+    /// Relocations that apply to the function. Only populated for relocatable objects.
+    relocations: Vec<RelocationKey>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct DecodedInstructionsKey {
+    /// The function's instructions as described by `instruction_shape`, without renaming
+    /// registers.
+    instructions: Vec<u8>,
+
+    /// Relocations that apply to the function. Only populated for relocatable objects.
+    relocations: Vec<RelocationKey>,
+}
+
+/// A key printed by `Options::key_cmd`. Copies in a group need to be the same size, so the size is
+/// part of the key too.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CommandKey {
+    key: String,
+    function_size: u64,
+}
+
+/// A relocation, described independently of where the function and its target end up.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct RelocationKey {
+    /// Offset of the relocation from the start of the function.
+    offset: u64,
+    kind: RelocationKind,
+    encoding: RelocationEncoding,
+    size: u8,
+    target: String,
+    addend: i64,
+}
+
+/// A 128 bit hash of another key. Keys for instructions hold a copy of each function's normalised
+/// code, so for huge binaries, only keeping a hash uses several times less memory.
+#[derive(PartialEq, Eq, Hash)]
+struct HashedKey<K> {
+    hash: u128,
+
+    /// Distinguishes functions with the same hash that `Options::verify_hashes` found to differ.
+    collision: u32,
+
+    key_type: PhantomData<K>,
+}
+
+impl<K: Hash> HashedKey<K> {
+    fn new(key: &K) -> Self {
+        let half = |seed: u8| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+        HashedKey {
+            hash: u128::from(half(0)) << 64 | u128::from(half(1)),
+            collision: 0,
+            key_type: PhantomData,
+        }
+    }
+}
+
+/// A 64 bit FNV-1a hash. Unlike `DefaultHasher`, which may change between Rust releases, its output
+/// is fixed, so it's used for hashes that are shown to users.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    /// Writes `bytes` preceded by their length, so that consecutive values can't run together.
+    fn write_prefixed(&mut self, bytes: &[u8]) {
+        self.write_u64(bytes.len() as u64);
+        self.write(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Returns a hash of `function`'s code for `DuplicateGroup::key_hash`. Code is normalised as for
+/// the key, but references outside the function are described by the target's name without
+/// rustc's hash, or left out if the target isn't a function, rather than by address.
+fn stable_code_hash(function: &Function, inputs: &KeyBuilderInputs) -> u64 {
+    let mut hasher = StableHasher::new();
+    let Some(fn_bytes) = get_fn_bytes(function, inputs.object) else {
+        return hasher.finish();
+    };
+    let name_of = |target: u64| {
+        inputs
+            .function_names
+            .as_ref()
+            .and_then(|names| names.get(&target))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let fn_range = function.address..function.address + function.size;
+    if let Some(relocations) = inputs.relocations_for(function) {
+        hasher.write_prefixed(fn_bytes);
+        for relocation in relocations {
+            hasher.write_u64(relocation.offset);
+            hasher.write_u64(u64::from(relocation.size));
+            hasher.write_prefixed(demangle::demangle(&relocation.target, true).as_bytes());
+            hasher.write_u64(relocation.addend as u64);
+        }
+    } else if let Some(arch) = &inputs.arch {
+        let strip_padding = !inputs.options.keep_padding;
+        let (bytes, names) = arch
+            .normalise(fn_bytes, function, strip_padding)
+            .into_key(fn_range, |target| Some(name_of(target)));
+        hasher.write_prefixed(&bytes);
+        for (offset, name) in names {
+            hasher.write_u64(offset as u64);
+            hasher.write_prefixed(name.as_bytes());
+        }
+    } else {
+        let mut instructions = decode_asm(fn_bytes, function.address, inputs.bitness);
+        if !inputs.options.keep_padding {
+            strip_trailing_padding(&mut instructions);
+        }
+        widen_branches(&mut instructions);
+        let rename_registers = inputs.options.key == KeyType::InstructionShape;
+        let shape = instruction_shape(&instructions, fn_range, rename_registers, |target| {
+            let mut hasher = StableHasher::new();
+            hasher.write(name_of(target).as_bytes());
+            hasher.finish()
+        });
+        hasher.write_prefixed(&shape);
+    }
+    hasher.finish()
+}
+
+struct KeyBuilderInputs<'data, 'inputs> {
+    /// For x86, the address of the last function, which is where functions are re-encoded. Other
+    /// architectures are normalised without being re-encoded, so this is `None` for them.
+    max_fn_address: Option<u64>,
+    object: &'inputs object::File<'data, &'data [u8]>,
+    options: &'inputs Options,
+
+    /// Whether the code is 16, 32 or 64 bit.
+    bitness: u32,
+
+    /// How to normalise code that isn't x86.
+    arch: Option<Box<dyn arch::Arch>>,
+
+    /// For relocatable objects, the relocations of each section, sorted by offset.
+    relocations: Option<HashMap<SectionIndex, Vec<(u64, Relocation)>>>,
+
+    /// Names of functions without rustc's hash, by address. Only populated for keys that decode
+    /// instructions.
+    function_names: Option<HashMap<u64, String>>,
+
+    /// The program to key functions with, if `Options::key_cmd` is set.
+    key_command: Option<&'inputs Mutex<KeyCommand>>,
+
+    /// Functions whose keys couldn't be built and why. Members aren't filled in.
+    failures: Mutex<Vec<KeyFailure>>,
+}
+impl<'data, 'inputs> KeyBuilderInputs<'data, 'inputs> {
+    fn new(
+        object: &'inputs object::File<'data, &'data [u8]>,
+        options: &'inputs Options,
+        functions: &[Function],
+    ) -> Self {
+        let max_fn_address =
+            is_x86(object).then(|| functions.iter().map(|f| f.address).max().unwrap_or(0));
+        let relocations =
+            (object.kind() == ObjectKind::Relocatable).then(|| section_relocations(object));
+        let function_names = (options.key_cmd.is_none()
+            && !matches!(
+                options.key,
+                KeyType::NameAndSize | KeyType::NameWithoutRustHash
+            ))
+        .then(|| function_names_by_address(functions));
+        Self {
+            max_fn_address,
+            object,
+            options,
+            bitness: bitness(object),
+            arch: arch::for_object(object),
+            relocations,
+            function_names,
+            key_command: None,
+            failures: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the value of `result`, or records why `function` couldn't be keyed and returns
+    /// `None`.
+    fn or_record_failure<T>(&self, function: &Function, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.failures.lock().unwrap().push(KeyFailure {
+                    name: function
+                        .name
+                        .as_ref()
+                        .map(|name| display_name(name, self.options)),
+                    address: function.address,
+                    size: function.size,
+                    member: None,
+                    error: format!("{error:#}"),
+                });
+                None
+            }
+        }
+    }
+
+    /// Returns the relocations that apply within `function`, described in terms of the names of
+    /// their targets.
+    fn relocations_for(&self, function: &Function) -> Option<Vec<RelocationKey>> {
+        let relocations = self.relocations.as_ref()?.get(&function.section_index?)?;
+        let section = self.object.section_by_index(function.section_index?).ok()?;
+        let start = function.address.checked_sub(section.address())?;
+        let end = start + function.size;
+        let first = relocations.partition_point(|(offset, _)| *offset < start);
+        Some(
+            relocations[first..]
+                .iter()
+                .take_while(|(offset, _)| *offset < end)
+                .map(|(offset, rel)| RelocationKey {
+                    offset: offset - start,
+                    kind: rel.kind(),
+                    encoding: rel.encoding(),
+                    size: rel.size(),
+                    target: self.relocation_target_name(rel.target()),
+                    addend: rel.addend(),
+                })
+                .collect(),
+        )
+    }
+
+    fn relocation_target_name(&self, target: RelocationTarget) -> String {
+        let name = match target {
+            RelocationTarget::Symbol(index) => {
+                self.object
+                    .symbol_by_index(index)
+                    .ok()
+                    .and_then(|sym| match sym.name() {
+                        Ok(name) if !name.is_empty() => Some(name.to_owned()),
+                        // Section symbols generally don't have names of their own.
+                        _ => sym
+                            .section_index()
+                            .and_then(|index| self.object.section_by_index(index).ok())
+                            .and_then(|section| section.name().ok().map(|name| name.to_owned())),
+                    })
+            }
+            RelocationTarget::Section(index) => self
+                .object
+                .section_by_index(index)
+                .ok()
+                .and_then(|section| section.name().ok().map(|name| name.to_owned())),
+            _ => None,
+        };
+        name.unwrap_or_default()
+    }
+}
+
+fn function_names_by_address(functions: &[Function]) -> HashMap<u64, String> {
+    let mut names = HashMap::new();
+    for function in functions {
+        let Some(name) = &function.name else {
+            continue;
+        };
+        let name = demangle::demangle(name, true).into_owned();
+        // Where there are aliases, pick consistently so that the result doesn't depend on symbol
+        // table order.
+        names
+            .entry(function.address)
+            .and_modify(|existing: &mut String| {
+                if name < *existing {
+                    existing.clone_from(&name);
+                }
+            })
+            .or_insert(name);
+    }
+    names
+}
+
+fn section_relocations(object: &object::File) -> HashMap<SectionIndex, Vec<(u64, Relocation)>> {
+    object
+        .sections()
+        .filter(is_executable)
+        .map(|section| {
+            let mut relocations = section.relocations().collect::<Vec<_>>();
+            relocations.sort_by_key(|(offset, _)| *offset);
+            (section.index(), relocations)
+        })
+        .collect()
+}
+
+/// Mach-O symbol table entries don't have sizes and some toolchains emit ELF symbols with a size of
+/// zero, so we assume that each symbol extends until the next symbol in the same section, or
+/// failing that, the end of the section. Sizes are keyed by section too, since sections in
+/// relocatable objects all start at zero.
+fn infer_symbol_sizes(object: &object::File) -> HashMap<(SectionIndex, u64), u64> {
+    let mut addresses_by_section: HashMap<SectionIndex, Vec<u64>> = HashMap::new();
+    for sym in object.symbols() {
+        if let Some(section_index) = sym.section_index() {
+            addresses_by_section
+                .entry(section_index)
+                .or_default()
+                .push(sym.address());
+        }
+    }
+    let mut sizes = HashMap::new();
+    for (section_index, mut addresses) in addresses_by_section {
+        let Ok(section) = object.section_by_index(section_index) else {
+            continue;
+        };
+        let section_end = section.address() + section.size();
+        addresses.sort_unstable();
+        addresses.dedup();
+        let ends = addresses.iter().skip(1).copied().chain([section_end]);
+        for (&address, end) in addresses.iter().zip(ends) {
+            sizes.insert((section_index, address), end.saturating_sub(address));
+        }
+    }
+    sizes
+}
+
+impl<K: Key> Key for HashedKey<K> {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self> {
+        K::from_fn(function, inputs).map(|key| HashedKey::new(&key))
+    }
+
+    fn verify(
+        mut keys: Vec<Option<Self>>,
+        bodies: &[&[&Function]],
+        inputs: &KeyBuilderInputs,
+    ) -> Vec<Option<Self>> {
+        let mut counts: HashMap<u128, u32> = HashMap::new();
+        for key in keys.iter().flatten() {
+            *counts.entry(key.hash).or_default() += 1;
+        }
+        // Only functions that share their hash need their full key, which we compute again.
+        let shared = keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| key.as_ref().is_some_and(|key| counts[&key.hash] > 1))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let full_keys = shared
+            .par_iter()
+            .map(|index| K::from_fn(bodies[*index][0], inputs))
+            .collect::<Vec<_>>();
+        let mut distinct: HashMap<u128, Vec<K>> = HashMap::new();
+        for (index, full_key) in shared.into_iter().zip(full_keys) {
+            let (Some(key), Some(full_key)) = (&mut keys[index], full_key) else {
+                continue;
+            };
+            let seen = distinct.entry(key.hash).or_default();
+            let collision = match seen.iter().position(|k| *k == full_key) {
+                Some(position) => position,
+                None => {
+                    seen.push(full_key);
+                    seen.len() - 1
+                }
+            };
+            key.collision = collision as u32;
+        }
+        keys
+    }
+}
+
+impl Key for NameAndSizeKey {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self> {
+        let name = function.name.as_ref()?;
+        let without_hash = inputs.options.key == KeyType::NameWithoutRustHash;
+        let demangled_name = demangle::try_demangle(name, without_hash)?;
+        Some(NameAndSizeKey {
+            demangled_name,
+            function_size: function.size,
+        })
+    }
+
+    fn stable_hash(&self, _function: &Function, _inputs: &KeyBuilderInputs) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.write_prefixed(self.demangled_name.as_bytes());
+        hasher.write_u64(self.function_size);
+        hasher.finish()
+    }
+}
+
+impl Key for InstructionsKey {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self> {
+        let fn_bytes = get_fn_bytes(function, inputs.object)?;
+        if let Some(relocations) = inputs.relocations_for(function) {
+            // In relocatable objects, references to other functions and data haven't been resolved
+            // yet, so the instruction bytes contain placeholders and the relocations tell us what
+            // is actually being referenced. Relative references within the function don't depend
+            // on where it's placed, so there's no need to re-encode.
+            return Some(Self {
+                function_bytes: fn_bytes.to_vec(),
+                relocations,
+            });
+        }
+        if let Some(arch) = &inputs.arch {
+            let fn_range = function.address..function.address + function.size;
+            let strip_padding = !inputs.options.keep_padding;
+            let (function_bytes, _) = arch
+                .normalise(fn_bytes, function, strip_padding)
+                .into_key(fn_range, |_| None);
+            return Some(Self {
+                function_bytes,
+                relocations: Vec::new(),
+            });
+        }
+        // In order to determine if two functions at different addresses are the same, we need to
+        // fix up IP-relative instructions. We relocate all our functions to the address of the last
+        // function in the file. If we picked an earlier address, then some relative relocations
+        // might wrap. If we chose a much later address, then we might exceed a 32 bit offset.
+        // Although plausibly picking 2**31 would also work OK.
+        let bytes = inputs
+            .or_record_failure(function, normalise_asm(fn_bytes, function.address, inputs))?;
+        Some(Self {
+            function_bytes: bytes,
+            relocations: Vec::new(),
+        })
+    }
+}
+
+impl Key for InstructionsWithCallsKey {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self> {
+        let fn_bytes = get_fn_bytes(function, inputs.object)?;
+        if let Some(relocations) = inputs.relocations_for(function) {
+            // In relocatable objects, calls are already described by relocations.
+            return Some(Self {
+                function_bytes: fn_bytes.to_vec(),
+                call_targets: relocations
+                    .into_iter()
+                    .map(|rel| {
+                        let target = demangle::demangle(&rel.target, true).into_owned();
+                        (rel.offset as usize, target)
+                    })
+                    .collect(),
+            });
+        }
+        let function_names = inputs.function_names.as_ref()?;
+        let fn_range = function.address..function.address + function.size;
+        if let Some(arch) = &inputs.arch {
+            let strip_padding = !inputs.options.keep_padding;
+            let (function_bytes, call_targets) = arch
+                .normalise(fn_bytes, function, strip_padding)
+                .into_key(fn_range, |target| function_names.get(&target).cloned());
+            return Some(Self {
+                function_bytes,
+                call_targets,
+            });
+        }
+        let new_address = inputs.max_fn_address?;
+        let mut instructions = decode_asm(fn_bytes, function.address, inputs.bitness);
+        if !inputs.options.keep_padding {
+            strip_trailing_padding(&mut instructions);
+        }
+        widen_branches(&mut instructions);
+        let mut call_targets = Vec::new();
+        for (index, instruction) in instructions.iter_mut().enumerate() {
+            if !(instruction.is_call_near()
+                || instruction.is_jmp_near()
+                || instruction.is_jcc_short_or_near())
+            {
+                continue;
+            }
+            let target = instruction.near_branch_target();
+            if fn_range.contains(&target) {
+                continue;
+            }
+            let Some(name) = function_names.get(&target) else {
+                continue;
+            };
+            // Point all named targets at the same place, so that the encoded bytes no longer
+            // depend on which copy of the callee is called.
+            set_near_branch_target(instruction, new_address);
+            call_targets.push((index, name.clone()));
+        }
+        let mut function_bytes = inputs.or_record_failure(
+            function,
+            encode_asm(&instructions, new_address, inputs.bitness),
+        )?;
+        if !inputs.options.keep_padding {
+            canonicalise_nops(&mut function_bytes, inputs.bitness);
+        }
+        Some(Self {
+            function_bytes,
+            call_targets,
+        })
+    }
+}
+
+impl Key for CommandKey {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self> {
+        let fn_bytes = get_fn_bytes(function, inputs.object)?;
+        let key = inputs
+            .key_command?
+            .lock()
+            .unwrap()
+            .key(function, fn_bytes, inputs.object)?;
+        Some(CommandKey {
+            key,
+            function_size: function.size,
+        })
+    }
+
+    fn stable_hash(&self, _function: &Function, _inputs: &KeyBuilderInputs) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.write_prefixed(self.key.as_bytes());
+        hasher.write_u64(self.function_size);
+        hasher.finish()
+    }
+}
+
+impl Key for InstructionShapeKey {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self> {
+        let (shape, relocations) = describe_instructions(function, inputs, true)?;
+        Some(Self { shape, relocations })
+    }
+}
+
+impl Key for DecodedInstructionsKey {
+    fn from_fn(function: &Function, inputs: &KeyBuilderInputs) -> Option<Self> {
+        let (instructions, relocations) = describe_instructions(function, inputs, false)?;
+        Some(Self {
+            instructions,
+            relocations,
+        })
+    }
+}
+
+/// Returns `function`'s instructions as described by `instruction_shape`, together with its
+/// relocations if it's in a relocatable object. Architectures other than x86 are described by the
+/// normalised bytes of `InstructionsKey` instead.
+fn describe_instructions(
+    function: &Function,
+    inputs: &KeyBuilderInputs,
+    rename_registers: bool,
+) -> Option<(Vec<u8>, Vec<RelocationKey>)> {
+    if inputs.arch.is_some() {
+        let key = InstructionsKey::from_fn(function, inputs)?;
+        return Some((key.function_bytes, key.relocations));
+    }
+    let fn_bytes = get_fn_bytes(function, inputs.object)?;
+    let mut instructions = decode_asm(fn_bytes, function.address, inputs.bitness);
+    if !inputs.options.keep_padding {
+        strip_trailing_padding(&mut instructions);
+    }
+    widen_branches(&mut instructions);
+    let fn_range = function.address..function.address + function.size;
+    Some((
+        instruction_shape(&instructions, fn_range, rename_registers, |target| target),
+        inputs.relocations_for(function).unwrap_or_default(),
+    ))
+}
+
+/// Describes `instructions` in a way that doesn't depend on where they are. If `rename_registers`
+/// is set, it also doesn't depend on which registers they use, only on which of them use the same
+/// registers. Registers are then numbered in the order that they're first used, except for the
+/// stack and instruction pointers, whose roles are fixed. Registers that are used implicitly, e.g.
+/// `rdx` by `div`, aren't renamed, so functions that use them differently may still get the same
+/// shape. Branches within the function are described by the index of the
+/// instruction that they branch to, so the shape doesn't depend on where the function is or on the
+/// sizes of the instructions in between. Other IP-relative references within the function are
+/// described relative to its start and those outside it by what `outside` returns for the target.
+fn instruction_shape(
+    instructions: &[iced_x86::Instruction],
+    fn_range: Range<u64>,
+    rename_registers: bool,
+    outside: impl Fn(u64) -> u64,
+) -> Vec<u8> {
+    use iced_x86::OpKind;
+    use iced_x86::Register;
+
+    let mut shape = Vec::new();
+    let mut push = |value: u64| shape.extend_from_slice(&value.to_le_bytes());
+    let mut renamed: HashMap<Register, u64> = HashMap::new();
+    let mut rename = |register: Register| {
+        let full = register.full_register();
+        if !rename_registers || matches!(full, Register::None | Register::RIP | Register::RSP) {
+            return register as u64;
+        }
+        let next = renamed.len() as u64;
+        // Registers are renamed as a whole, but which part of one is used, e.g. `eax` or `rax`,
+        // still matters.
+        let index = *renamed.entry(full).or_insert(next);
+        (index + 1) << 32 | register.size() as u64
+    };
+    let labels: HashMap<u64, u64> = instructions
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| (instruction.ip(), index as u64))
+        .collect();
+    let address = |target: u64| {
+        if let Some(index) = labels.get(&target) {
+            *index | 1 << 62
+        } else if fn_range.contains(&target) {
+            target - fn_range.start
+        } else {
+            // The top bit keeps these distinct from offsets within the function.
+            outside(target) | 1 << 63
+        }
+    };
+    for instruction in instructions {
+        // Assemblers differ in which encodings they pick for multi-byte NOPs.
+        if is_padding(instruction) && instruction.code() != iced_x86::Code::Int3 {
+            push(u64::MAX);
+            continue;
+        }
+        push(instruction.code() as u64);
+        for operand in 0..instruction.op_count() {
+            let kind = instruction.op_kind(operand);
+            push(kind as u64);
+            match kind {
+                OpKind::Register => push(rename(instruction.op_register(operand))),
+                OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+                    push(address(instruction.near_branch_target()))
+                }
+                OpKind::Memory if instruction.is_ip_rel_memory_operand() => {
+                    push(address(instruction.ip_rel_memory_address()))
+                }
+                OpKind::Memory => {
+                    push(rename(instruction.memory_base()));
+                    push(rename(instruction.memory_index()));
+                    push(u64::from(instruction.memory_index_scale()));
+                    push(instruction.memory_displacement64());
+                }
+                OpKind::Immediate8
+                | OpKind::Immediate8_2nd
+                | OpKind::Immediate16
+                | OpKind::Immediate32
+                | OpKind::Immediate64
+                | OpKind::Immediate8to16
+                | OpKind::Immediate8to32
+                | OpKind::Immediate8to64
+                | OpKind::Immediate32to64 => push(instruction.immediate(operand)),
+                _ => {}
+            }
+        }
+    }
+    shape
+}
+
+fn normalise_asm(fn_bytes: &[u8], base_address: u64, inputs: &KeyBuilderInputs) -> Result<Vec<u8>> {
+    let mut instructions = decode_asm(fn_bytes, base_address, inputs.bitness);
+    if !inputs.options.keep_padding {
+        strip_trailing_padding(&mut instructions);
+    }
+    widen_branches(&mut instructions);
+    let new_address = inputs
+        .max_fn_address
+        .context("Only x86 code can be re-encoded")?;
+    let mut bytes = encode_asm(&instructions, new_address, inputs.bitness)?;
+    if !inputs.options.keep_padding {
+        canonicalise_nops(&mut bytes, inputs.bitness);
+    }
+    Ok(bytes)
+}
+
+/// Removes `int3` and `nop` instructions from the end of a function. Some toolchains include the
+/// padding up to the next function in the symbol size, which would otherwise make copies that are
+/// followed by different amounts of padding look different.
+fn strip_trailing_padding(instructions: &mut Vec<iced_x86::Instruction>) {
+    while instructions.last().is_some_and(is_padding) {
+        instructions.pop();
+    }
+}
+
+/// Converts short branches to their near forms. Which form an assembler picks can depend on things
+/// other than the code itself, e.g. how far relaxation got, so copies of a function may differ only
+/// in this. The encoder would otherwise keep whichever form it was given where it fits. Branches
+/// with only a short form, such as `loop`, are left alone.
+fn widen_branches(instructions: &mut [iced_x86::Instruction]) {
+    for instruction in instructions {
+        instruction.as_near_branch();
+    }
+}
+
+fn is_padding(instruction: &iced_x86::Instruction) -> bool {
+    instruction.code() == iced_x86::Code::Int3 || instruction.mnemonic() == iced_x86::Mnemonic::Nop
+}
+
+/// Replaces each NOP in `bytes` with single-byte NOPs of the same total length. Assemblers differ
+/// in which encodings they pick for multi-byte NOPs, but that doesn't change what the code does.
+/// The result is only used as a key, so it doesn't matter that it's not how anyone would write it.
+fn canonicalise_nops(bytes: &mut [u8], bitness: u32) {
+    let options = iced_x86::DecoderOptions::NONE;
+    let nops = iced_x86::Decoder::with_ip(bitness, bytes, 0, options)
+        .into_iter()
+        .filter(|instruction| instruction.mnemonic() == iced_x86::Mnemonic::Nop)
+        .map(|instruction| instruction.ip() as usize..instruction.next_ip() as usize)
+        .collect::<Vec<_>>();
+    for range in nops {
+        bytes[range].fill(0x90);
+    }
+}
+
+fn is_x86(object: &object::File) -> bool {
+    matches!(
+        object.architecture(),
+        Architecture::I386 | Architecture::X86_64
+    )
+}
+
+/// Returns an error if `object` isn't x86, since `what` needs to decode its instructions with
+/// `iced_x86`, which would make nonsense of other architectures' code rather than failing.
+fn require_x86(object: &object::File, what: &str) -> Result {
+    if !is_x86(object) {
+        bail!(
+            "{what} is only supported for x86, not {:?}",
+            object.architecture()
+        );
+    }
+    Ok(())
+}
+
+/// Returns an error if `options.key` needs to decode the instructions of `object`, but we don't
+/// know how to for its architecture.
+fn check_key_supported(object: &object::File, options: &Options) -> Result {
+    let decodes = options.key_cmd.is_none()
+        && !matches!(
+            options.key,
+            KeyType::NameAndSize | KeyType::NameWithoutRustHash
+        );
+    if decodes && !is_x86(object) && arch::for_object(object).is_none() {
+        let key = clap::ValueEnum::to_possible_value(&options.key)
+            .map(|value| value.get_name().to_owned())
+            .unwrap_or_default();
+        bail!(
+            "`--key {key}` isn't supported for {:?}, use `--key name-and-size`",
+            object.architecture()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the bitness of code in `object`. Code for architectures other than x86 is either
+/// normalised without `iced_x86` or will fail to decode regardless, so we don't care what we return
+/// for those.
+fn bitness(object: &object::File) -> u32 {
+    match object.architecture() {
+        Architecture::I386 => 32,
+        _ => 64,
+    }
+}
+
+fn decode_asm(fn_bytes: &[u8], base_address: u64, bitness: u32) -> Vec<iced_x86::Instruction> {
+    let options = iced_x86::DecoderOptions::NONE;
+    let decoder = iced_x86::Decoder::with_ip(bitness, fn_bytes, base_address, options);
+    decoder.into_iter().collect()
+}
+
+fn encode_asm(
+    instructions: &[iced_x86::Instruction],
+    new_address: u64,
+    bitness: u32,
+) -> Result<Vec<u8>> {
+    // The encoder's error for these doesn't say where they are.
+    if let Some(invalid) = instructions.iter().find(|i| i.is_invalid()) {
+        bail!("Failed to decode the instruction at {:#x}", invalid.ip());
+    }
+    let block = iced_x86::InstructionBlock::new(instructions, new_address);
+    Ok(
+        iced_x86::BlockEncoder::encode(bitness, block, iced_x86::BlockEncoderOptions::NONE)?
+            .code_buffer,
+    )
+}
+
+/// Points the branch `instruction` at `target`. Branches in 32 bit code store their target
+/// separately from those in 64 bit code.
+fn set_near_branch_target(instruction: &mut iced_x86::Instruction, target: u64) {
+    match instruction.op0_kind() {
+        iced_x86::OpKind::NearBranch16 => instruction.set_near_branch16(target as u16),
+        iced_x86::OpKind::NearBranch32 => instruction.set_near_branch32(target as u32),
+        _ => instruction.set_near_branch64(target),
+    }
+}
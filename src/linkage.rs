@@ -0,0 +1,143 @@
+//! Classification of functions by how their symbols are bound and whether they're visible outside
+//! the binary. Only some copies can be removed by the linker, e.g. exported functions must keep
+//! distinct addresses, since other binaries may compare them, so safe ICF can't fold them.
+
+use object::ObjectSymbol;
+use object::SymbolScope;
+
+/// A symbol's binding.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum Binding {
+    /// The symbol table doesn't say, e.g. because functions came from a PDB.
+    Unknown,
+
+    /// Only visible within the object that defined it, e.g. a `static` function in C.
+    Local,
+
+    /// Global, but may be overridden by another definition.
+    Weak,
+
+    Global,
+}
+
+/// Whether a symbol is visible outside the binary.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum Visibility {
+    /// The symbol table doesn't say, e.g. because functions came from `nm` output.
+    Unknown,
+
+    /// Only visible within the binary, either because it's local or has hidden visibility.
+    Hidden,
+
+    /// Exported from the binary, so other binaries may refer to it.
+    Exported,
+}
+
+/// The binding and visibility of a function's symbol. Ordered so that the most visible is
+/// greatest, which is the one that matters when several symbols name the same function.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Linkage {
+    pub visibility: Visibility,
+    pub binding: Binding,
+}
+
+impl Linkage {
+    pub const UNKNOWN: Linkage = Linkage {
+        visibility: Visibility::Unknown,
+        binding: Binding::Unknown,
+    };
+
+    /// Returns the linkage of a symbol whose visibility isn't known, other than that local symbols
+    /// are never exported.
+    pub(crate) fn from_binding(binding: Binding) -> Linkage {
+        let visibility = match binding {
+            Binding::Local => Visibility::Hidden,
+            _ => Visibility::Unknown,
+        };
+        Linkage {
+            visibility,
+            binding,
+        }
+    }
+
+    pub(crate) fn of_symbol<'data>(symbol: &impl ObjectSymbol<'data>) -> Linkage {
+        let binding = if symbol.is_local() {
+            Binding::Local
+        } else if symbol.is_weak() {
+            Binding::Weak
+        } else {
+            Binding::Global
+        };
+        let visibility = match symbol.scope() {
+            SymbolScope::Unknown => Visibility::Unknown,
+            SymbolScope::Compilation | SymbolScope::Linkage => Visibility::Hidden,
+            SymbolScope::Dynamic => Visibility::Exported,
+        };
+        Linkage {
+            visibility,
+            binding,
+        }
+    }
+
+    /// A short human-readable description.
+    pub fn description(self) -> String {
+        self.describe(" ")
+    }
+
+    /// A stable identifier for machine-readable output.
+    pub fn id(self) -> String {
+        self.describe("_")
+    }
+
+    fn describe(self, separator: &str) -> String {
+        let binding = match self.binding {
+            Binding::Unknown => return "unknown".to_owned(),
+            // Visibility doesn't add anything for local symbols, which are never exported.
+            Binding::Local => return "local".to_owned(),
+            Binding::Weak => "weak",
+            Binding::Global => "global",
+        };
+        match self.visibility {
+            Visibility::Unknown => binding.to_owned(),
+            Visibility::Hidden => format!("{binding}{separator}hidden"),
+            Visibility::Exported => format!("{binding}{separator}exported"),
+        }
+    }
+
+    /// Parses an identifier as returned by `id`.
+    pub(crate) fn parse_id(id: &str) -> Option<Linkage> {
+        let mut parts = id.split('_');
+        let binding = match parts.next()? {
+            "unknown" => return Some(Linkage::UNKNOWN),
+            "local" => return Some(Linkage::from_binding(Binding::Local)),
+            "weak" => Binding::Weak,
+            "global" => Binding::Global,
+            _ => return None,
+        };
+        let visibility = match parts.next() {
+            None => Visibility::Unknown,
+            Some("hidden") => Visibility::Hidden,
+            Some("exported") => Visibility::Exported,
+            Some(_) => return None,
+        };
+        Some(Linkage {
+            visibility,
+            binding,
+        })
+    }
+}
+
+/// Linkages are stored by their `id`.
+impl serde::Serialize for Linkage {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.id())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Linkage {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Linkage, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Linkage::parse_id(&id)
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid linkage `{id}`")))
+    }
+}
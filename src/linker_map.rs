@@ -0,0 +1,147 @@
+//! Parsing of linker map files, so that each copy of a function can be attributed to the input file
+//! that it came from.
+//!
+//! GNU ld style maps (also produced by gold and mold) and LLD style maps are supported.
+
+use crate::DuplicationReport;
+use crate::Result;
+use anyhow::bail;
+
+/// The address ranges of input sections and the input files that they came from.
+pub struct LinkerMap {
+    /// Sorted by start address.
+    sections: Vec<InputSection>,
+}
+
+struct InputSection {
+    start: u64,
+    end: u64,
+
+    /// The input file, e.g. `foo.o` or `libfoo.a(foo-123.o)`.
+    input: String,
+}
+
+impl LinkerMap {
+    pub fn parse(text: &str) -> Result<LinkerMap> {
+        let mut sections = if let Some(header) = text.lines().find(|line| is_lld_header(line)) {
+            parse_lld(text, header)
+        } else {
+            parse_gnu(text)
+        };
+        if sections.is_empty() {
+            bail!("No input sections found. Only GNU ld and LLD style maps are supported");
+        }
+        sections.sort_by_key(|section| section.start);
+        Ok(LinkerMap { sections })
+    }
+
+    /// Returns the input file that contributed the code at `address`.
+    pub fn input_for(&self, address: u64) -> Option<&str> {
+        let index = self
+            .sections
+            .partition_point(|section| section.start <= address)
+            .checked_sub(1)?;
+        let section = &self.sections[index];
+        (address < section.end).then_some(section.input.as_str())
+    }
+
+    /// Sets the member of each function instance in `report` that doesn't already have one to the
+    /// input file that contributed it.
+    pub fn attribute(&self, report: &mut DuplicationReport) {
+        for group in &mut report.groups {
+            for instance in &mut group.instances {
+                if instance.member.is_none() {
+                    instance.member = self.input_for(instance.address).map(|i| i.to_owned());
+                }
+            }
+        }
+    }
+}
+
+fn is_lld_header(line: &str) -> bool {
+    let columns = line.split_whitespace().collect::<Vec<_>>();
+    columns.ends_with(&["Out", "In", "Symbol"])
+}
+
+/// Parses lines like:
+///
+/// ```text
+///              VMA              LMA     Size Align Out     In      Symbol
+///           201120           201120       2a    16         /tmp/foo.o:(.text.foo)
+/// ```
+///
+/// Older versions and non-ELF targets omit the LMA column.
+fn parse_lld(text: &str, header: &str) -> Vec<InputSection> {
+    let size_column = if header.contains("LMA") { 2 } else { 1 };
+    let in_column = size_column + 2;
+    text.lines()
+        .filter_map(|line| {
+            let columns = line.split_whitespace().collect::<Vec<_>>();
+            let input = columns.get(in_column)?;
+            // Input sections are the only lines where this column looks like `file:(section)`.
+            let (input, _section) = input.rsplit_once(":(")?;
+            let start = u64::from_str_radix(columns[0], 16).ok()?;
+            let size = u64::from_str_radix(columns[size_column], 16).ok()?;
+            (size > 0).then(|| InputSection {
+                start,
+                end: start + size,
+                input: input.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Parses the memory map part of a GNU ld map, which has lines like:
+///
+/// ```text
+///  .text.foo      0x0000000000401030       0x15 foo.o
+///  .text.a_very_long_section_name
+///                 0x0000000000401050       0x20 libfoo.a(bar.o)
+/// ```
+fn parse_gnu(text: &str) -> Vec<InputSection> {
+    let mut sections = Vec::new();
+    // Earlier parts of the map, such as the list of discarded sections, have lines that look
+    // similar, but don't describe the output.
+    let mut in_memory_map = false;
+    let mut pending_name = false;
+    for line in text.lines() {
+        if line.starts_with("Linker script and memory map") {
+            in_memory_map = true;
+            continue;
+        }
+        if !in_memory_map || !line.starts_with(' ') {
+            pending_name = false;
+            continue;
+        }
+        let mut columns = line.split_whitespace().collect::<Vec<_>>();
+        if columns.len() == 1 && columns[0].starts_with('.') {
+            // The name was too long, so the rest is on the next line.
+            pending_name = true;
+            continue;
+        }
+        if pending_name {
+            columns.insert(0, "");
+            pending_name = false;
+        }
+        // Skip symbol lines, `*fill*` and input section patterns.
+        if columns.len() < 4 || !columns[0].starts_with('.') && !columns[0].is_empty() {
+            continue;
+        }
+        let (Some(start), Some(size)) = (parse_hex(columns[1]), parse_hex(columns[2])) else {
+            continue;
+        };
+        if size == 0 {
+            continue;
+        }
+        sections.push(InputSection {
+            start,
+            end: start + size,
+            input: columns[3..].join(" "),
+        });
+    }
+    sections
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+    u64::from_str_radix(text.strip_prefix("0x")?, 16).ok()
+}
@@ -1,312 +1,3089 @@
 use anyhow::bail;
 use anyhow::Context;
-use clap::Parser as _;
-use object::Object as _;
-use object::ObjectSection as _;
-use object::ObjectSymbol;
-use object::SectionKind;
-use object::SymbolKind;
-use std::borrow::Cow;
-use std::collections::HashMap;
-use std::hash::Hash;
+use budgets::Budgets;
+use clap::CommandFactory as _;
+use clap::FromArgMatches as _;
+use duplicate_function_checker::cache;
+use duplicate_function_checker::collisions;
+use duplicate_function_checker::collisions::Collision;
+use duplicate_function_checker::compare;
+use duplicate_function_checker::compare::Comparison;
+use duplicate_function_checker::compare::Row;
+use duplicate_function_checker::cross_binary;
+use duplicate_function_checker::cross_binary::SharedFunction;
+use duplicate_function_checker::debug_file;
+use duplicate_function_checker::diff;
+use duplicate_function_checker::diff::GroupChange;
+use duplicate_function_checker::diff::ReportDiff;
+use duplicate_function_checker::diff::Snapshot;
+use duplicate_function_checker::disasm::Disassembler;
+use duplicate_function_checker::fuzzy;
+use duplicate_function_checker::fuzzy::SimilarCluster;
+use duplicate_function_checker::hint;
+use duplicate_function_checker::icf;
+use duplicate_function_checker::icf::IcfSummary;
+use duplicate_function_checker::input_files;
+use duplicate_function_checker::linker_map::LinkerMap;
+use duplicate_function_checker::metrics::GroupMetrics;
+use duplicate_function_checker::profile::Profile;
+use duplicate_function_checker::reachability;
+use duplicate_function_checker::reachability::Reachability;
+use duplicate_function_checker::rodata;
+use duplicate_function_checker::rodata::RodataReport;
+use duplicate_function_checker::shared_libs;
+use duplicate_function_checker::source::SourceLocations;
+use duplicate_function_checker::stats;
+use duplicate_function_checker::stats::Phase;
+use duplicate_function_checker::stats::Skip;
+use duplicate_function_checker::stats::Stats;
+use duplicate_function_checker::symbol_checks;
+use duplicate_function_checker::symbol_checks::ProblemKind;
+use duplicate_function_checker::symbol_checks::SymbolProblem;
+use duplicate_function_checker::trend;
+use duplicate_function_checker::trend::TrendEntry;
+use duplicate_function_checker::universal;
+use duplicate_function_checker::universal::Slice;
+use duplicate_function_checker::what_if;
+use duplicate_function_checker::what_if::Estimate;
+use duplicate_function_checker::Denominator;
+use duplicate_function_checker::DuplicateGroup;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::GenericRoot;
+use duplicate_function_checker::KeyFailure;
+use duplicate_function_checker::KeyType;
+use duplicate_function_checker::ModuleStats;
+use duplicate_function_checker::Options;
+use duplicate_function_checker::Progress;
+use duplicate_function_checker::Result;
+use duplicate_function_checker::SectionFilter;
+use duplicate_function_checker::SymbolSource;
+use ignore_list::IgnoreList;
+use progress::ProgressBar;
+use query::Query;
+use regex::Regex;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
 use std::io::Write as _;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::OnceLock;
 
-type Result<T = (), E = anyhow::Error> = core::result::Result<T, E>;
+mod budgets;
+mod config;
+mod csv;
+mod export_disasm;
+mod flamegraph;
+mod html;
+mod ignore_list;
+mod markdown;
+mod progress;
+mod query;
+mod sqlite;
+mod watch;
 
 /// A tool to determine what percentage of a binary's functions are excess duplicates. A symbol
 /// table is needed and functions in the symbol table need to have non-zero sizes.
 #[derive(clap::Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Input binary to parse.
-    bin: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Whether to print information about each duplicate symbol.
+    /// Input binaries to parse. When there's more than one, each is summarised, then functions that
+    /// are present in more than one of them are listed. A directory is parsed as a single input
+    /// made up of all of the object files and archives under it.
+    #[arg(required_unless_present_any = ["globs", "generate_manpage"])]
+    bins: Vec<PathBuf>,
+
+    /// Also parse the binaries matching this glob pattern, e.g. `target/release/*-service`. May be
+    /// given more than once.
+    #[arg(long = "glob", value_name = "PATTERN")]
+    globs: Vec<String>,
+
+    /// Also parse the shared libraries that each ELF binary needs, directly or indirectly, to find
+    /// functions duplicated between a binary and its libraries.
+    #[arg(long)]
+    with_deps: bool,
+
+    /// Look for shared libraries needed by `--with-deps` under this directory, rather than in the
+    /// system's library directories.
+    #[arg(long, requires = "with_deps")]
+    sysroot: Option<PathBuf>,
+
+    /// Instead of analysing anything, print a man page generated from these options, for packaging.
     #[arg(long)]
+    generate_manpage: bool,
+
+    /// Read default settings from this TOML file. By default, `duplicate-function-checker.toml` in
+    /// the current directory is used if it exists. Flags given on the command line take precedence.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Whether to print information about each duplicate symbol.
+    #[arg(long, global = true)]
     verbose: bool,
 
-    /// Whether to demangle symbol names.
-    #[arg(long)]
-    demangle: bool,
+    /// Log what the analysis is doing to stderr, separately from the report. `-v` logs timings and
+    /// how many functions were skipped, `-vv` each skipped function and why, and `-vvv` everything.
+    /// Only warnings and errors are logged by default.
+    #[arg(short = 'v', action = clap::ArgAction::Count, global = true)]
+    log_level: u8,
+
+    /// The format of log messages.
+    #[arg(long, global = true, default_value = "text")]
+    log_format: LogFormat,
+
+    /// Don't show a progress bar while functions are being keyed.
+    #[arg(long, global = true)]
+    quiet: bool,
+
+    /// Keep running, and each time that the binary changes, analyse it again and print how excess
+    /// bytes and duplicate groups changed since the previous build. With `--verbose`, the groups
+    /// that changed are listed.
+    #[arg(long)]
+    watch: bool,
+
+    /// Print how long each phase took, peak memory use and how many functions were skipped and
+    /// why to stderr when done.
+    #[arg(long, global = true)]
+    stats: bool,
+
+    /// List each function that wasn't checked because its instructions couldn't be decoded or
+    /// re-encoded, with the error.
+    #[arg(long)]
+    strict: bool,
+
+    /// Whether to demangle symbol names.
+    #[arg(long, global = true)]
+    demangle: bool,
+
+    /// Whether to demangle symbol names and drop rust's hashes. Names in a group that only differ
+    /// in their hash are then listed once, with a count.
+    #[arg(long, global = true)]
+    demangle_no_hash: bool,
+
+    /// What to key functions by.
+    #[arg(long, default_value = "instructions", global = true)]
+    key: KeyType,
+
+    /// Key functions by running this program instead, e.g. to try out a custom canonicalisation. It
+    /// gets a line of JSON on stdin for each function, with its name, size and bytes in hex, and
+    /// must print a line with its key, or an empty line to leave it out.
+    #[arg(long, value_name = "PROGRAM", global = true)]
+    key_cmd: Option<PathBuf>,
+
+    /// Don't ignore trailing `int3`/`nop` padding or differences in how NOPs are encoded when
+    /// comparing instructions.
+    #[arg(long, global = true)]
+    keep_padding: bool,
+
+    /// Count symbols with the same address and size, such as aliases, as separate copies rather
+    /// than as one function with several names.
+    #[arg(long, global = true)]
+    keep_aliases: bool,
+
+    /// Which architecture to analyse in a Mach-O universal binary, e.g. `arm64` or
+    /// `aarch64-apple-darwin`. By default, each architecture is analysed and reported separately.
+    #[arg(long, value_name = "TRIPLE", global = true)]
+    arch: Option<String>,
+
+    /// Check PLT stubs and IFUNC resolvers like other functions and count PLT sections towards the
+    /// total size. By default, they're left out and their size is reported separately.
+    #[arg(long, global = true)]
+    include_plt: bool,
+
+    /// Infer the sizes of functions whose symbols have a size of zero from the address of the next
+    /// symbol or the end of the section. Mach-O sizes are always inferred.
+    #[arg(long, global = true)]
+    infer_sizes: bool,
+
+    /// Key functions by a 128 bit hash of their normalised instructions, rather than keeping the
+    /// instructions themselves, which uses much less memory for huge binaries.
+    #[arg(long, global = true)]
+    hash_keys: bool,
+
+    /// With `--hash-keys`, check that functions with the same hash really are the same.
+    #[arg(long, global = true, requires = "hash_keys")]
+    verify_hashes: bool,
+
+    /// Don't read or write cached results. Results are cached under `$XDG_CACHE_HOME`, keyed by
+    /// the contents of the binary and the flags that affect them.
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// What to sort results by.
+    #[arg(long, default_value = "excess-bytes", global = true)]
+    sort: SortType,
+
+    /// Output format.
+    #[arg(long, default_value = "text", global = true)]
+    format: OutputFormat,
+
+    /// Print sizes as exact byte counts, rather than rounded to KiB, MiB etc, so that small
+    /// regressions in large binaries show up.
+    #[arg(long, global = true)]
+    bytes: bool,
+
+    /// How many decimal places to print rounded sizes with.
+    #[arg(long, value_name = "DIGITS", default_value_t = 1, global = true)]
+    size_precision: usize,
+
+    /// Write the report to this file rather than to stdout.
+    #[arg(long, global = true)]
+    output: Option<PathBuf>,
+
+    /// Only show the N worst duplicate groups according to `--sort`.
+    #[arg(long)]
+    top: Option<usize>,
+
+    /// Comma-separated names of the executable sections to count and look for functions in. Names
+    /// prefixed with `!` are excluded and a trailing `*` matches any suffix, e.g.
+    /// `.text*,!.text.unlikely*`. By default, all executable sections are used.
+    #[arg(long, value_name = "SECTIONS", global = true)]
+    sections: Option<SectionFilter>,
+
+    /// Only look for functions within this range of hexadecimal addresses, e.g.
+    /// `0x8000000-0x8040000` for a region of firmware placed by a linker script. Totals are
+    /// computed against the size of the range rather than of all executable sections.
+    #[arg(long, value_name = "START-END", value_parser = parse_range, global = true)]
+    range: Option<Range<u64>>,
+
+    /// Don't list duplicate groups whose functions are smaller than this many bytes.
+    #[arg(long, value_name = "BYTES")]
+    min_size: Option<u64>,
+
+    /// Don't list duplicate groups with fewer than this many copies.
+    #[arg(long, value_name = "N")]
+    min_copies: Option<u64>,
+
+    /// Also leave the groups excluded by `--min-size` and `--min-copies` out of the totals.
+    #[arg(long)]
+    min_applies_to_totals: bool,
+
+    /// Only consider functions whose demangled name matches this regular expression.
+    #[arg(long, global = true)]
+    filter: Option<Regex>,
+
+    /// Ignore functions whose demangled name matches this regular expression.
+    #[arg(long, global = true)]
+    exclude: Option<Regex>,
+
+    /// Ignore functions from `core`, `alloc` and `std`, including instantiations of their generics
+    /// such as `core::ptr::drop_in_place<T>`.
+    #[arg(long, global = true)]
+    ignore_std: bool,
+
+    /// Ignore functions from `compiler_builtins`. Its unmangled exports, such as `memcpy`, aren't
+    /// ignored, since they can't be told apart from other C functions.
+    #[arg(long, global = true)]
+    ignore_compiler_builtins: bool,
+
+    /// Whether to print excess bytes grouped by the crate that the duplicated function comes from.
+    #[arg(long)]
+    by_crate: bool,
+
+    /// Print excess bytes grouped by crate and then module, as a tree that's DEPTH levels deep,
+    /// e.g. `--by-module=3`. The depth defaults to 2. A depth of 1 is the same as `--by-crate`.
+    /// Respects `--top` at each level.
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "2"
+    )]
+    by_module: Option<usize>,
+
+    /// Whether to print excess bytes grouped by the kind of function, e.g. drop glue, shims,
+    /// closures and user code.
+    #[arg(long)]
+    by_category: bool,
+
+    /// Whether to print the size and excess bytes of each executable section, e.g. to see whether
+    /// duplicates are in hot code or in cold partitions like `.text.unlikely`.
+    #[arg(long)]
+    by_section: bool,
+
+    /// Whether to print excess bytes grouped by the binding and visibility of the duplicates'
+    /// symbols, e.g. to see how many could be folded by the linker.
+    #[arg(long)]
+    by_linkage: bool,
+
+    /// Whether to print how many duplicate groups and excess bytes there are for each range of
+    /// function sizes.
+    #[arg(long)]
+    histogram: bool,
+
+    /// Whether to print the total size of each generic function across all of its instantiations,
+    /// not just exact duplicates. Respects `--top`.
+    #[arg(long)]
+    by_generic: bool,
+
+    /// Whether to print the disassembly of one copy of each duplicate group. Implies `--verbose`.
+    #[arg(long)]
+    show_disasm: bool,
+
+    /// Write the current duplicate groups to this file, for later use with `--baseline`.
+    #[arg(long)]
+    write_baseline: Option<PathBuf>,
+
+    /// Compare against a file previously written with `--write-baseline` and report duplicate
+    /// groups that are new or have more copies. Groups are matched by ID, then by name.
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Estimate how much a linker's identical code folding could save, including functions that
+    /// only differ in calling different copies of foldable functions.
+    #[arg(long)]
+    icf_simulate: bool,
+
+    /// Write the symbols of each group of byte-identical functions to this file, one group per
+    /// line, so that a post-link tool can fold them. The first symbol on each line is the copy to
+    /// keep.
+    #[arg(long, value_name = "FILE")]
+    emit_icf_symbols: Option<PathBuf>,
+
+    /// Write every function that was checked to this file, one JSON object per line, with its
+    /// name, demangled name, address, size and the number and ID of its group, so that other tools
+    /// can join group membership against their own data.
+    #[arg(long, value_name = "FILE")]
+    emit_symbol_index: Option<PathBuf>,
+
+    /// Write a JSON file to this directory for each duplicate group, with what's known about it and
+    /// the disassembly of one copy, for attaching to an issue or reviewing. Respects `--top`.
+    #[arg(long, value_name = "DIR")]
+    export_disasm: Option<PathBuf>,
+
+    /// Follow calls and function pointers from the entry point and exported symbols to find how
+    /// much duplication is in unreachable code, which `--gc-sections` would already remove.
+    #[arg(long)]
+    reachability: bool,
+
+    /// Also report clusters of distinct functions whose instruction sequences are at least this
+    /// percentage similar, e.g. generic instantiations that only differ in a constant.
+    #[arg(long, value_name = "PERCENT")]
+    fuzzy: Option<f64>,
+
+    /// List names that are shared by functions with different contents, ignoring rustc's hash,
+    /// e.g. distinct monomorphisations or unrelated static functions. Respects `--top`.
+    #[arg(long)]
+    collisions: bool,
+
+    /// Check function symbols for overlaps, for extending past the end of their section and for
+    /// disagreeing about the size of the function at an address, any of which would distort excess
+    /// bytes. Respects `--top`.
+    #[arg(long)]
+    verify: bool,
+
+    /// Estimate side by side how much exact ICF, ICF with call-target folding, sharing generics
+    /// and merging near-duplicates could each save. Near-duplicates use the `--fuzzy` threshold,
+    /// or 90% if it isn't given.
+    #[arg(long)]
+    what_if: bool,
+
+    /// A CPU profile of the binary, as written by `perf script -F ip`. Sampled duplicate groups are
+    /// ranked by their excess bytes weighted by their samples, since hot duplicates waste
+    /// instruction cache. Respects `--top`.
+    #[arg(long)]
+    profile: Option<PathBuf>,
+
+    /// Subtracted from the profile's addresses. For position-independent binaries, this is the
+    /// address that the binary was loaded at, since `perf` records run-time addresses.
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address, default_value = "0")]
+    profile_bias: u64,
+
+    /// A GNU ld or LLD style map file produced when linking the binary. Used to show which input
+    /// file contributed each copy of a function.
+    #[arg(long)]
+    map: Option<PathBuf>,
+
+    /// Read functions from this PDB rather than from the symbol table. By default, a PDB next to a
+    /// PE binary is used if there is one.
+    #[arg(long)]
+    pdb: Option<PathBuf>,
+
+    /// Read functions from this separate debug info file rather than from the symbol table. By
+    /// default, stripped ELF binaries have their debug info looked for by build ID and debuglink
+    /// in the same places as gdb, then in `<binary>.debug`.
+    #[arg(long)]
+    debug_file: Option<PathBuf>,
+
+    /// Read function names, addresses and sizes from this `nm -S` or `objdump -t` listing rather
+    /// than from the symbol table.
+    #[arg(long)]
+    symbols_file: Option<PathBuf>,
+
+    /// Which symbol table to read functions from. `auto` uses the symbol table, falling back to
+    /// debug info, unwind tables and then the dynamic symbol table if it's missing. `both` merges
+    /// the two tables, adding dynamic symbols at addresses that the symbol table doesn't have.
+    #[arg(long, value_name = "SOURCE", default_value = "auto")]
+    symbols: SymbolSource,
+
+    /// A debuginfod server to fetch debug info from if a stripped binary's debug info can't be
+    /// found locally, e.g. `https://debuginfod.elfutils.org`.
+    #[arg(long, value_name = "URL")]
+    debuginfod_url: Option<String>,
+
+    /// Exit with an error if excess bytes exceed this percentage of executable code, or of whatever
+    /// `--denominator` selects.
+    #[arg(long)]
+    max_excess_percent: Option<f64>,
+
+    /// What excess bytes are given as a percentage of: executable code, the size of the file on
+    /// disk or the in-memory size of its loadable segments.
+    #[arg(long, default_value = "text", global = true)]
+    denominator: Denominator,
+
+    /// Exit with an error if excess bytes exceed this many bytes.
+    #[arg(long)]
+    max_excess_bytes: Option<u64>,
+
+    /// Exit with an error if any crate's excess bytes exceed its allowance in this TOML file, which
+    /// maps crate names, optionally containing `*`, to the maximum excess bytes for each crate.
+    #[arg(long)]
+    budgets: Option<PathBuf>,
+
+    /// Leave duplicate groups with a name matching a pattern in this file out of totals and limits,
+    /// listing them as ignored instead. Each line is a pattern, optionally containing `*`, or a
+    /// regular expression starting with `regex:`.
+    #[arg(long, value_name = "FILE")]
+    ignore_file: Option<PathBuf>,
+
+    /// Only report and count duplicate groups matching this expression, e.g.
+    /// `copies > 4 && size > 1024 && name ~ "serde"`. Fields are `copies`, `size`, `excess`,
+    /// `name`, `crate`, `section` and `id`. See the README for details.
+    #[arg(long = "where", value_name = "EXPR")]
+    query: Option<Query>,
+
+    /// Also estimate the savings if each function starts on a boundary of this many bytes, e.g. 16
+    /// or 32, as removing a copy removes the padding after it too.
+    #[arg(long, value_name = "BYTES", value_parser = parse_alignment)]
+    alignment: Option<u64>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Compare duplicate groups between two binaries. Groups are matched by ID, then by name for
+    /// those whose ID changed, so `--demangle-no-hash` is recommended when the binaries come from
+    /// different builds.
+    Diff {
+        /// The binary from before the change.
+        old: PathBuf,
+
+        /// The binary from after the change.
+        new: PathBuf,
+    },
+
+    /// Instead of the full report, list every function whose name matches a pattern, whether or not
+    /// it's duplicated, along with the group of identical functions that it's in.
+    Find {
+        /// A regular expression to match against demangled names.
+        pattern: Regex,
+
+        /// The binary to search.
+        bin: PathBuf,
+    },
+
+    /// Instead of functions, look for duplicated constants, such as string literals, in read-only
+    /// data. Most constants only have their own section in object files, so this works best on
+    /// rlibs or a directory of objects such as `target/release/deps`.
+    Rodata {
+        /// The binary, archive or directory of objects to search.
+        bin: PathBuf,
+    },
+
+    /// Show how duplication has changed over time, from a history that each run can append to. If
+    /// a binary is given, it's analysed and its summary is added to the history first.
+    Trend {
+        /// The history file, with one JSON object per run. It's created if it doesn't exist.
+        #[arg(long, value_name = "FILE")]
+        history: PathBuf,
+
+        /// The commit that the binary was built from, to identify its run in the history.
+        #[arg(long)]
+        commit: Option<String>,
+
+        /// An identifier for the build, e.g. a CI build number, used if there's no commit.
+        #[arg(long)]
+        build_id: Option<String>,
+
+        /// The binary to analyse and add to the history.
+        bin: Option<PathBuf>,
+    },
+
+    /// Check whether a function is the same in two binaries and show a side-by-side diff of its
+    /// disassembly. References to other functions and data are compared by symbol name, not
+    /// address.
+    CompareFn {
+        /// The binary from before the change.
+        old: PathBuf,
+
+        /// The binary from after the change.
+        new: PathBuf,
+
+        /// The name of the function, either mangled or as it's displayed.
+        symbol: String,
+    },
+
+    /// Print a script that completes this tool's subcommands and options in a shell, e.g. for
+    /// bash, `duplicate-function-checker completions bash >
+    /// /usr/share/bash-completion/completions/duplicate-function-checker`.
+    Completions {
+        /// The shell to complete in.
+        shell: clap_complete::Shell,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+enum SortType {
+    /// Sort by excess bytes of function in the binary.
+    ExcessBytes,
+
+    /// Sort by number of copies.
+    Copies,
+
+    /// Sort by function size.
+    Size,
+
+    /// Sort by name, using the smallest of each group's names once demangled without rustc's hash,
+    /// so that reports on slightly different binaries can be diffed as text.
+    Name,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable text.
+    Text,
+
+    /// A single JSON object containing the summary and all duplicate groups.
+    Json,
+
+    /// A standalone HTML page with summary charts and a sortable table of duplicate groups.
+    Html,
+
+    /// A single JSON object with just the headline numbers. The schema is documented in the README
+    /// and versioned by `schema_version`.
+    JsonSummary,
+
+    /// Rows appended to the SQLite database given by `--output`, with a run for each binary.
+    Sqlite,
+
+    /// Folded stacks of crate, modules and function with excess bytes as the count, for tools
+    /// such as inferno to draw as a flamegraph.
+    Flamegraph,
+
+    /// A row of comma-separated values for each duplicate group, worst first.
+    Csv,
+
+    /// A GitHub-flavored Markdown summary and table of the worst groups, for posting as a comment
+    /// on a pull request. Shows `--top` groups, or 20 if it isn't given.
+    Markdown,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
+enum LogFormat {
+    /// A line of text for each message.
+    Text,
+
+    /// A JSON object for each message, for collecting in CI logs.
+    Json,
+}
+
+/// The similarity above which `--what-if` considers functions to be near-duplicates, if `--fuzzy`
+/// isn't given.
+const DEFAULT_SIMILARITY_PERCENT: f64 = 90.0;
+
+/// Incremented whenever a field of the `json-summary` output is removed or changes meaning. Adding
+/// fields doesn't change the version.
+const SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+fn main() -> Result {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    // These describe the command line itself, so don't depend on a config file.
+    if let Some(Command::Completions { shell }) = args.command {
+        let mut out = std::io::stdout().lock();
+        clap_complete::generate(
+            shell,
+            &mut Args::command(),
+            env!("CARGO_BIN_NAME"),
+            &mut out,
+        );
+        return Ok(out.flush()?);
+    }
+    if args.generate_manpage {
+        let mut out = std::io::stdout().lock();
+        clap_mangen::Man::new(Args::command()).render(&mut out)?;
+        return Ok(out.flush()?);
+    }
+    config::apply(&mut args, &matches)?;
+    init_logging(&args);
+    SIZE_FORMAT
+        .set(SizeFormat {
+            exact: args.bytes,
+            precision: args.size_precision,
+        })
+        .unwrap();
+    let stats = args.stats.then(|| Arc::new(Stats::default()));
+    let result = run(args, stats.clone());
+    if let Some(stats) = &stats {
+        print_stats(&mut std::io::stderr(), stats)?;
+    }
+    result
+}
+
+fn init_logging(args: &Args) {
+    let level = match args.log_level {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::INFO,
+        2 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level);
+    match args.log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+fn run(args: Args, stats: Option<Arc<Stats>>) -> Result {
+    let options = Options {
+        key: args.key,
+        key_cmd: args.key_cmd.clone(),
+        denominator: args.denominator,
+        demangle: args.demangle,
+        demangle_no_hash: args.demangle_no_hash,
+        filter: args.filter.clone(),
+        exclude: args.exclude.clone(),
+        ignore_std: args.ignore_std,
+        ignore_compiler_builtins: args.ignore_compiler_builtins,
+        pdb: args.pdb.clone(),
+        debug_file: args.debug_file.clone(),
+        symbols_file: args.symbols_file.clone(),
+        symbols: args.symbols,
+        sections: args.sections.clone().unwrap_or_default(),
+        address_range: args.range.clone(),
+        arch: args.arch.clone(),
+        keep_padding: args.keep_padding,
+        keep_aliases: args.keep_aliases,
+        include_plt: args.include_plt,
+        infer_sizes: args.infer_sizes,
+        hash_keys: args.hash_keys,
+        verify_hashes: args.verify_hashes,
+        cache_dir: if args.no_cache {
+            None
+        } else {
+            cache::default_dir()
+        },
+        progress: None,
+        stats,
+    };
+    let progress = (!args.quiet).then(|| Arc::new(ProgressBar::new()));
+    let options = Options {
+        progress: progress.clone().map(|p| p as Arc<dyn Progress>),
+        ..options
+    };
+    if let Some(Command::Diff { old, new }) = &args.command {
+        let old = analyze(old, &options)?;
+        let new = analyze(new, &options)?;
+        finish_progress(progress.as_deref());
+        let diff = diff::diff(&old, &new);
+        let mut out = open_output(&args)?;
+        match args.format {
+            OutputFormat::Text => print_diff(&mut out, &diff, &args)?,
+            OutputFormat::Json => print_diff_json(&mut out, &diff)?,
+            OutputFormat::Html => bail!("HTML output isn't supported when diffing"),
+            OutputFormat::JsonSummary => bail!("json-summary output isn't supported when diffing"),
+            OutputFormat::Sqlite => bail!("SQLite output isn't supported when diffing"),
+            OutputFormat::Flamegraph => bail!("Flamegraph output isn't supported when diffing"),
+            OutputFormat::Csv => bail!("CSV output isn't supported when diffing"),
+            OutputFormat::Markdown => markdown::write_diff(&mut out, &diff)?,
+        }
+        return Ok(out.flush()?);
+    }
+    if let Some(Command::Find { pattern, bin }) = &args.command {
+        if args.format != OutputFormat::Text {
+            bail!("Only text output is supported by `find`");
+        }
+        // Patterns are written against demangled names.
+        let options = Options {
+            demangle: !options.demangle_no_hash,
+            ..options
+        };
+        let report = analyze(bin, &options)?;
+        finish_progress(progress.as_deref());
+        let mut out = open_output(&args)?;
+        print_matches(&mut out, &report, pattern)?;
+        return Ok(out.flush()?);
+    }
+    if let Some(Command::Rodata { bin }) = &args.command {
+        let report = rodata::find_duplicate_constants(bin, &options)
+            .with_context(|| format!("Failed to process `{}`", bin.display()))?;
+        finish_progress(progress.as_deref());
+        let mut out = open_output(&args)?;
+        match args.format {
+            OutputFormat::Text => print_rodata(&mut out, &report, &args)?,
+            OutputFormat::Json => print_rodata_json(&mut out, &report)?,
+            _ => bail!("Only text and JSON output are supported by `rodata`"),
+        }
+        return Ok(out.flush()?);
+    }
+    if let Some(Command::Trend {
+        history,
+        commit,
+        build_id,
+        bin,
+    }) = &args.command
+    {
+        if let Some(bin) = bin {
+            let report = analyze(bin, &options)?;
+            finish_progress(progress.as_deref());
+            let entry =
+                TrendEntry::from_report(&report, bin, commit.as_deref(), build_id.as_deref());
+            trend::append(history, &entry)?;
+        }
+        let entries = trend::load(history)?;
+        let mut out = open_output(&args)?;
+        match args.format {
+            OutputFormat::Text => print_trend(&mut out, &entries)?,
+            OutputFormat::Json => print_trend_json(&mut out, &entries)?,
+            _ => bail!("Only text and JSON output are supported by `trend`"),
+        }
+        return Ok(out.flush()?);
+    }
+    if let Some(Command::CompareFn { old, new, symbol }) = &args.command {
+        if args.format != OutputFormat::Text {
+            bail!("Only text output is supported by `compare-fn`");
+        }
+        let options = Options {
+            demangle: !options.demangle_no_hash,
+            progress: None,
+            ..options
+        };
+        let listing = |path: &Path| {
+            let data = duplicate_function_checker::read_input(path)
+                .with_context(|| format!("Failed to read `{}`", path.display()))?;
+            let data = universal::select(&data, options.arch.as_deref())?;
+            compare::listing(data, symbol, &options)
+                .with_context(|| format!("Failed to process `{}`", path.display()))
+        };
+        let comparison = Comparison {
+            old: listing(old)?,
+            new: listing(new)?,
+        };
+        let mut out = open_output(&args)?;
+        print_comparison(&mut out, &comparison, symbol)?;
+        return Ok(out.flush()?);
+    }
+    // Find out about a missing database before spending time on the analysis.
+    if args.format == OutputFormat::Sqlite && args.output.is_none() {
+        bail!("SQLite output needs `--output` to specify the database");
+    }
+    let budgets = args.budgets.as_deref().map(Budgets::load).transpose()?;
+    let ignore_list = args
+        .ignore_file
+        .as_deref()
+        .map(IgnoreList::load)
+        .transpose()?;
+    let inputs = input_paths(&args)?;
+    let bin = match inputs.as_slice() {
+        [] => bail!("No input binary specified"),
+        [bin] => bin,
+        _ => {
+            return analyze_many(
+                &inputs,
+                &options,
+                progress.as_deref(),
+                budgets.as_ref(),
+                &args,
+            )
+        }
+    };
+    if args.watch {
+        if bin.is_dir() {
+            bail!("`--watch` needs a binary, not a directory");
+        }
+        return watch::watch(bin, &options, progress.as_deref(), &args);
+    }
+    if bin.is_dir() {
+        return analyze_object_files(
+            bin,
+            &options,
+            progress.as_deref(),
+            budgets.as_ref(),
+            ignore_list.as_ref(),
+            &args,
+        );
+    }
+    let data = duplicate_function_checker::timed(&options, Phase::Read, || {
+        duplicate_function_checker::read_input(bin)
+    })
+    .with_context(|| format!("Failed to read `{}`", bin.display()))?;
+    if input_files::is_thin_archive(&data) {
+        return analyze_object_files(
+            bin,
+            &options,
+            progress.as_deref(),
+            budgets.as_ref(),
+            ignore_list.as_ref(),
+            &args,
+        );
+    }
+    if args.arch.is_none() {
+        if let Some(slices) = universal::slices(&data)? {
+            return analyze_universal(
+                &slices,
+                &options,
+                progress.as_deref(),
+                budgets.as_ref(),
+                ignore_list.as_ref(),
+                &args,
+            );
+        }
+    }
+    let data = universal::select(&data, args.arch.as_deref())?;
+    // Resolve the PDB and debug file up front, so that the extra analyses below use them too.
+    let mut options = options;
+    if options.symbols == SymbolSource::Auto {
+        if options.pdb.is_none() {
+            options.pdb = duplicate_function_checker::sibling_pdb(bin, data);
+        }
+        if options.debug_file.is_none() {
+            options.debug_file = debug_file::find(bin, data, args.debuginfod_url.as_deref())
+                .with_context(|| format!("Failed to find debug info for `{}`", bin.display()))?;
+        }
+    }
+    let mut report = duplicate_function_checker::analyze_data(data, &options)
+        .with_context(|| format!("Failed to process `{}`", bin.display()))?;
+    finish_progress(progress.as_deref());
+    // The other analyses don't report progress, so the finished bar shouldn't be resurrected.
+    options.progress = None;
+    if let Some(path) = &args.map {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read `{}`", path.display()))?;
+        LinkerMap::parse(&text)
+            .with_context(|| format!("Failed to parse linker map `{}`", path.display()))?
+            .attribute(&mut report);
+    }
+    write_symbol_index(&report, &args)?;
+    apply_group_filters(&mut report, &args);
+    let ignored = ignore_list.map(|list| list.apply(&mut report));
+    // Source locations are only shown alongside the list of duplicates.
+    let lists_duplicates = args.verbose
+        || args.export_disasm.is_some()
+        || matches!(args.format, OutputFormat::Json | OutputFormat::Html);
+    let sources = if lists_duplicates {
+        // A stripped binary's DWARF lives in its debug file along with its symbols.
+        let debug_data = options
+            .debug_file
+            .as_ref()
+            .map(|path| {
+                duplicate_function_checker::read_input(path)
+                    .with_context(|| format!("Failed to read `{}`", path.display()))
+            })
+            .transpose()?;
+        SourceLocations::from_data(debug_data.as_deref().unwrap_or(data))
+            .with_context(|| format!("Failed to read debug info for `{}`", bin.display()))?
+    } else {
+        None
+    };
+    let metrics = lists_duplicates
+        .then(|| GroupMetrics::from_data(data, &options))
+        .flatten();
+    let disassembler = if args.show_disasm && args.format == OutputFormat::Text {
+        Disassembler::from_data(data)?
+    } else {
+        None
+    };
+    let details = GroupDetails {
+        sources: sources.as_ref(),
+        metrics: metrics.as_ref(),
+        disassembler: disassembler.as_ref(),
+    };
+    // Other analyses can take a while, so in the meantime, show the groups that we've found.
+    let mut out = open_output(&args)?;
+    if args.format == OutputFormat::Text && (args.verbose || args.show_disasm) {
+        let mut symbols = sorted_duplicates(&report, &args);
+        let omitted = split_top(&mut symbols, args.top);
+        print_duplicates(&mut out, &report, symbols, &omitted, &args, details)?;
+        out.flush()?;
+    }
+    if let Some(path) = &args.write_baseline {
+        let mut file = std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create `{}`", path.display()))?,
+        );
+        Snapshot::from_report(&report).write_json(&mut file)?;
+        file.flush()?;
+    }
+    let baseline_diff = args
+        .baseline
+        .as_ref()
+        .map(|path| -> Result<ReportDiff> {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read `{}`", path.display()))?;
+            let baseline = Snapshot::from_json(&text)
+                .with_context(|| format!("Failed to parse baseline `{}`", path.display()))?;
+            Ok(diff::diff_snapshots(
+                &baseline,
+                &Snapshot::from_report(&report),
+            ))
+        })
+        .transpose()?;
+    let icf = args
+        .icf_simulate
+        .then(|| -> Result<IcfSummary> {
+            icf::simulate_icf(data, &options)
+                .with_context(|| format!("Failed to simulate ICF for `{}`", bin.display()))
+        })
+        .transpose()?;
+    if let Some(path) = &args.emit_icf_symbols {
+        let groups = icf::byte_identical_groups(data, &options)
+            .with_context(|| format!("Failed to find foldable functions in `{}`", bin.display()))?;
+        let mut file = std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create `{}`", path.display()))?,
+        );
+        write_icf_symbols(&mut file, &groups)?;
+        file.flush()?;
+    }
+    if let Some(dir) = &args.export_disasm {
+        export_disasm::export_groups(dir, data, &report, &args, details)
+            .with_context(|| format!("Failed to export disassembly of `{}`", bin.display()))?;
+    }
+    // Groups that only differ in register allocation need outlining rather than ICF, so we
+    // compare against grouping by exact instructions to tell them apart.
+    let register_only_bytes = (options.key == KeyType::InstructionShape)
+        .then(|| -> Result<u64> {
+            let exact_options = Options {
+                key: KeyType::Instructions,
+                ..options.clone()
+            };
+            let mut exact = duplicate_function_checker::analyze_data(data, &exact_options)
+                .with_context(|| format!("Failed to process `{}`", bin.display()))?;
+            apply_group_filters(&mut exact, &args);
+            Ok(report.excess_bytes().saturating_sub(exact.excess_bytes()))
+        })
+        .transpose()?;
+    let reachability = args
+        .reachability
+        .then(|| -> Result<Reachability> {
+            reachability::find_reachable(data, &options)
+                .with_context(|| format!("Failed to find reachable fns in `{}`", bin.display()))
+        })
+        .transpose()?;
+    let similar = args
+        .fuzzy
+        .map(|percent| -> Result<Vec<SimilarCluster>> {
+            fuzzy::find_similar(data, &options, percent / 100.0)
+                .with_context(|| format!("Failed to find similar functions in `{}`", bin.display()))
+        })
+        .transpose()?;
+    let collisions = args
+        .collisions
+        .then(|| -> Result<Vec<Collision>> {
+            collisions::find_collisions(data, &options)
+                .with_context(|| format!("Failed to find name collisions in `{}`", bin.display()))
+        })
+        .transpose()?;
+    let symbol_problems = args
+        .verify
+        .then(|| -> Result<Vec<SymbolProblem>> {
+            symbol_checks::check_symbols(data, &options)
+                .with_context(|| format!("Failed to check the symbols of `{}`", bin.display()))
+        })
+        .transpose()?;
+    let what_if = args
+        .what_if
+        .then(|| -> Result<Vec<Estimate>> {
+            let percent = args.fuzzy.unwrap_or(DEFAULT_SIMILARITY_PERCENT);
+            what_if::estimate(data, &options, percent / 100.0)
+                .with_context(|| format!("Failed to estimate savings for `{}`", bin.display()))
+        })
+        .transpose()?;
+    let profile = args
+        .profile
+        .as_ref()
+        .map(|path| -> Result<Profile> {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read `{}`", path.display()))?;
+            Profile::parse(&text, args.profile_bias)
+                .with_context(|| format!("Failed to parse profile `{}`", path.display()))
+        })
+        .transpose()?;
+    let extras = Extras {
+        baseline_diff,
+        icf,
+        register_only_bytes,
+        reachability,
+        similar,
+        collisions,
+        symbol_problems,
+        ignored,
+        what_if,
+        profile,
+        sources,
+        metrics,
+    };
+    duplicate_function_checker::timed(&options, Phase::Reporting, || -> Result {
+        match args.format {
+            OutputFormat::Text => print_report(&mut out, &report, &extras, &args)?,
+            OutputFormat::Json => print_report_json(&mut out, &report, &extras, &args)?,
+            OutputFormat::Html => html::write_report(&mut out, bin, &report, &extras, &args)?,
+            OutputFormat::JsonSummary => print_summary_json(&mut out, &report)?,
+            OutputFormat::Sqlite => sqlite::write_reports(&[(bin, &report)], &args)?,
+            OutputFormat::Flamegraph => flamegraph::write_folded(&mut out, &report, &args)?,
+            OutputFormat::Csv => csv::write_groups(&mut out, &report, &args)?,
+            OutputFormat::Markdown => {
+                markdown::write_report(&mut out, bin, &report, &extras, &args)?
+            }
+        }
+        Ok(out.flush()?)
+    })?;
+    check_limits(&report, budgets.as_ref(), &args)
+}
+
+/// Returns the binaries given on the command line followed by those matching `--glob`. With
+/// `--with-deps`, each is followed by the shared libraries that it needs.
+fn input_paths(args: &Args) -> Result<Vec<PathBuf>> {
+    let mut paths = args.bins.clone();
+    for pattern in &args.globs {
+        let matches = glob::glob(pattern)
+            .with_context(|| format!("Invalid glob pattern `{pattern}`"))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            bail!("`{pattern}` didn't match any files");
+        }
+        paths.extend(matches);
+    }
+    if args.with_deps {
+        let mut with_deps = Vec::new();
+        for path in paths {
+            let deps =
+                shared_libs::dependencies(&path, args.sysroot.as_deref()).with_context(|| {
+                    format!("Failed to find shared libraries of `{}`", path.display())
+                })?;
+            with_deps.push(path);
+            for dep in deps {
+                if !with_deps.contains(&dep) {
+                    with_deps.push(dep);
+                }
+            }
+        }
+        paths = with_deps;
+    }
+    Ok(paths)
+}
+
+/// Summarises each of `paths`, then reports functions that are present in more than one of them.
+/// Analyses a directory or thin archive, whose functions come from several object files. Analyses
+/// that need a single linked binary aren't available.
+/// Inputs other than a single binary, which some flags can't be used with.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OtherInput {
+    /// A directory of object files or a thin archive, which are analysed as one report.
+    ObjectFiles,
+
+    /// More than one input, each with its own report.
+    Many,
+
+    /// A universal binary without `--arch`, which gets a report for each architecture.
+    Universal,
+}
+
+impl OtherInput {
+    /// Why a flag can't be used with this input.
+    fn restriction(self) -> &'static str {
+        match self {
+            OtherInput::ObjectFiles => "can't be used with a directory or thin archive",
+            OtherInput::Many => "can only be used with a single binary",
+            OtherInput::Universal => {
+                "needs `--arch` to pick an architecture from a universal binary"
+            }
+        }
+    }
+}
+
+/// Fails if a flag that needs a single binary was given for `input`. Each flag is listed with the
+/// other inputs that it works with.
+fn check_single_binary_flags(args: &Args, input: OtherInput) -> Result {
+    // Flags that only change how a report is printed or checked work wherever there are reports.
+    const REPORTS: &[OtherInput] = &[OtherInput::ObjectFiles, OtherInput::Universal];
+    let flags: [(&str, bool, &[OtherInput]); 28] = [
+        ("--map", args.map.is_some(), &[]),
+        ("--pdb", args.pdb.is_some(), &[]),
+        ("--debug-file", args.debug_file.is_some(), &[]),
+        ("--symbols-file", args.symbols_file.is_some(), &[]),
+        ("--baseline", args.baseline.is_some(), &[]),
+        ("--write-baseline", args.write_baseline.is_some(), &[]),
+        ("--icf-simulate", args.icf_simulate, &[]),
+        ("--emit-icf-symbols", args.emit_icf_symbols.is_some(), &[]),
+        (
+            "--emit-symbol-index",
+            args.emit_symbol_index.is_some(),
+            &[OtherInput::ObjectFiles],
+        ),
+        ("--export-disasm", args.export_disasm.is_some(), &[]),
+        ("--watch", args.watch, &[]),
+        ("--reachability", args.reachability, &[]),
+        ("--fuzzy", args.fuzzy.is_some(), &[]),
+        ("--collisions", args.collisions, &[]),
+        ("--verify", args.verify, &[]),
+        ("--what-if", args.what_if, &[]),
+        ("--profile", args.profile.is_some(), &[]),
+        ("--show-disasm", args.show_disasm, &[]),
+        ("--by-crate", args.by_crate, REPORTS),
+        ("--by-module", args.by_module.is_some(), REPORTS),
+        ("--by-category", args.by_category, REPORTS),
+        ("--by-section", args.by_section, REPORTS),
+        ("--by-linkage", args.by_linkage, REPORTS),
+        ("--histogram", args.histogram, REPORTS),
+        ("--by-generic", args.by_generic, REPORTS),
+        ("--strict", args.strict, REPORTS),
+        ("--ignore-file", args.ignore_file.is_some(), REPORTS),
+        ("--alignment", args.alignment.is_some(), REPORTS),
+    ];
+    let unsupported = flags
+        .iter()
+        .find(|(_, used, works_with)| *used && !works_with.contains(&input));
+    if let Some((flag, ..)) = unsupported {
+        bail!("{flag} {}", input.restriction());
+    }
+    Ok(())
+}
+
+fn analyze_object_files(
+    path: &Path,
+    options: &Options,
+    progress: Option<&ProgressBar>,
+    budgets: Option<&Budgets>,
+    ignore_list: Option<&IgnoreList>,
+    args: &Args,
+) -> Result {
+    check_single_binary_flags(args, OtherInput::ObjectFiles)?;
+    let mut report = analyze(path, options)?;
+    finish_progress(progress);
+    write_symbol_index(&report, args)?;
+    apply_group_filters(&mut report, args);
+    let ignored = ignore_list.map(|list| list.apply(&mut report));
+    let mut out = open_output(args)?;
+    if args.format == OutputFormat::Text && args.verbose {
+        let mut symbols = sorted_duplicates(&report, args);
+        let omitted = split_top(&mut symbols, args.top);
+        print_duplicates(
+            &mut out,
+            &report,
+            symbols,
+            &omitted,
+            args,
+            GroupDetails::default(),
+        )?;
+    }
+    let extras = Extras {
+        baseline_diff: None,
+        icf: None,
+        register_only_bytes: None,
+        reachability: None,
+        similar: None,
+        collisions: None,
+        symbol_problems: None,
+        ignored,
+        what_if: None,
+        profile: None,
+        sources: None,
+        metrics: None,
+    };
+    duplicate_function_checker::timed(options, Phase::Reporting, || -> Result {
+        match args.format {
+            OutputFormat::Text => print_report(&mut out, &report, &extras, args)?,
+            OutputFormat::Json => print_report_json(&mut out, &report, &extras, args)?,
+            OutputFormat::Html => html::write_report(&mut out, path, &report, &extras, args)?,
+            OutputFormat::JsonSummary => print_summary_json(&mut out, &report)?,
+            OutputFormat::Sqlite => sqlite::write_reports(&[(path, &report)], args)?,
+            OutputFormat::Flamegraph => flamegraph::write_folded(&mut out, &report, args)?,
+            OutputFormat::Csv => csv::write_groups(&mut out, &report, args)?,
+            OutputFormat::Markdown => {
+                markdown::write_report(&mut out, path, &report, &extras, args)?
+            }
+        }
+        Ok(out.flush()?)
+    })?;
+    check_limits(&report, budgets, args)
+}
+
+fn analyze_many(
+    paths: &[PathBuf],
+    options: &Options,
+    progress: Option<&ProgressBar>,
+    budgets: Option<&Budgets>,
+    args: &Args,
+) -> Result {
+    check_single_binary_flags(args, OtherInput::Many)?;
+    let reports = paths
+        .iter()
+        .map(|path| {
+            let mut report = analyze(path, options)?;
+            apply_group_filters(&mut report, args);
+            Ok(report)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    finish_progress(progress);
+    let mut shared = cross_binary::shared_functions(&reports);
+    let omitted = split_top(&mut shared, args.top);
+    let mut out = open_output(args)?;
+    duplicate_function_checker::timed(options, Phase::Reporting, || -> Result {
+        match args.format {
+            OutputFormat::Text => print_many(&mut out, paths, &reports, &shared, &omitted, args)?,
+            OutputFormat::Json => print_many_json(&mut out, paths, &reports, &shared, &omitted)?,
+            OutputFormat::Sqlite => {
+                let reports = paths
+                    .iter()
+                    .map(PathBuf::as_path)
+                    .zip(&reports)
+                    .collect::<Vec<_>>();
+                sqlite::write_reports(&reports, args)?;
+            }
+            OutputFormat::Html
+            | OutputFormat::JsonSummary
+            | OutputFormat::Flamegraph
+            | OutputFormat::Csv
+            | OutputFormat::Markdown => {
+                bail!("Only text, JSON and SQLite output are supported with multiple binaries")
+            }
+        }
+        Ok(out.flush()?)
+    })?;
+    for (path, report) in paths.iter().zip(&reports) {
+        check_limits(report, budgets, args).with_context(|| format!("In `{}`", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Analyses the binary for each architecture in a universal binary, reporting each separately,
+/// followed by a table that compares them.
+fn analyze_universal(
+    slices: &[Slice],
+    options: &Options,
+    progress: Option<&ProgressBar>,
+    budgets: Option<&Budgets>,
+    ignore_list: Option<&IgnoreList>,
+    args: &Args,
+) -> Result {
+    check_single_binary_flags(args, OtherInput::Universal)?;
+    let analyses = slices
+        .iter()
+        .map(|slice| {
+            let mut report = duplicate_function_checker::analyze_data(slice.data, options)
+                .with_context(|| format!("Failed to process the {} binary", slice.name()))?;
+            apply_group_filters(&mut report, args);
+            let extras = Extras {
+                baseline_diff: None,
+                icf: None,
+                register_only_bytes: None,
+                reachability: None,
+                similar: None,
+                collisions: None,
+                symbol_problems: None,
+                ignored: ignore_list.map(|list| list.apply(&mut report)),
+                what_if: None,
+                profile: None,
+                sources: None,
+                metrics: None,
+            };
+            Ok((report, extras))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    finish_progress(progress);
+    let mut out = open_output(args)?;
+    duplicate_function_checker::timed(options, Phase::Reporting, || -> Result {
+        match args.format {
+            OutputFormat::Text => print_universal(&mut out, slices, &analyses, args)?,
+            OutputFormat::Json => print_universal_json(&mut out, slices, &analyses)?,
+            _ => bail!(
+                "Only text and JSON output are supported for universal binaries, unless `--arch` \
+                 picks one architecture"
+            ),
+        }
+        Ok(out.flush()?)
+    })?;
+    for (slice, (report, _)) in slices.iter().zip(&analyses) {
+        check_limits(report, budgets, args)
+            .with_context(|| format!("In the {} binary", slice.name()))?;
+    }
+    Ok(())
+}
+
+fn finish_progress(progress: Option<&ProgressBar>) {
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+}
+
+fn check_limits(report: &DuplicationReport, budgets: Option<&Budgets>, args: &Args) -> Result {
+    let excess_percent = report.excess_fraction() * 100.0;
+    if let Some(max) = args.max_excess_percent {
+        if excess_percent > max {
+            bail!(
+                "Excess bytes are {excess_percent:.1}% of {}, which exceeds the limit of {max}%",
+                report.denominator.description()
+            );
+        }
+    }
+    if let Some(max) = args.max_excess_bytes {
+        let excess_bytes = report.excess_bytes();
+        if excess_bytes > max {
+            bail!("Excess bytes are {excess_bytes}, which exceeds the limit of {max}");
+        }
+    }
+    if let Some(budgets) = budgets {
+        budgets.check(report)?;
+    }
+    Ok(())
+}
+
+fn open_output(args: &Args) -> Result<Box<dyn std::io::Write>> {
+    // A database isn't written as a stream and would be truncated by opening it here.
+    if args.format == OutputFormat::Sqlite {
+        return Ok(Box::new(std::io::sink()));
+    }
+    Ok(match &args.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Failed to create `{}`", path.display()))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    })
+}
+
+fn analyze(path: &Path, options: &Options) -> Result<DuplicationReport> {
+    duplicate_function_checker::analyze(path, options)
+        .with_context(|| format!("Failed to process `{}`", path.display()))
+}
+
+/// Optional analyses that are shown alongside the main report.
+struct Extras<'data> {
+    baseline_diff: Option<ReportDiff>,
+    icf: Option<IcfSummary>,
+
+    /// With `--key instruction-shape`, the excess bytes in functions that only differ from
+    /// another copy in register allocation.
+    register_only_bytes: Option<u64>,
+
+    reachability: Option<Reachability>,
+    similar: Option<Vec<SimilarCluster>>,
+    collisions: Option<Vec<Collision>>,
+    symbol_problems: Option<Vec<SymbolProblem>>,
+
+    /// Duplicate groups that were left out because they matched `--ignore-file`.
+    ignored: Option<Vec<DuplicateGroup>>,
+
+    what_if: Option<Vec<Estimate>>,
+    profile: Option<Profile>,
+    sources: Option<SourceLocations>,
+    metrics: Option<GroupMetrics<'data>>,
+}
+
+fn print_report(
+    out: &mut impl std::io::Write,
+    report: &DuplicationReport,
+    extras: &Extras,
+    args: &Args,
+) -> Result {
+    // The duplicate groups themselves have already been printed by this point.
+    if args.verbose {
+        if let Some(similar) = &extras.similar {
+            print_similar(out, similar)?;
+        }
+    }
+    if let Some(collisions) = &extras.collisions {
+        print_collisions(out, collisions, args)?;
+    }
+    if let Some(problems) = &extras.symbol_problems {
+        print_symbol_problems(out, problems, args)?;
+    }
+    if args.verbose {
+        if let Some(ignored) = extras.ignored.as_ref().filter(|i| !i.is_empty()) {
+            writeln!(out, "Ignored duplicate groups:")?;
+            for group in ignored {
+                writeln!(
+                    out,
+                    "  {}x `{}` ({} excess)",
+                    group.copies,
+                    group.representative_name().unwrap_or("<unknown>"),
+                    pretty_size(group.excess_bytes())
+                )?;
+            }
+            writeln!(out)?;
+        }
+    }
+    if let Some(profile) = &extras.profile {
+        print_hot_groups(out, profile, report, args)?;
+    }
+
+    if let Some(baseline_diff) = extras
+        .baseline_diff
+        .as_ref()
+        .filter(|d| d.regressions().next().is_some())
+    {
+        writeln!(out, "New or grown duplicate groups since baseline:")?;
+        for c in baseline_diff.regressions() {
+            print_change(out, c)?;
+        }
+        writeln!(out)?;
+    }
+    if args.strict && !report.key_failures.is_empty() {
+        writeln!(out, "Functions that couldn't be keyed:")?;
+        for failure in &report.key_failures {
+            let name = failure.name.as_deref().unwrap_or("<unknown>");
+            let mut location = format!("{} at {:#x}", pretty_size(failure.size), failure.address);
+            if let Some(member) = &failure.member {
+                location.push_str(&format!(" in {member}"));
+            }
+            writeln!(out, "  {name} ({location}): {}", failure.error)?;
+        }
+        writeln!(out)?;
+    }
+
+    let window = args.range.as_ref().map_or(String::new(), |range| {
+        format!(" in {:#x}-{:#x}", range.start, range.end)
+    });
+    writeln!(
+        out,
+        "Original binary: {} of executable code{window}",
+        pretty_size(report.text_size)
+    )?;
+    match report.denominator {
+        Denominator::Text => {}
+        Denominator::FileSize => {
+            writeln!(out, "      File size: {}", pretty_size(report.file_size))?;
+        }
+        Denominator::LoadedSegments => {
+            writeln!(out, "Loaded segments: {}", pretty_size(report.loaded_size))?;
+        }
+    }
+    writeln!(
+        out,
+        "   Excess bytes: {} ({:.1}% of {})",
+        pretty_size(report.excess_bytes()),
+        report.excess_fraction() * 100.0,
+        report.denominator.description()
+    )?;
+    if let Some(alignment) = args.alignment {
+        writeln!(
+            out,
+            "        Aligned: {} saved with {alignment} byte function alignment",
+            pretty_size(report.aligned_excess_bytes(alignment))
+        )?;
+    }
+    writeln!(
+        out,
+        "            Fns: {} with dupes, {} excess instances",
+        report.duplicated_functions(),
+        report.excess_instances()
+    )?;
+    if report.filtered_functions > 0 {
+        writeln!(
+            out,
+            "       Filtered: {} fns ({}) excluded by filters",
+            report.filtered_functions,
+            pretty_size(report.filtered_bytes)
+        )?;
+    }
+    if report.inferred_sizes > 0 {
+        writeln!(
+            out,
+            "       Inferred: sizes of {} fns whose symbols had no size",
+            report.inferred_sizes
+        )?;
+    }
+    if report.unwind_functions > 0 {
+        writeln!(
+            out,
+            "         Unwind: bounds of {} fns came from unwind tables, as symbols had no sizes",
+            report.unwind_functions
+        )?;
+    }
+    if report.plt_bytes > 0 {
+        writeln!(
+            out,
+            "            PLT: {} of PLT stubs and IFUNC resolvers weren't checked",
+            pretty_size(report.plt_bytes)
+        )?;
+    }
+    writeln!(
+        out,
+        "       Coverage: {:.1}% of executable code ({}) is in fns that were checked",
+        report.coverage_fraction() * 100.0,
+        pretty_size(report.checked_bytes)
+    )?;
+    if !report.key_failures.is_empty() {
+        writeln!(
+            out,
+            "    Not checked: {} fns ({}) couldn't be decoded or re-encoded{}",
+            report.key_failures.len(),
+            pretty_size(report.key_failure_bytes()),
+            if args.strict { "" } else { ", see `--strict`" }
+        )?;
+    }
+    let comdat_excess_bytes = report.comdat_excess_bytes();
+    if comdat_excess_bytes > 0 {
+        writeln!(
+            out,
+            "         COMDAT: {} of excess bytes will be deduplicated by the linker, leaving {}",
+            pretty_size(comdat_excess_bytes),
+            pretty_size(report.excess_bytes() - comdat_excess_bytes)
+        )?;
+    }
+    if report.dynamic_symbols_only {
+        writeln!(
+            out,
+            "        Warning: no symbol table, so only exported fns in `.dynsym` were checked"
+        )?;
+    }
+    if let Some(baseline_diff) = &extras.baseline_diff {
+        writeln!(
+            out,
+            "  From baseline: {} -> {} excess bytes ({}), {} new or grown groups",
+            pretty_size(baseline_diff.old_excess_bytes),
+            pretty_size(baseline_diff.new_excess_bytes),
+            pretty_size_delta(baseline_diff.excess_bytes_delta()),
+            baseline_diff.regressions().count()
+        )?;
+    }
+    if let Some(icf) = &extras.icf {
+        writeln!(
+            out,
+            "ICF could remove: {} ({:.1}% of {}, {} from byte-identical fns)",
+            pretty_size(icf.icf_excess_bytes),
+            report.fraction_of_denominator(icf.icf_excess_bytes) * 100.0,
+            report.denominator.description(),
+            pretty_size(icf.byte_identical_excess_bytes)
+        )?;
+    }
+    if let Some(register_only_bytes) = extras.register_only_bytes {
+        writeln!(
+            out,
+            "  Register-only: {} of excess bytes differ in register allocation, so need \
+             outlining rather than ICF",
+            pretty_size(register_only_bytes)
+        )?;
+    }
+    if let Some(reachability) = &extras.reachability {
+        writeln!(
+            out,
+            "    Unreachable: {} of excess bytes, removable by `--gc-sections` \
+             ({} fns, {} in total)",
+            pretty_size(reachability.unreachable_excess_bytes(report)),
+            reachability.unreachable_functions,
+            pretty_size(reachability.unreachable_bytes)
+        )?;
+    }
+    if let Some(similar) = &extras.similar {
+        writeln!(
+            out,
+            "     Near-dupes: {} clusters of similar fns, {} could be saved by merging",
+            similar.len(),
+            pretty_size(similar.iter().map(|c| c.potential_savings()).sum())
+        )?;
+    }
+
+    if let Some(collisions) = &extras.collisions {
+        writeln!(
+            out,
+            "     Collisions: {} names shared by fns with different contents",
+            collisions.len()
+        )?;
+    }
+
+    if let Some(ignored) = extras.ignored.as_ref().filter(|i| !i.is_empty()) {
+        writeln!(
+            out,
+            "        Ignored: {} groups with {} excess, accepted by `--ignore-file`",
+            ignored.len(),
+            pretty_size(ignored.iter().map(|g| g.excess_bytes()).sum())
+        )?;
+    }
+    if let Some(problems) = &extras.symbol_problems {
+        writeln!(
+            out,
+            "        Symbols: {} with suspicious sizes, affecting {}",
+            problems.len(),
+            pretty_size(problems.iter().map(|p| p.affected_bytes).sum())
+        )?;
+    }
+
+    if let Some(profile) = &extras.profile {
+        let hot_groups = profile.hot_groups(report);
+        writeln!(
+            out,
+            " Hot duplicates: {} groups sampled, {:.1}% of samples in duplicated fns",
+            hot_groups.len(),
+            hot_groups.iter().map(|g| g.samples).sum::<u64>() as f64
+                / profile.total_samples() as f64
+                * 100.0
+        )?;
+    }
+
+    if let Some(what_if) = &extras.what_if {
+        writeln!(out)?;
+        writeln!(out, "Estimated savings by strategy:")?;
+        for estimate in what_if {
+            writeln!(
+                out,
+                "  {:>28}: {} ({:.1}% of {})",
+                estimate.strategy.description(),
+                pretty_size(estimate.saved_bytes),
+                report.fraction_of_denominator(estimate.saved_bytes) * 100.0,
+                report.denominator.description()
+            )?;
+        }
+    }
+
+    if args.by_crate {
+        writeln!(out)?;
+        writeln!(out, "Excess bytes by crate:")?;
+        for (crate_name, bytes) in report.excess_bytes_by_crate() {
+            writeln!(
+                out,
+                "  {}: {}",
+                crate_name.as_deref().unwrap_or("<unknown>"),
+                pretty_size(bytes)
+            )?;
+        }
+    }
+
+    if let Some(depth) = args.by_module {
+        writeln!(out)?;
+        writeln!(out, "Excess bytes by module:")?;
+        print_modules(out, &report.excess_bytes_by_module(depth), 1, args.top)?;
+    }
+
+    if args.by_category {
+        writeln!(out)?;
+        writeln!(out, "Excess bytes by category:")?;
+        for (category, bytes) in report.excess_bytes_by_category() {
+            writeln!(out, "  {}: {}", category.description(), pretty_size(bytes))?;
+        }
+    }
 
-    /// Whether to demangle symbol names and drop rust's hashes.
-    #[arg(long)]
-    demangle_no_hash: bool,
+    if args.by_section {
+        writeln!(out)?;
+        writeln!(out, "Excess bytes by section:")?;
+        for section in report.excess_bytes_by_section() {
+            writeln!(
+                out,
+                "  {}: {} of {}",
+                section.name.as_deref().unwrap_or("<unknown>"),
+                pretty_size(section.excess_bytes),
+                pretty_size(section.size)
+            )?;
+        }
+    }
 
-    /// What to key functions by.
-    #[arg(long, default_value = "instructions")]
-    key: KeyType,
+    if args.by_linkage {
+        writeln!(out)?;
+        writeln!(out, "Excess bytes by linkage:")?;
+        for (linkage, bytes) in report.excess_bytes_by_linkage() {
+            writeln!(out, "  {}: {}", linkage.description(), pretty_size(bytes))?;
+        }
+    }
 
-    /// What to sort results by.
-    #[arg(long, default_value = "excess-bytes")]
-    sort: SortType,
+    if args.histogram {
+        writeln!(out)?;
+        writeln!(out, "Excess bytes by function size:")?;
+        let buckets = report.excess_bytes_by_size();
+        let max = buckets
+            .iter()
+            .map(|b| b.excess_bytes)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for bucket in buckets {
+            let range = match (bucket.min_size, bucket.max_size) {
+                (0, Some(max_size)) => format!("<{}", short_size(max_size)),
+                (min_size, Some(max_size)) => {
+                    format!("{}-{}", short_size(min_size), short_size(max_size))
+                }
+                (min_size, None) => format!(">={}", short_size(min_size)),
+            };
+            writeln!(
+                out,
+                "  {range:>11}: {:<40} {} groups, {}",
+                "#".repeat((bucket.excess_bytes * 40 / max) as usize),
+                bucket.groups,
+                pretty_size(bucket.excess_bytes)
+            )?;
+        }
+    }
+
+    if args.by_generic {
+        writeln!(out)?;
+        writeln!(out, "Bytes by generic function:")?;
+        for root in generic_roots(report, args.top) {
+            writeln!(
+                out,
+                "  {}: {} total, {} excess, {} instantiations, {} copies",
+                root.name,
+                pretty_size(root.total_bytes),
+                pretty_size(root.excess_bytes),
+                root.instantiations,
+                root.copies
+            )?;
+        }
+    }
+
+    Ok(())
 }
 
-#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
-enum KeyType {
-    /// Group by normalised instruction bytes.
-    Instructions,
+/// Prints `modules` and the modules inside them, indented by `level`. Only the first `top` modules
+/// at each level are printed.
+fn print_modules(
+    out: &mut impl std::io::Write,
+    modules: &[ModuleStats],
+    level: usize,
+    top: Option<usize>,
+) -> Result {
+    for module in modules.iter().take(top.unwrap_or(usize::MAX)) {
+        writeln!(
+            out,
+            "{:indent$}{}: {}",
+            "",
+            module.name.as_deref().unwrap_or("<unknown>"),
+            pretty_size(module.excess_bytes),
+            indent = level * 2
+        )?;
+        print_modules(out, &module.children, level + 1, top)?;
+    }
+    Ok(())
+}
 
-    /// Key by function name and size.
-    NameAndSize,
+fn modules_json(modules: &[ModuleStats]) -> Value {
+    modules
+        .iter()
+        .map(|module| {
+            json!({
+                "name": module.name,
+                "excess_bytes": module.excess_bytes,
+                "children": modules_json(&module.children),
+            })
+        })
+        .collect()
+}
 
-    /// Key by function name and size, but drop the hash added by rustc. This may group
-    /// monomorphisations that are fundamentally different, so isn't recommended.
-    NameWithoutRustHash,
+/// Returns generic roots that have more than one copy, limited to `top` if set.
+fn generic_roots(report: &DuplicationReport, top: Option<usize>) -> Vec<GenericRoot> {
+    report
+        .bytes_by_generic()
+        .into_iter()
+        .filter(|root| root.copies > 1)
+        .take(top.unwrap_or(usize::MAX))
+        .collect()
 }
 
-#[derive(Clone, Copy, clap::ValueEnum, PartialEq, Eq)]
-enum SortType {
-    /// Sort by excess bytes of function in the binary.
-    ExcessBytes,
+fn print_summary_json(out: &mut impl std::io::Write, report: &DuplicationReport) -> Result {
+    let summary = json!({
+        "schema_version": SUMMARY_SCHEMA_VERSION,
+        "text_size": report.text_size,
+        "excess_bytes": report.excess_bytes(),
+        "excess_percent": json_percent(report.excess_fraction()),
+        "denominator": report.denominator.id(),
+        "dup_groups": report.duplicated_functions(),
+        "excess_instances": report.excess_instances(),
+        "key_failures": report.key_failures.len(),
+        "checked_bytes": report.checked_bytes,
+        "coverage_percent": json_percent(report.coverage_fraction()),
+    });
+    writeln!(out, "{summary}")?;
+    Ok(())
+}
 
-    /// Sort by number of copies.
-    Copies,
+/// Converts `fraction` to a percentage for JSON output, rounded to 3 decimal places, since more
+/// precision than that is just noise.
+fn json_percent(fraction: f64) -> f64 {
+    (fraction * 100_000.0).round() / 1000.0
+}
 
-    /// Sort by function size.
-    Size,
+fn key_failures_json(failures: &[KeyFailure]) -> Value {
+    failures
+        .iter()
+        .map(|failure| {
+            json!({
+                "name": failure.name,
+                "address": failure.address,
+                "size": failure.size,
+                "member": failure.member,
+                "error": failure.error,
+            })
+        })
+        .collect()
 }
 
-fn main() -> Result {
-    let args = Args::parse();
-    let r = match args.key {
-        KeyType::NameAndSize => process::<NameAndSizeKey>(&args.bin, &args),
-        KeyType::NameWithoutRustHash => process::<NameAndSizeKey>(&args.bin, &args),
-        KeyType::Instructions => process::<InstructionsKey>(&args.bin, &args),
+fn print_report_json(
+    out: &mut impl std::io::Write,
+    report: &DuplicationReport,
+    extras: &Extras,
+    args: &Args,
+) -> Result {
+    let mut symbols = sorted_duplicates(report, args);
+    let omitted = split_top(&mut symbols, args.top);
+    let details = GroupDetails {
+        sources: extras.sources.as_ref(),
+        metrics: extras.metrics.as_ref(),
+        disassembler: None,
     };
-    r.with_context(|| format!("Failed to process `{}`", args.bin.display()))?;
+    let mut json = json!({
+        "text_size": report.text_size,
+        "file_size": report.file_size,
+        "loaded_size": report.loaded_size,
+        "excess_bytes": report.excess_bytes(),
+        "excess_percent": json_percent(report.excess_fraction()),
+        "denominator": report.denominator.id(),
+        "functions_with_duplicates": report.duplicated_functions(),
+        "excess_instances": report.excess_instances(),
+        "filtered_functions": report.filtered_functions,
+        "filtered_bytes": report.filtered_bytes,
+        "inferred_sizes": report.inferred_sizes,
+        "dynamic_symbols_only": report.dynamic_symbols_only,
+        "unwind_functions": report.unwind_functions,
+        "plt_bytes": report.plt_bytes,
+        "comdat_excess_bytes": report.comdat_excess_bytes(),
+        "key_failures": report.key_failures.len(),
+        "key_failure_bytes": report.key_failure_bytes(),
+        "checked_bytes": report.checked_bytes,
+        "coverage_percent": json_percent(report.coverage_fraction()),
+        "duplicates": duplicates_json(symbols, details),
+        "omitted_groups": omitted.len(),
+        "omitted_excess_bytes": omitted.iter().map(|v| v.excess_bytes()).sum::<u64>(),
+    });
+    if args.strict {
+        json["key_failure_details"] = key_failures_json(&report.key_failures);
+    }
+    if let Some(baseline_diff) = &extras.baseline_diff {
+        json["baseline"] = json!({
+            "old_excess_bytes": baseline_diff.old_excess_bytes,
+            "new_excess_bytes": baseline_diff.new_excess_bytes,
+            "regressions": baseline_diff.regressions().collect::<Vec<_>>(),
+        });
+    }
+    if let Some(icf) = &extras.icf {
+        json["icf"] = json!({
+            "icf_excess_bytes": icf.icf_excess_bytes,
+            "byte_identical_excess_bytes": icf.byte_identical_excess_bytes,
+            "iterations": icf.iterations,
+        });
+    }
+    if let Some(register_only_bytes) = extras.register_only_bytes {
+        json["register_only_excess_bytes"] = json!(register_only_bytes);
+    }
+    if let Some(reachability) = &extras.reachability {
+        json["reachability"] = json!({
+            "unreachable_excess_bytes": reachability.unreachable_excess_bytes(report),
+            "unreachable_functions": reachability.unreachable_functions,
+            "unreachable_bytes": reachability.unreachable_bytes,
+        });
+    }
+    if let Some(similar) = &extras.similar {
+        json["similar"] = similar_json(similar);
+    }
+    if let Some(collisions) = &extras.collisions {
+        json["collisions"] = json!(collisions);
+    }
+    if let Some(alignment) = args.alignment {
+        json["alignment"] = json!(alignment);
+        json["aligned_excess_bytes"] = json!(report.aligned_excess_bytes(alignment));
+    }
+    if let Some(ignored) = &extras.ignored {
+        json["ignored"] = duplicates_json(ignored.iter().collect(), GroupDetails::default());
+    }
+    if let Some(problems) = &extras.symbol_problems {
+        json["symbol_problems"] = symbol_problems_json(problems);
+    }
+    if let Some(profile) = &extras.profile {
+        json["hot_duplicates"] = json!({
+            "total_samples": profile.total_samples(),
+            "groups": profile.hot_groups(report),
+        });
+    }
+    if let Some(what_if) = &extras.what_if {
+        json["what_if"] = what_if
+            .iter()
+            .map(|estimate| {
+                json!({
+                    "strategy": estimate.strategy.id(),
+                    "saved_bytes": estimate.saved_bytes,
+                })
+            })
+            .collect();
+    }
+    if args.by_crate {
+        json["by_crate"] = report
+            .excess_bytes_by_crate()
+            .iter()
+            .map(|(crate_name, bytes)| json!({"crate": crate_name, "excess_bytes": bytes}))
+            .collect();
+    }
+    if let Some(depth) = args.by_module {
+        json["by_module"] = modules_json(&report.excess_bytes_by_module(depth));
+    }
+    if args.by_category {
+        json["by_category"] = report
+            .excess_bytes_by_category()
+            .iter()
+            .map(|(category, bytes)| json!({"category": category.id(), "excess_bytes": bytes}))
+            .collect();
+    }
+    if args.by_section {
+        json["by_section"] = report
+            .excess_bytes_by_section()
+            .iter()
+            .map(|section| {
+                json!({
+                    "section": section.name,
+                    "size": section.size,
+                    "excess_bytes": section.excess_bytes,
+                })
+            })
+            .collect();
+    }
+    if args.by_linkage {
+        json["by_linkage"] = report
+            .excess_bytes_by_linkage()
+            .iter()
+            .map(|(linkage, bytes)| json!({"linkage": linkage.id(), "excess_bytes": bytes}))
+            .collect();
+    }
+    if args.histogram {
+        json["histogram"] = report
+            .excess_bytes_by_size()
+            .iter()
+            .map(|bucket| {
+                json!({
+                    "min_size": bucket.min_size,
+                    "max_size": bucket.max_size,
+                    "groups": bucket.groups,
+                    "excess_bytes": bucket.excess_bytes,
+                })
+            })
+            .collect();
+    }
+    if args.by_generic {
+        json["by_generic"] = generic_roots(report, args.top)
+            .iter()
+            .map(|root| {
+                json!({
+                    "name": root.name,
+                    "instantiations": root.instantiations,
+                    "copies": root.copies,
+                    "total_bytes": root.total_bytes,
+                    "excess_bytes": root.excess_bytes,
+                })
+            })
+            .collect();
+    }
+    writeln!(out, "{json}")?;
+    Ok(())
+}
+
+fn print_many(
+    out: &mut impl std::io::Write,
+    paths: &[PathBuf],
+    reports: &[DuplicationReport],
+    shared: &[SharedFunction],
+    omitted: &[SharedFunction],
+    args: &Args,
+) -> Result {
+    if !shared.is_empty() {
+        writeln!(out, "Functions in more than one binary:")?;
+        for function in shared {
+            writeln!(
+                out,
+                "  {}/{} binaries, {} each: `{}`",
+                function.binaries.len(),
+                paths.len(),
+                pretty_size(function.function_size),
+                function.name
+            )?;
+            if args.verbose {
+                for &index in &function.binaries {
+                    writeln!(out, "    {}", paths[index].display())?;
+                }
+            }
+        }
+        if !omitted.is_empty() {
+            writeln!(
+                out,
+                "  {} more functions omitted ({} in extra copies)",
+                omitted.len(),
+                pretty_size(omitted.iter().map(|f| f.excess_bytes()).sum())
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "Per binary:")?;
+    for (path, report) in paths.iter().zip(reports) {
+        writeln!(
+            out,
+            "  {}: {} of executable code, {} excess ({:.1}%), {} fns with dupes",
+            path.display(),
+            pretty_size(report.text_size),
+            pretty_size(report.excess_bytes()),
+            report.excess_fraction() * 100.0,
+            report.duplicated_functions()
+        )?;
+        if report.dynamic_symbols_only {
+            writeln!(out, "    (only exported fns, as there's no symbol table)")?;
+        }
+    }
+    writeln!(out)?;
+    let count = shared.len() + omitted.len();
+    let excess_bytes: u64 = shared.iter().chain(omitted).map(|f| f.excess_bytes()).sum();
+    writeln!(
+        out,
+        "   Cross-binary: {count} fns in more than one binary, {} in extra copies",
+        pretty_size(excess_bytes)
+    )?;
     Ok(())
 }
 
-trait Key: Hash + Eq + Sized {
-    fn from_sym<'data>(
-        sym: &object::Symbol<'data, '_, &'data [u8]>,
-        inputs: &KeyBuilderInputs,
-    ) -> Option<Self>;
+fn print_universal(
+    out: &mut impl std::io::Write,
+    slices: &[Slice],
+    analyses: &[(DuplicationReport, Extras)],
+    args: &Args,
+) -> Result {
+    for (slice, (report, extras)) in slices.iter().zip(analyses) {
+        writeln!(out, "Architecture: {}", slice.name())?;
+        writeln!(out)?;
+        if args.verbose {
+            let mut symbols = sorted_duplicates(report, args);
+            let omitted = split_top(&mut symbols, args.top);
+            print_duplicates(
+                out,
+                report,
+                symbols,
+                &omitted,
+                args,
+                GroupDetails::default(),
+            )?;
+        }
+        print_report(out, report, extras, args)?;
+        writeln!(out)?;
+    }
+
+    writeln!(out, "Per architecture:")?;
+    for (slice, (report, _)) in slices.iter().zip(analyses) {
+        writeln!(
+            out,
+            "  {}: {} of executable code, {} excess ({:.1}%), {} fns with dupes",
+            slice.name(),
+            pretty_size(report.text_size),
+            pretty_size(report.excess_bytes()),
+            report.excess_fraction() * 100.0,
+            report.duplicated_functions()
+        )?;
+    }
+    writeln!(out)?;
+    let text_size: u64 = analyses.iter().map(|(report, _)| report.text_size).sum();
+    let excess_bytes: u64 = analyses
+        .iter()
+        .map(|(report, _)| report.excess_bytes())
+        .sum();
+    writeln!(
+        out,
+        "Universal total: {} of executable code, {} excess ({:.1}%)",
+        pretty_size(text_size),
+        pretty_size(excess_bytes),
+        excess_bytes as f64 / text_size.max(1) as f64 * 100.0
+    )?;
+    Ok(())
 }
 
-fn process<K: Key>(path: &Path, args: &Args) -> Result {
-    let data = std::fs::read(path)?;
-    let object = object::File::parse(data.as_slice())?;
-    let mut symbols = HashMap::new();
+fn print_universal_json(
+    out: &mut impl std::io::Write,
+    slices: &[Slice],
+    analyses: &[(DuplicationReport, Extras)],
+) -> Result {
+    let architectures = slices
+        .iter()
+        .zip(analyses)
+        .map(|(slice, (report, _))| {
+            json!({
+                "arch": slice.name(),
+                "text_size": report.text_size,
+                "excess_bytes": report.excess_bytes(),
+                "excess_percent": json_percent(report.excess_fraction()),
+                "functions_with_duplicates": report.duplicated_functions(),
+                "excess_instances": report.excess_instances(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let text_size: u64 = analyses.iter().map(|(report, _)| report.text_size).sum();
+    let excess_bytes: u64 = analyses
+        .iter()
+        .map(|(report, _)| report.excess_bytes())
+        .sum();
+    let json = json!({
+        "architectures": architectures,
+        "text_size": text_size,
+        "excess_bytes": excess_bytes,
+    });
+    writeln!(out, "{json}")?;
+    Ok(())
+}
 
-    let inputs = KeyBuilderInputs::new(&object, args);
-    let mut considered = 0;
+fn print_many_json(
+    out: &mut impl std::io::Write,
+    paths: &[PathBuf],
+    reports: &[DuplicationReport],
+    shared: &[SharedFunction],
+    omitted: &[SharedFunction],
+) -> Result {
+    let binaries = paths
+        .iter()
+        .zip(reports)
+        .map(|(path, report)| {
+            json!({
+                "path": path.display().to_string(),
+                "text_size": report.text_size,
+                "excess_bytes": report.excess_bytes(),
+                "excess_percent": json_percent(report.excess_fraction()),
+                "functions_with_duplicates": report.duplicated_functions(),
+                "excess_instances": report.excess_instances(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let shared_functions = shared
+        .iter()
+        .rev()
+        .map(|function| {
+            let binaries = function
+                .binaries
+                .iter()
+                .map(|&index| paths[index].display().to_string())
+                .collect::<Vec<_>>();
+            json!({
+                "name": function.name,
+                "function_size": function.function_size,
+                "copies": function.copies,
+                "excess_bytes": function.excess_bytes(),
+                "binaries": binaries,
+            })
+        })
+        .collect::<Vec<_>>();
+    let json = json!({
+        "binaries": binaries,
+        "shared_functions": shared_functions,
+        "omitted_shared_functions": omitted.len(),
+        "omitted_excess_bytes": omitted.iter().map(|f| f.excess_bytes()).sum::<u64>(),
+    });
+    writeln!(out, "{json}")?;
+    Ok(())
+}
 
-    for sym in object.symbols() {
-        if sym.kind() != SymbolKind::Text || sym.size() == 0 {
-            continue;
+fn print_diff(out: &mut impl std::io::Write, diff: &ReportDiff, args: &Args) -> Result {
+    if args.verbose {
+        for (heading, changes) in [
+            ("Appeared", &diff.appeared),
+            ("Disappeared", &diff.disappeared),
+            ("Changed", &diff.changed),
+        ] {
+            if changes.is_empty() {
+                continue;
+            }
+            writeln!(out, "{heading}:")?;
+            for c in changes {
+                print_change(out, c)?;
+            }
+            writeln!(out)?;
         }
-        let Some(key) = K::from_sym(&sym, &inputs) else {
-            continue;
-        };
-        considered += 1;
-        let info = symbols.entry(key).or_insert_with(|| SymInfo {
-            count: 0,
-            names: Default::default(),
-            function_size: sym.size(),
-        });
-        info.count += 1;
-        if let Ok(name) = sym.name() {
-            let key = if args.demangle {
-                Cow::Owned(rustc_demangle::demangle(name).to_string())
-            } else if args.demangle_no_hash {
-                Cow::Owned(format!("{:#}", rustc_demangle::demangle(name)))
-            } else {
-                Cow::Borrowed(name)
-            };
-            *info.names.entry(key).or_default() += 1;
-        };
     }
+    writeln!(
+        out,
+        "   Excess bytes: {} -> {} ({})",
+        pretty_size(diff.old_excess_bytes),
+        pretty_size(diff.new_excess_bytes),
+        pretty_size_delta(diff.excess_bytes_delta())
+    )?;
+    writeln!(
+        out,
+        "         Groups: {} appeared, {} disappeared, {} changed",
+        diff.appeared.len(),
+        diff.disappeared.len(),
+        diff.changed.len()
+    )?;
+    Ok(())
+}
 
-    let (duplicated_bytes, duplicated_functions, duplicate_instances) =
-        symbols.values().fold((0, 0, 0), |prev, v| {
-            (
-                prev.0 + v.excess_bytes(),
-                prev.1 + if v.count > 1 { 1 } else { 0 },
-                prev.2 + v.count.saturating_sub(1),
-            )
-        });
+fn print_diff_json(out: &mut impl std::io::Write, diff: &ReportDiff) -> Result {
+    let json = json!({
+        "old_excess_bytes": diff.old_excess_bytes,
+        "new_excess_bytes": diff.new_excess_bytes,
+        "excess_bytes_delta": diff.excess_bytes_delta(),
+        "appeared": diff.appeared,
+        "disappeared": diff.disappeared,
+        "changed": diff.changed,
+    });
+    writeln!(out, "{json}")?;
+    Ok(())
+}
+
+fn print_change(out: &mut impl std::io::Write, c: &GroupChange) -> Result {
+    writeln!(
+        out,
+        "  {} -> {} copies ({}) `{}`",
+        c.old_copies,
+        c.new_copies,
+        pretty_size_delta(c.excess_bytes_delta()),
+        c.name
+    )?;
+    Ok(())
+}
 
-    let text_size = determine_text_size(&object);
-    let percent = duplicated_bytes as f64 / text_size as f64;
+fn sorted_duplicates<'a>(report: &'a DuplicationReport, args: &Args) -> Vec<&'a DuplicateGroup> {
+    let mut symbols = report
+        .duplicates()
+        .filter(|group| meets_minimums(group, args))
+        .collect::<Vec<_>>();
 
-    if args.verbose {
-        print_duplicates(symbols, args.sort)?;
+    match args.sort {
+        SortType::ExcessBytes => symbols.sort_by_key(|v| v.excess_bytes()),
+        SortType::Copies => symbols.sort_by_key(|v| v.copies),
+        SortType::Size => symbols.sort_by_key(|v| v.function_size),
+        SortType::Name => {
+            // `--top` keeps the groups at the end, so the groups with the most excess bytes go
+            // there, then each part is sorted by name.
+            symbols.sort_by_key(|v| v.excess_bytes());
+            let omitted = symbols.len().saturating_sub(args.top.unwrap_or(usize::MAX));
+            let (omitted, kept) = symbols.split_at_mut(omitted);
+            omitted.sort_by_cached_key(|v| v.demangled_name());
+            kept.sort_by_cached_key(|v| v.demangled_name());
+        }
+    };
+    symbols
+}
+
+fn meets_minimums(group: &DuplicateGroup, args: &Args) -> bool {
+    args.min_size.is_none_or(|min| group.function_size >= min)
+        && args.min_copies.is_none_or(|min| group.copies >= min)
+}
+
+/// Leaves out groups that don't match `--where`, or that don't meet the minimums if they apply to
+/// totals, so that they aren't printed or counted towards limits.
+fn apply_group_filters(report: &mut DuplicationReport, args: &Args) {
+    if let Some(query) = &args.query {
+        report
+            .groups
+            .retain(|group| group.copies <= 1 || query.matches(group));
+    }
+    if args.min_applies_to_totals {
+        report
+            .groups
+            .retain(|group| group.copies <= 1 || meets_minimums(group, args));
+    }
+}
+
+fn parse_alignment(text: &str) -> Result<u64> {
+    let alignment = text
+        .parse::<u64>()
+        .with_context(|| format!("Invalid alignment `{text}`"))?;
+    if !alignment.is_power_of_two() {
+        bail!("Alignment must be a power of two, not {alignment}");
+    }
+    Ok(alignment)
+}
+
+/// Parses a hexadecimal address, with or without a `0x` prefix.
+fn parse_address(text: &str) -> Result<u64> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    u64::from_str_radix(digits, 16).with_context(|| format!("Invalid address `{text}`"))
+}
+
+/// Parses a range of hexadecimal addresses like `0x1000-0x2000`, where the end is exclusive.
+fn parse_range(text: &str) -> Result<Range<u64>> {
+    let Some((start, end)) = text.split_once('-') else {
+        bail!("Invalid range `{text}`, expected START-END");
+    };
+    let range = parse_address(start.trim())?..parse_address(end.trim())?;
+    if range.is_empty() {
+        bail!("Range `{text}` is empty");
     }
+    Ok(range)
+}
+
+/// Removes and returns the items that don't make the `--top` cut. Items are sorted with the worst
+/// last, so these are the ones at the start.
+fn split_top<T>(items: &mut Vec<T>, top: Option<usize>) -> Vec<T> {
+    let Some(top) = top else {
+        return Vec::new();
+    };
+    let omitted = items.len().saturating_sub(top);
+    items.drain(..omitted).collect()
+}
+
+/// What we know about each duplicate group beyond what's in the report. Each is only worked out
+/// when the output needs it.
+#[derive(Clone, Copy, Default)]
+struct GroupDetails<'a> {
+    sources: Option<&'a SourceLocations>,
+    metrics: Option<&'a GroupMetrics<'a>>,
+    disassembler: Option<&'a Disassembler<'a>>,
+}
 
-    if considered == 0 {
-        if object.symbols().next().is_none() {
-            bail!("Binary has no symbol table");
+fn print_duplicates(
+    out: &mut impl std::io::Write,
+    report: &DuplicationReport,
+    symbols: Vec<&DuplicateGroup>,
+    omitted: &[&DuplicateGroup],
+    args: &Args,
+    details: GroupDetails,
+) -> Result {
+    // Groups are listed with the largest last, so the cumulative share of each group includes
+    // those that follow it.
+    let total_excess_bytes = report.excess_bytes();
+    let mut remaining_excess_bytes = symbols.iter().map(|v| v.excess_bytes()).sum::<u64>();
+    let mut remaining_groups = symbols.len();
+    for v in symbols {
+        writeln!(out, "Group: {}", v.id())?;
+        writeln!(out, "Function size: {}", pretty_size(v.function_size))?;
+        if let Some(m) = details.metrics.and_then(|m| m.get(v)) {
+            writeln!(
+                out,
+                "Instructions: {} ({} basic blocks)",
+                m.instructions, m.basic_blocks
+            )?;
+        }
+        writeln!(out, "Copies: {}", v.copies)?;
+        writeln!(
+            out,
+            "Excess bytes: {} ({:.2}% of {})",
+            pretty_size(v.excess_bytes()),
+            report.fraction_of_denominator(v.excess_bytes()) * 100.0,
+            report.denominator.description()
+        )?;
+        if args.sort == SortType::ExcessBytes {
+            let groups = match remaining_groups {
+                1 => "the largest group has".to_owned(),
+                n => format!("the top {n} groups have"),
+            };
+            writeln!(
+                out,
+                "Cumulative: {groups} {:.1}% of excess bytes",
+                remaining_excess_bytes as f64 / total_excess_bytes as f64 * 100.0
+            )?;
+        }
+        remaining_excess_bytes -= v.excess_bytes();
+        remaining_groups -= 1;
+        if let Some(location) = details.sources.and_then(|s| s.find_for_group(v)) {
+            writeln!(out, "Source: {location}")?;
+        }
+        if v.names.is_empty() {
+            // Functions found through unwind tables may not have symbols.
+            writeln!(out, "Names: none, only known by address")?;
+        } else {
+            writeln!(out, "Names:")?;
+            for (name, count) in &v.names {
+                writeln!(out, "  {count}x `{name}`")?;
+            }
+        }
+        if let Some(hint) = hint::suggest(v) {
+            writeln!(out, "Hint: {hint}")?;
         }
-        bail!("No functions were checked for duplication, symbols may have zero sizes");
+        print_instances(out, v)?;
+        if let Some(lines) = details.disassembler.and_then(|d| d.disassemble_group(v)) {
+            writeln!(out, "Disassembly:")?;
+            for line in lines {
+                writeln!(out, "  {line}")?;
+            }
+        }
+        writeln!(out)?;
+    }
+    if !omitted.is_empty() {
+        writeln!(
+            out,
+            "{} more groups omitted ({} excess bytes)",
+            omitted.len(),
+            pretty_size(omitted.iter().map(|v| v.excess_bytes()).sum())
+        )?;
+        if args.verbose {
+            print_tail(out, omitted, args.top.unwrap_or(usize::MAX))?;
+        }
+        writeln!(out)?;
     }
+    Ok(())
+}
 
-    println!(
-        "Original binary: {} of executable code",
-        pretty_size(text_size)
-    );
-    println!(
-        "   Excess bytes: {} ({:.1}% of executable code)",
-        pretty_size(duplicated_bytes),
-        percent * 100.0
-    );
-    println!(
-        "            Fns: {duplicated_functions} with dupes, {duplicate_instances} excess instances"
-    );
+/// Prints the groups that didn't make the `--top` cut totalled by generic function, so that the
+/// verbose report still accounts for every group. Only the `top` generic functions with the most
+/// excess bytes are listed, with the rest totalled on one line.
+fn print_tail(out: &mut impl std::io::Write, omitted: &[&DuplicateGroup], top: usize) -> Result {
+    let mut roots = duplicate_function_checker::group_by_generic_root(omitted.iter().copied());
+    roots.sort_by(|a, b| {
+        b.excess_bytes
+            .cmp(&a.excess_bytes)
+            .then(a.name.cmp(&b.name))
+    });
+    let others = roots.split_off(top.min(roots.len()));
+    writeln!(out, "Omitted groups by generic function:")?;
+    for root in &roots {
+        print_tail_line(
+            out,
+            &root.name,
+            root.instantiations,
+            root.copies,
+            root.excess_bytes,
+        )?;
+    }
+    if !others.is_empty() {
+        print_tail_line(
+            out,
+            &format!("{} other generic functions", others.len()),
+            others.iter().map(|root| root.instantiations).sum(),
+            others.iter().map(|root| root.copies).sum(),
+            others.iter().map(|root| root.excess_bytes).sum(),
+        )?;
+    }
+    let unnamed = omitted
+        .iter()
+        .filter(|group| group.names.is_empty())
+        .collect::<Vec<_>>();
+    if !unnamed.is_empty() {
+        print_tail_line(
+            out,
+            "Functions without names",
+            unnamed.len() as u64,
+            unnamed.iter().map(|group| group.copies).sum(),
+            unnamed.iter().map(|group| group.excess_bytes()).sum(),
+        )?;
+    }
+    Ok(())
+}
+
+fn print_tail_line(
+    out: &mut impl std::io::Write,
+    name: &str,
+    groups: u64,
+    copies: u64,
+    excess_bytes: u64,
+) -> Result {
+    writeln!(
+        out,
+        "  {name}: {groups} groups, {copies} copies, {} excess",
+        pretty_size(excess_bytes)
+    )?;
+    Ok(())
+}
 
+fn print_instances(out: &mut impl std::io::Write, group: &DuplicateGroup) -> Result {
+    writeln!(out, "Instances:")?;
+    let mut instances = group.instances.iter().collect::<Vec<_>>();
+    instances.sort_by_key(|i| (&i.member, &i.file, i.address));
+    for instance in instances {
+        write!(out, "  {:#x}", instance.address)?;
+        if let Some(section) = &instance.section {
+            write!(out, " in {section}")?;
+        }
+        if let Some(member) = &instance.member {
+            write!(out, " from `{member}`")?;
+        } else if let Some(file) = &instance.file {
+            write!(out, " from `{file}`")?;
+        }
+        writeln!(out)?;
+    }
     Ok(())
 }
 
-fn get_fn_bytes<'data>(
-    sym: &object::Symbol<'data, '_, &'data [u8]>,
-    object: &object::File<'data, &'data [u8]>,
-) -> Option<&'data [u8]> {
-    let section = object.section_by_index(sym.section_index()?).ok()?;
-    let section_data = section.data().ok()?;
-    let offset = sym.address().checked_sub(section.address())? as usize;
-    let end = offset + sym.size() as usize;
-    if end > section_data.len() {
-        return None;
+/// Prints the summary of duplicated read-only data, preceded in verbose mode by each group of
+/// duplicated constants.
+fn print_rodata(out: &mut impl std::io::Write, report: &RodataReport, args: &Args) -> Result {
+    let mut groups = report.duplicates().collect::<Vec<_>>();
+    if args.verbose {
+        let omitted = split_top(&mut groups, args.top);
+        for group in &groups {
+            writeln!(out, "Constant size: {}", pretty_size(group.size))?;
+            writeln!(out, "Copies: {}", group.copies)?;
+            writeln!(out, "Excess bytes: {}", pretty_size(group.excess_bytes()))?;
+            if group.vtable {
+                writeln!(out, "Kind: vtable")?;
+            }
+            writeln!(out, "Contents: {}", group.preview)?;
+            if !group.names.is_empty() {
+                writeln!(out, "Names:")?;
+                for (name, count) in &group.names {
+                    writeln!(out, "  {count}x `{name}`")?;
+                }
+            }
+            writeln!(out, "Instances:")?;
+            for instance in &group.instances {
+                write!(out, "  {:#x} in {}", instance.address, instance.section)?;
+                if let Some(member) = &instance.member {
+                    write!(out, " from `{member}`")?;
+                }
+                writeln!(out)?;
+            }
+            writeln!(out)?;
+        }
+        if !omitted.is_empty() {
+            writeln!(
+                out,
+                "{} more groups omitted ({} excess bytes)",
+                omitted.len(),
+                pretty_size(omitted.iter().map(|group| group.excess_bytes()).sum())
+            )?;
+            writeln!(out)?;
+        }
+        groups.extend(omitted);
+    }
+    writeln!(out, " Read-only data: {}", pretty_size(report.rodata_size))?;
+    writeln!(
+        out,
+        "   Excess bytes: {} ({:.1}% of read-only data)",
+        pretty_size(report.excess_bytes()),
+        report.excess_fraction() * 100.0
+    )?;
+    writeln!(
+        out,
+        "      Constants: {} with dupes, {} excess instances",
+        groups.len(),
+        groups.iter().map(|group| group.copies - 1).sum::<u64>()
+    )?;
+    let vtable_excess_bytes = report.vtable_excess_bytes();
+    if vtable_excess_bytes > 0 {
+        writeln!(
+            out,
+            "        Vtables: {} of excess bytes",
+            pretty_size(vtable_excess_bytes)
+        )?;
     }
-    Some(&section_data[offset..end])
+    Ok(())
 }
 
-fn print_duplicates<K: Key>(symbols: HashMap<K, SymInfo>, sort: SortType) -> Result {
-    let mut symbols = symbols
-        .into_values()
-        .filter(|info| info.count > 1)
+fn print_rodata_json(out: &mut impl std::io::Write, report: &RodataReport) -> Result {
+    let groups = report
+        .duplicates()
+        .map(|group| {
+            let names = group
+                .names
+                .iter()
+                .map(|(name, count)| json!({"name": name, "count": count}))
+                .collect::<Vec<_>>();
+            let instances = group
+                .instances
+                .iter()
+                .map(|instance| {
+                    let mut json = json!({
+                        "address": instance.address,
+                        "section": instance.section,
+                    });
+                    if let Some(member) = &instance.member {
+                        json["member"] = json!(member);
+                    }
+                    json
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "size": group.size,
+                "copies": group.copies,
+                "excess_bytes": group.excess_bytes(),
+                "vtable": group.vtable,
+                "preview": group.preview,
+                "names": names,
+                "instances": instances,
+            })
+        })
         .collect::<Vec<_>>();
+    let json = json!({
+        "rodata_size": report.rodata_size,
+        "excess_bytes": report.excess_bytes(),
+        "vtable_excess_bytes": report.vtable_excess_bytes(),
+        "groups": groups,
+    });
+    writeln!(out, "{json}")?;
+    Ok(())
+}
 
-    match sort {
-        SortType::ExcessBytes => symbols.sort_by_key(|v| v.excess_bytes()),
-        SortType::Copies => symbols.sort_by_key(|v| v.count),
-        SortType::Size => symbols.sort_by_key(|v| v.function_size),
+/// Prints each run in the history with its excess bytes, the change from the run before and a bar
+/// to show the trend, followed by the change over the whole history. Bars span from the fewest
+/// excess bytes to the most, so that slow creep is visible even when it's small compared to the
+/// total.
+fn print_trend(out: &mut impl std::io::Write, entries: &[TrendEntry]) -> Result {
+    let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+        bail!("The history has no runs yet");
+    };
+    let min = entries.iter().map(|e| e.excess_bytes).min().unwrap_or(0);
+    let max = entries.iter().map(|e| e.excess_bytes).max().unwrap_or(0);
+    let bar = |excess_bytes: u64| {
+        let width = match max - min {
+            0 => 40,
+            range => 1 + (excess_bytes - min) * 39 / range,
+        };
+        "#".repeat(width as usize)
     };
+    writeln!(out, "{:<12} {:>12} {:>12}", "Run", "Excess bytes", "Change")?;
+    let mut previous: Option<&TrendEntry> = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let label = match entry.label() {
+            Some(label) => label.chars().take(12).collect(),
+            None => format!("#{}", i + 1),
+        };
+        let change = previous.map_or(String::new(), |previous| {
+            pretty_size_delta(entry.excess_bytes as i64 - previous.excess_bytes as i64)
+        });
+        writeln!(
+            out,
+            "{label:<12} {:>12} {change:>12} {}",
+            pretty_size(entry.excess_bytes),
+            bar(entry.excess_bytes)
+        )?;
+        previous = Some(entry);
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Over {} runs: {} -> {} excess bytes ({})",
+        entries.len(),
+        pretty_size(first.excess_bytes),
+        pretty_size(last.excess_bytes),
+        pretty_size_delta(last.excess_bytes as i64 - first.excess_bytes as i64)
+    )?;
+    Ok(())
+}
 
-    let mut out = std::io::stdout().lock();
-    for v in symbols {
-        writeln!(&mut out, "Function size: {}", pretty_size(v.function_size))?;
-        writeln!(&mut out, "Copies: {}", v.count)?;
-        writeln!(&mut out, "Excess bytes: {}", pretty_size(v.excess_bytes()))?;
-        writeln!(&mut out, "Names:")?;
-        for (name, count) in &v.names {
-            writeln!(&mut out, "  {count}x `{name}`")?;
+fn print_trend_json(out: &mut impl std::io::Write, entries: &[TrendEntry]) -> Result {
+    write!(out, "[")?;
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
         }
-        writeln!(&mut out)?;
+        writeln!(out)?;
+        entry.write_json(out)?;
     }
+    writeln!(out, "\n]")?;
     Ok(())
 }
 
-fn determine_text_size<'data>(object: &object::File<'data, &'data [u8]>) -> u64 {
-    object
-        .sections()
-        .map(|sec| {
-            if sec.kind() == SectionKind::Text {
-                sec.size()
-            } else {
-                0
+/// Prints each group with a name that matches `pattern`, including groups of one function.
+fn print_matches(
+    out: &mut impl std::io::Write,
+    report: &DuplicationReport,
+    pattern: &Regex,
+) -> Result {
+    let mut matching_groups = 0;
+    let mut duplicated_groups = 0;
+    for group in &report.groups {
+        let (matches, others): (Vec<_>, Vec<_>) = group
+            .names
+            .iter()
+            .partition(|(name, _)| pattern.is_match(name));
+        if matches.is_empty() {
+            continue;
+        }
+        matching_groups += 1;
+        for (name, count) in matches {
+            writeln!(out, "Match: {count}x `{name}`")?;
+        }
+        writeln!(out, "Function size: {}", pretty_size(group.function_size))?;
+        if group.copies > 1 {
+            duplicated_groups += 1;
+            writeln!(
+                out,
+                "Copies: {} ({} excess bytes)",
+                group.copies,
+                pretty_size(group.excess_bytes())
+            )?;
+        } else {
+            writeln!(out, "Copies: 1 (not duplicated)")?;
+        }
+        if !others.is_empty() {
+            writeln!(out, "Other names in group:")?;
+            for (name, count) in others {
+                writeln!(out, "  {count}x `{name}`")?;
             }
+        }
+        print_instances(out, group)?;
+        writeln!(out)?;
+    }
+    if matching_groups == 0 {
+        bail!("No functions match `{pattern}`");
+    }
+    writeln!(
+        out,
+        "Matching groups: {matching_groups}, {duplicated_groups} with duplicates"
+    )?;
+    Ok(())
+}
+
+fn print_comparison(
+    out: &mut impl std::io::Write,
+    comparison: &Comparison,
+    symbol: &str,
+) -> Result {
+    for (label, listing) in [("Old", &comparison.old), ("New", &comparison.new)] {
+        write!(
+            out,
+            "{label}: {} in {} instructions",
+            pretty_size(listing.function_size),
+            listing.lines.len()
+        )?;
+        if listing.matches > 1 {
+            write!(
+                out,
+                " (the first of {} functions called `{symbol}`)",
+                listing.matches
+            )?;
+        }
+        writeln!(out)?;
+    }
+    let rows = comparison.rows();
+    let width = rows
+        .iter()
+        .map(|row| match row {
+            Row::Same(old) | Row::Changed(old, _) | Row::Removed(old) => old.len(),
+            Row::Added(_) => 0,
         })
-        .sum()
+        .max()
+        .unwrap_or(0);
+    writeln!(out)?;
+    for row in &rows {
+        let (old, marker, new) = match row {
+            Row::Same(line) => (*line, ' ', *line),
+            Row::Changed(old, new) => (*old, '|', *new),
+            Row::Removed(old) => (*old, '<', ""),
+            Row::Added(new) => ("", '>', *new),
+        };
+        let line = format!("{old:width$}  {marker}  {new}");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    writeln!(out)?;
+    if comparison.is_identical() {
+        writeln!(out, "`{symbol}` is identical in both binaries")?;
+    } else {
+        let differing = rows
+            .iter()
+            .filter(|row| !matches!(row, Row::Same(_)))
+            .count();
+        writeln!(
+            out,
+            "`{symbol}` differs: {differing} of {} lines changed",
+            rows.len()
+        )?;
+    }
+    Ok(())
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-struct NameAndSizeKey {
-    demangled_name: String,
-    function_size: u64,
+/// Writes the `--emit-symbol-index` file, if requested. This is done before any groups are left out
+/// of the report, so that it has every function that was checked.
+fn write_symbol_index(report: &DuplicationReport, args: &Args) -> Result {
+    let Some(path) = &args.emit_symbol_index else {
+        return Ok(());
+    };
+    let mut file = std::io::BufWriter::new(
+        std::fs::File::create(path)
+            .with_context(|| format!("Failed to create `{}`", path.display()))?,
+    );
+    duplicate_function_checker::symbol_index::write(&mut file, report)?;
+    Ok(file.flush()?)
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-struct InstructionsKey {
-    function_bytes: Vec<u8>,
+fn write_icf_symbols(out: &mut impl std::io::Write, groups: &[Vec<String>]) -> Result {
+    writeln!(
+        out,
+        "# Byte-identical functions, one group per line. The first symbol in each group is \
+         the copy to keep."
+    )?;
+    for names in groups {
+        writeln!(out, "{}", names.join(" "))?;
+    }
+    Ok(())
 }
 
-struct KeyBuilderInputs<'data, 'inputs> {
-    max_fn_address: u64,
-    object: &'inputs object::File<'data, &'data [u8]>,
-    args: &'inputs Args,
+fn print_similar(out: &mut impl std::io::Write, clusters: &[SimilarCluster]) -> Result {
+    for cluster in clusters {
+        writeln!(
+            out,
+            "Similar functions: {} variants, {} potential savings",
+            cluster.variants.len(),
+            pretty_size(cluster.potential_savings())
+        )?;
+        for variant in &cluster.variants {
+            writeln!(
+                out,
+                "  {} x{}:",
+                pretty_size(variant.function_size),
+                variant.copies
+            )?;
+            for name in &variant.names {
+                writeln!(out, "    `{name}`")?;
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
 }
-impl<'data, 'inputs> KeyBuilderInputs<'data, 'inputs> {
-    fn new(object: &'inputs object::File<'data, &'data [u8]>, args: &'inputs Args) -> Self {
-        let max_fn_address = object
-            .symbols()
-            .filter(|s| s.kind() == SymbolKind::Text)
-            .map(|s| s.address())
-            .max()
-            .unwrap_or(0);
-        Self {
-            max_fn_address,
-            object,
-            args,
+
+fn print_collisions(
+    out: &mut impl std::io::Write,
+    collisions: &[Collision],
+    args: &Args,
+) -> Result {
+    let mut collisions = collisions.iter().collect::<Vec<_>>();
+    let omitted = split_top(&mut collisions, args.top);
+    for collision in collisions {
+        writeln!(
+            out,
+            "Name collision: {} variants, {} in total",
+            collision.variants.len(),
+            pretty_size(collision.total_bytes())
+        )?;
+        writeln!(out, "  `{}`", collision.name)?;
+        for variant in &collision.variants {
+            writeln!(
+                out,
+                "  {} x{}",
+                pretty_size(variant.function_size),
+                variant.copies
+            )?;
         }
+        writeln!(out)?;
+    }
+    if !omitted.is_empty() {
+        writeln!(out, "{} more name collisions omitted", omitted.len())?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn print_hot_groups(
+    out: &mut impl std::io::Write,
+    profile: &Profile,
+    report: &DuplicationReport,
+    args: &Args,
+) -> Result {
+    let mut hot_groups = profile.hot_groups(report);
+    let omitted = split_top(&mut hot_groups, args.top);
+    for group in hot_groups {
+        writeln!(
+            out,
+            "Hot duplicate: {:.1}% of samples, {} of {} copies hot, {} excess",
+            group.samples as f64 / profile.total_samples() as f64 * 100.0,
+            group.hot_copies,
+            group.copies,
+            pretty_size(group.excess_bytes)
+        )?;
+        writeln!(out, "  `{}`", group.name)?;
+        writeln!(out)?;
+    }
+    if !omitted.is_empty() {
+        writeln!(out, "{} more hot duplicates omitted", omitted.len())?;
+        writeln!(out)?;
     }
+    Ok(())
 }
 
-impl Key for NameAndSizeKey {
-    fn from_sym<'data>(
-        sym: &object::Symbol<'data, '_, &'data [u8]>,
-        inputs: &KeyBuilderInputs,
-    ) -> Option<Self> {
-        let Ok(name) = sym.name() else {
-            return None;
-        };
-        let Ok(demangled) = rustc_demangle::try_demangle(name) else {
-            return None;
-        };
-        let demangled_name = if inputs.args.key == KeyType::NameWithoutRustHash {
-            format!("{demangled:#}")
-        } else {
-            demangled.to_string()
+fn print_symbol_problems(
+    out: &mut impl std::io::Write,
+    problems: &[SymbolProblem],
+    args: &Args,
+) -> Result {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let mut problems = problems.iter().collect::<Vec<_>>();
+    let omitted = split_top(&mut problems, args.top);
+    writeln!(out, "Symbol problems:")?;
+    if !omitted.is_empty() {
+        writeln!(out, "  {} smaller problems omitted", omitted.len())?;
+    }
+    for problem in problems {
+        let name = |name: &Option<String>| name.as_deref().unwrap_or("<unknown>").to_owned();
+        let description = match &problem.kind {
+            ProblemKind::Overlap {
+                other,
+                other_address,
+            } => format!(
+                "overlaps `{}` at {other_address:#x} by {}",
+                name(other),
+                pretty_size(problem.affected_bytes)
+            ),
+            ProblemKind::BeyondSection { section_end } => format!(
+                "extends {} past the end of its section at {section_end:#x}",
+                pretty_size(problem.affected_bytes)
+            ),
+            ProblemKind::SizeMismatch { other, other_size } => format!(
+                "is at the same address as `{}`, which is {}",
+                name(other),
+                pretty_size(*other_size)
+            ),
         };
-        Some(NameAndSizeKey {
-            demangled_name,
-            function_size: sym.size(),
-        })
+        writeln!(
+            out,
+            "  `{}` ({} at {:#x}) {description}",
+            name(&problem.name),
+            pretty_size(problem.size),
+            problem.address
+        )?;
     }
+    writeln!(out)?;
+    Ok(())
 }
 
-impl Key for InstructionsKey {
-    fn from_sym<'data>(
-        sym: &object::Symbol<'data, '_, &'data [u8]>,
-        inputs: &KeyBuilderInputs,
-    ) -> Option<Self> {
-        let fn_bytes = get_fn_bytes(sym, inputs.object)?;
-        // In order to determine if two functions at different addresses are the same, we need to
-        // fix up IP-relative instructions. We relocate all our functions to the address of the last
-        // function in the file. If we picked an earlier address, then some relative relocations
-        // might wrap. If we chose a much later address, then we might exceed a 32 bit offset.
-        // Although plausibly picking 2**31 would also work OK.
-        let bytes = normalise_asm(fn_bytes, sym.address(), inputs.max_fn_address).ok()?;
-        Some(Self {
-            function_bytes: bytes,
+fn symbol_problems_json(problems: &[SymbolProblem]) -> Value {
+    problems
+        .iter()
+        .map(|problem| {
+            let mut json = json!({
+                "kind": problem.kind.id(),
+                "name": problem.name,
+                "address": problem.address,
+                "size": problem.size,
+                "affected_bytes": problem.affected_bytes,
+            });
+            match &problem.kind {
+                ProblemKind::Overlap {
+                    other,
+                    other_address,
+                } => {
+                    json["other"] = json!(other);
+                    json["other_address"] = json!(other_address);
+                }
+                ProblemKind::BeyondSection { section_end } => {
+                    json["section_end"] = json!(section_end);
+                }
+                ProblemKind::SizeMismatch { other, other_size } => {
+                    json["other"] = json!(other);
+                    json["other_size"] = json!(other_size);
+                }
+            }
+            json
         })
-    }
+        .collect()
+}
+
+fn similar_json(clusters: &[SimilarCluster]) -> Value {
+    clusters
+        .iter()
+        .map(|cluster| {
+            json!({
+                "potential_savings": cluster.potential_savings(),
+                "variants": cluster.variants,
+            })
+        })
+        .collect()
+}
+
+fn duplicates_json(symbols: Vec<&DuplicateGroup>, details: GroupDetails) -> Value {
+    symbols
+        .iter()
+        .map(|v| Value::Object(group_fields(v, details)))
+        .collect()
 }
 
-struct SymInfo<'data> {
-    count: u64,
-    names: HashMap<Cow<'data, str>, u32>,
-    function_size: u64,
+/// Returns the fields of the JSON object describing `v`, so that callers can add their own.
+fn group_fields(v: &DuplicateGroup, details: GroupDetails) -> Map<String, Value> {
+    let mut fields = Map::new();
+    fields.insert("id".to_owned(), json!(v.id()));
+    fields.insert("function_size".to_owned(), json!(v.function_size));
+    fields.insert("copies".to_owned(), json!(v.copies));
+    fields.insert("excess_bytes".to_owned(), json!(v.excess_bytes()));
+    if let Some(m) = details.metrics.and_then(|m| m.get(v)) {
+        fields.insert("instructions".to_owned(), json!(m.instructions));
+        fields.insert("basic_blocks".to_owned(), json!(m.basic_blocks));
+    }
+    if let Some(location) = details.sources.and_then(|s| s.find_for_group(v)) {
+        let mut source = json!({"file": location.file});
+        if let Some(line) = location.line {
+            source["line"] = json!(line);
+        }
+        fields.insert("source".to_owned(), source);
+    }
+    if let Some(hint) = hint::suggest(v) {
+        fields.insert(
+            "hint".to_owned(),
+            json!({"kind": hint.id(), "text": hint.to_string()}),
+        );
+    }
+    let names = v
+        .names
+        .iter()
+        .map(|(name, count)| json!({"name": name, "count": count}))
+        .collect();
+    fields.insert("names".to_owned(), names);
+    let instances = v
+        .instances
+        .iter()
+        .map(|instance| {
+            let mut json = json!({
+                "address": instance.address,
+                "linkage": instance.linkage.id(),
+            });
+            for (key, value) in [
+                ("section", &instance.section),
+                ("member", &instance.member),
+                ("file", &instance.file),
+                ("comdat", &instance.comdat),
+            ] {
+                if let Some(value) = value {
+                    json[key] = json!(value);
+                }
+            }
+            json
+        })
+        .collect();
+    fields.insert("instances".to_owned(), instances);
+    fields
 }
 
-impl SymInfo<'_> {
-    fn excess_bytes(&self) -> u64 {
-        self.count.saturating_sub(1) * self.function_size
+/// Prints how long each phase took, peak memory use and how many functions were skipped. Phases
+/// that didn't happen, e.g. keying when the report was cached, take no time.
+fn print_stats(out: &mut impl std::io::Write, stats: &Stats) -> Result {
+    for phase in Phase::ALL {
+        let seconds = stats.duration(phase).as_secs_f64();
+        writeln!(out, "{:>15}: {seconds:.3}s", phase.description())?;
     }
+    let peak_rss = stats::peak_rss().map_or_else(|| "unknown".to_owned(), pretty_size);
+    writeln!(out, "       Peak RSS: {peak_rss}")?;
+    let total: u64 = Skip::ALL.iter().map(|skip| stats.skipped(*skip)).sum();
+    let reasons = Skip::ALL
+        .iter()
+        .map(|skip| format!("{} {}", stats.skipped(*skip), skip.description()))
+        .collect::<Vec<_>>();
+    writeln!(out, "        Skipped: {total} fns ({})", reasons.join(", "))?;
+    Ok(())
+}
+
+/// How `pretty_size` formats sizes. Set once from the command line, since sizes are formatted
+/// throughout the output code.
+static SIZE_FORMAT: OnceLock<SizeFormat> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug)]
+struct SizeFormat {
+    /// Whether to print sizes in bytes, without rounding.
+    exact: bool,
+
+    /// The number of decimal places for rounded sizes.
+    precision: usize,
 }
 
-fn normalise_asm(fn_bytes: &[u8], base_address: u64, new_address: u64) -> Result<Vec<u8>> {
-    const BIT_CLASS: u32 = 64;
-    let options = iced_x86::DecoderOptions::NONE;
-    let decoder = iced_x86::Decoder::with_ip(BIT_CLASS, fn_bytes, base_address, options);
-    let instructions = decoder.into_iter().collect::<Vec<_>>();
-    let block = iced_x86::InstructionBlock::new(&instructions, new_address);
-    Ok(iced_x86::BlockEncoder::encode(64, block, iced_x86::BlockEncoderOptions::NONE)?.code_buffer)
+impl Default for SizeFormat {
+    fn default() -> Self {
+        SizeFormat {
+            exact: false,
+            precision: 1,
+        }
+    }
 }
 
 fn pretty_size(size: u64) -> String {
+    let format = SIZE_FORMAT.get().copied().unwrap_or_default();
+    if format.exact {
+        return format!("{size}B");
+    }
+
     const KIBIBYTE: u64 = 1024;
     const MEBIBYTE: u64 = 1_048_576;
     const GIBIBYTE: u64 = 1_073_741_824;
@@ -324,5 +3101,19 @@ fn pretty_size(size: u64) -> String {
         _ => (size as f64 / EXBIBYTE as f64, "EiB"),
     };
 
-    format!("{:.1}{}", size, symbol)
+    format!("{:.*}{}", format.precision, size, symbol)
+}
+
+/// Formats a size that's a power of two without a decimal point, for use in labels.
+fn short_size(size: u64) -> String {
+    if size >= 1024 && size.is_multiple_of(1024) {
+        format!("{}KiB", size / 1024)
+    } else {
+        format!("{size}B")
+    }
+}
+
+fn pretty_size_delta(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{sign}{}", pretty_size(delta.unsigned_abs()))
 }
@@ -0,0 +1,212 @@
+//! GitHub-flavored Markdown that's compact enough to post as a comment on a pull request: the
+//! headline numbers, followed by a table of the worst duplicate groups.
+
+use crate::pretty_size;
+use crate::pretty_size_delta;
+use crate::sorted_duplicates;
+use crate::split_top;
+use crate::Args;
+use crate::Extras;
+use duplicate_function_checker::diff::GroupChange;
+use duplicate_function_checker::diff::ReportDiff;
+use duplicate_function_checker::Denominator;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How many groups are listed if `--top` isn't given. Nobody scrolls through hundreds of rows in a
+/// comment.
+const DEFAULT_TOP: usize = 20;
+
+pub(crate) fn write_report(
+    out: &mut impl std::io::Write,
+    bin: &Path,
+    report: &DuplicationReport,
+    extras: &Extras,
+    args: &Args,
+) -> Result {
+    writeln!(
+        out,
+        "### Duplicate functions in {}\n",
+        code(&bin.display().to_string())
+    )?;
+    let baseline_diff = extras.baseline_diff.as_ref();
+
+    writeln!(out, "| | Value |")?;
+    writeln!(out, "|---|---:|")?;
+    writeln!(
+        out,
+        "| Executable code | {} |",
+        pretty_size(report.text_size)
+    )?;
+    match report.denominator {
+        Denominator::Text => {}
+        Denominator::FileSize => {
+            writeln!(out, "| File size | {} |", pretty_size(report.file_size))?;
+        }
+        Denominator::LoadedSegments => {
+            writeln!(
+                out,
+                "| Loaded segments | {} |",
+                pretty_size(report.loaded_size)
+            )?;
+        }
+    }
+    writeln!(
+        out,
+        "| Excess bytes | {} ({:.1}% of {}) |",
+        pretty_size(report.excess_bytes()),
+        report.excess_fraction() * 100.0,
+        report.denominator.description()
+    )?;
+    if let Some(diff) = baseline_diff {
+        writeln!(
+            out,
+            "| Excess bytes in baseline | {} ({}) |",
+            pretty_size(diff.old_excess_bytes),
+            pretty_size_delta(diff.excess_bytes_delta())
+        )?;
+        writeln!(
+            out,
+            "| New or grown groups | {} |",
+            diff.regressions().count()
+        )?;
+    }
+    writeln!(
+        out,
+        "| Functions with duplicates | {} |",
+        report.duplicated_functions()
+    )?;
+    writeln!(out, "| Excess instances | {} |", report.excess_instances())?;
+
+    let mut symbols = sorted_duplicates(report, args);
+    let omitted = split_top(&mut symbols, Some(args.top.unwrap_or(DEFAULT_TOP)));
+    if symbols.is_empty() {
+        return Ok(());
+    }
+    writeln!(out)?;
+    // Changes for groups that are in this report have the group's ID. Those that aren't in the
+    // baseline diff haven't changed.
+    let mut changes: HashMap<&str, &GroupChange> = HashMap::new();
+    if let Some(diff) = baseline_diff {
+        for change in diff.appeared.iter().chain(&diff.changed) {
+            if let Some(id) = &change.id {
+                changes.insert(id, change);
+            }
+        }
+    }
+    if baseline_diff.is_some() {
+        writeln!(
+            out,
+            "| Function | Size | Copies before | Copies | Excess before | Excess bytes | Group |"
+        )?;
+        writeln!(out, "|---|---:|---:|---:|---:|---:|---|")?;
+    } else {
+        writeln!(out, "| Function | Size | Copies | Excess bytes | Group |")?;
+        writeln!(out, "|---|---:|---:|---:|---|")?;
+    }
+    for group in symbols.iter().rev() {
+        let name = group.representative_name().unwrap_or_default();
+        write!(
+            out,
+            "| {} | {} |",
+            code(name),
+            pretty_size(group.function_size)
+        )?;
+        if baseline_diff.is_some() {
+            let (old_copies, old_excess_bytes) = changes
+                .get(group.id().as_str())
+                .map_or((group.copies, group.excess_bytes()), |change| {
+                    (change.old_copies, change.old_excess_bytes)
+                });
+            write!(out, " {old_copies} |")?;
+            write!(out, " {} |", group.copies)?;
+            write!(out, " {} |", pretty_size(old_excess_bytes))?;
+        } else {
+            write!(out, " {} |", group.copies)?;
+        }
+        writeln!(
+            out,
+            " {} | `{}` |",
+            pretty_size(group.excess_bytes()),
+            group.id()
+        )?;
+    }
+    if !omitted.is_empty() {
+        writeln!(
+            out,
+            "\n{} more groups with {} of excess bytes not shown.",
+            omitted.len(),
+            pretty_size(omitted.iter().map(|group| group.excess_bytes()).sum())
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the changes between two binaries, largest first.
+pub(crate) fn write_diff(out: &mut impl std::io::Write, diff: &ReportDiff) -> Result {
+    writeln!(out, "### Change in duplicate functions\n")?;
+    writeln!(out, "| | Before | After | Change |")?;
+    writeln!(out, "|---|---:|---:|---:|")?;
+    writeln!(
+        out,
+        "| Excess bytes | {} | {} | {} |",
+        pretty_size(diff.old_excess_bytes),
+        pretty_size(diff.new_excess_bytes),
+        pretty_size_delta(diff.excess_bytes_delta())
+    )?;
+    writeln!(
+        out,
+        "\n{} groups appeared, {} disappeared and {} changed.",
+        diff.appeared.len(),
+        diff.disappeared.len(),
+        diff.changed.len()
+    )?;
+
+    let mut changes = diff
+        .appeared
+        .iter()
+        .chain(&diff.disappeared)
+        .chain(&diff.changed)
+        .collect::<Vec<_>>();
+    if changes.is_empty() {
+        return Ok(());
+    }
+    changes.sort_by(|a, b| {
+        let key = |c: &GroupChange| (c.excess_bytes_delta().abs(), c.name.clone());
+        key(b).cmp(&key(a))
+    });
+    writeln!(out)?;
+    writeln!(out, "| Function | Copies before | Copies after | Change |")?;
+    writeln!(out, "|---|---:|---:|---:|")?;
+    for c in changes.iter().take(DEFAULT_TOP) {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            code(&c.name),
+            c.old_copies,
+            c.new_copies,
+            pretty_size_delta(c.excess_bytes_delta())
+        )?;
+    }
+    if changes.len() > DEFAULT_TOP {
+        writeln!(
+            out,
+            "\n{} more changes not shown.",
+            changes.len() - DEFAULT_TOP
+        )?;
+    }
+    Ok(())
+}
+
+/// Formats `text` as a code span that can go in a table cell. A pipe would end the cell, even in a
+/// code span, unless it's escaped.
+fn code(text: &str) -> String {
+    let text = text.replace('|', "\\|");
+    if text.contains('`') {
+        format!("`` {text} ``")
+    } else {
+        format!("`{text}`")
+    }
+}
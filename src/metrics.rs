@@ -0,0 +1,135 @@
+//! Instruction and basic block counts for duplicate groups, so that long straight-line code can be
+//! told apart from branchy code without disassembling it. Basic blocks are only counted roughly: a
+//! block starts at the start of the function, at each branch target within it and after each
+//! branch, return or indirect jump. Calls don't end blocks and jump tables aren't followed.
+
+use crate::arch;
+use crate::arch::Flow;
+use crate::arch::Instruction;
+use crate::bitness;
+use crate::decode_asm;
+use crate::disasm::function_bytes;
+use crate::is_x86;
+use crate::strip_trailing_padding;
+use crate::DuplicateGroup;
+use crate::Options;
+use iced_x86::FlowControl;
+use iced_x86::OpKind;
+use std::collections::BTreeSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CodeMetrics {
+    pub instructions: u64,
+    pub basic_blocks: u64,
+}
+
+impl CodeMetrics {
+    /// Returns the metrics of the function at `address` that's made up of `instructions`, or `None`
+    /// if there aren't any.
+    fn from_instructions(instructions: &[Instruction], address: u64) -> Option<CodeMetrics> {
+        // If padding was stripped, the function ends with its last instruction, not its symbol.
+        let end = instructions
+            .iter()
+            .map(|instruction| instruction.end)
+            .max()?;
+        let fn_range = address..end;
+        let mut leaders = BTreeSet::from([fn_range.start]);
+        for instruction in instructions {
+            match instruction.flow {
+                Flow::Next => continue,
+                Flow::Branch(target) => {
+                    leaders.insert(target);
+                }
+                Flow::Stop => {}
+            }
+            leaders.insert(instruction.end);
+        }
+        // Tail calls and the end of the function don't start blocks that we'd count.
+        leaders.retain(|address| fn_range.contains(address));
+        Some(CodeMetrics {
+            instructions: instructions.len() as u64,
+            basic_blocks: leaders.len() as u64,
+        })
+    }
+}
+
+/// Works out the metrics of duplicate groups as they're needed, so that only those that are printed
+/// are decoded. Since the copies are identical, they're computed from the first copy of each group
+/// that we can find.
+pub struct GroupMetrics<'data> {
+    object: object::File<'data, &'data [u8]>,
+
+    /// How to split the code into instructions, or `None` for x86.
+    arch: Option<Box<dyn arch::Arch>>,
+
+    bitness: u32,
+    strip_padding: bool,
+}
+
+impl<'data> GroupMetrics<'data> {
+    /// Returns `None` if `data` isn't a single object, e.g. because it's an archive, or if we don't
+    /// know how to split its architecture's code into instructions. Unless `options.keep_padding`
+    /// is set, trailing padding isn't counted, just as it isn't part of the key.
+    pub fn from_data(data: &'data [u8], options: &Options) -> Option<GroupMetrics<'data>> {
+        let object = object::File::parse(data).ok()?;
+        let arch = if is_x86(&object) {
+            None
+        } else {
+            Some(arch::for_object(&object)?)
+        };
+        Some(GroupMetrics {
+            bitness: bitness(&object),
+            object,
+            arch,
+            strip_padding: !options.keep_padding,
+        })
+    }
+
+    pub fn get(&self, group: &DuplicateGroup) -> Option<CodeMetrics> {
+        group.instances.iter().find_map(|instance| {
+            let bytes = function_bytes(&self.object, instance, group.function_size)?;
+            let address = instance.address;
+            let instructions = match &self.arch {
+                Some(arch) => arch.instructions(bytes, address, self.strip_padding)?,
+                None => x86_instructions(bytes, address, self.bitness, self.strip_padding),
+            };
+            CodeMetrics::from_instructions(&instructions, address)
+        })
+    }
+}
+
+fn x86_instructions(
+    fn_bytes: &[u8],
+    address: u64,
+    bitness: u32,
+    strip_padding: bool,
+) -> Vec<Instruction> {
+    let mut instructions = decode_asm(fn_bytes, address, bitness);
+    if strip_padding {
+        strip_trailing_padding(&mut instructions);
+    }
+    instructions
+        .into_iter()
+        .map(|instruction| {
+            let flow = match instruction.flow_control() {
+                FlowControl::UnconditionalBranch | FlowControl::ConditionalBranch => {
+                    match instruction.op0_kind() {
+                        OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+                            Flow::Branch(instruction.near_branch_target())
+                        }
+                        // Far jumps leave the function.
+                        _ => Flow::Stop,
+                    }
+                }
+                FlowControl::IndirectBranch | FlowControl::Return | FlowControl::Exception => {
+                    Flow::Stop
+                }
+                _ => Flow::Next,
+            };
+            Instruction {
+                end: instruction.next_ip(),
+                flow,
+            }
+        })
+        .collect()
+}
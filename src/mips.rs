@@ -0,0 +1,110 @@
+//! Normalisation of MIPS code, 32 and 64 bit, in either byte order. The PC-relative instructions
+//! are branches, whose offsets are relative to the delay slot, and `j` and `jal`, which replace the
+//! low bits of the delay slot's address. microMIPS and MIPS16e, as well as release 6's compact
+//! branches, aren't recognised.
+
+use crate::arch::normalise_words;
+use crate::arch::read_word;
+use crate::arch::sign_extend;
+use crate::arch::strip_trailing_words;
+use crate::arch::trim_trailing_words;
+use crate::arch::Arch;
+use crate::arch::Flow;
+use crate::arch::Instruction;
+use crate::arch::Normalised;
+use crate::Function;
+
+/// `sll $zero, $zero, 0`, which is the canonical NOP.
+const NOP: u32 = 0;
+
+pub(crate) struct Mips {
+    pub(crate) little_endian: bool,
+}
+
+impl Arch for Mips {
+    fn normalise(&self, fn_bytes: &[u8], function: &Function, strip_padding: bool) -> Normalised {
+        let mut normalised =
+            normalise_words(fn_bytes, function.address, self.little_endian, reference);
+        if strip_padding {
+            strip_trailing_words(&mut normalised.bytes, &[NOP], self.little_endian);
+        }
+        normalised
+    }
+
+    fn instructions(
+        &self,
+        mut fn_bytes: &[u8],
+        address: u64,
+        strip_padding: bool,
+    ) -> Option<Vec<Instruction>> {
+        if strip_padding {
+            let mut len = trim_trailing_words(fn_bytes, &[NOP], self.little_endian).len();
+            // A NOP straight after the last branch is its delay slot, not padding.
+            if len >= 4 && len < fn_bytes.len() {
+                let last = read_word(&fn_bytes[len - 4..], self.little_endian);
+                if flow(last, address + len as u64 - 4) != Flow::Next {
+                    len += 4;
+                }
+            }
+            fn_bytes = &fn_bytes[..len];
+        }
+        let instructions = fn_bytes
+            .chunks_exact(4)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let address = address + index as u64 * 4;
+                let flow = flow(read_word(chunk, self.little_endian), address);
+                // The delay slot runs before the branch is taken, so it's part of the same block.
+                let len = if flow == Flow::Next { 4 } else { 8 };
+                Instruction {
+                    end: address + len,
+                    flow,
+                }
+            })
+            .collect();
+        Some(instructions)
+    }
+}
+
+/// Returns how the instruction `word` at `address` affects control flow. `jal`, `jalr` and the
+/// linking branches are calls.
+fn flow(word: u32, address: u64) -> Flow {
+    let opcode = word >> 26;
+    let links = opcode == 0x03 || (opcode == 0x01 && (word >> 16) & 0x10 != 0);
+    if opcode == 0 && word & 0x3f == 0x08 {
+        // `jr`, which is also how functions return.
+        return Flow::Stop;
+    }
+    match reference(word, address) {
+        Some((target, _)) if !links => Flow::Branch(target),
+        _ => Flow::Next,
+    }
+}
+
+/// If the instruction `word` at `address` is PC-relative, returns its target and the bits that hold
+/// its offset.
+fn reference(word: u32, address: u64) -> Option<(u64, u32)> {
+    let delay_slot = address.wrapping_add(4);
+    let opcode = word >> 26;
+    let rs = (word >> 21) & 0x1f;
+    let rt = (word >> 16) & 0x1f;
+    let is_branch = match opcode {
+        // `bltz`, `bgez`, `bal` and their likely and linking variants.
+        0x01 => matches!(rt, 0x00..=0x03 | 0x10..=0x13),
+        // `beq`, `bne`, `blez`, `bgtz` and their likely variants.
+        0x04..=0x07 | 0x14..=0x17 => true,
+        // `bc1f`, `bc1t`, `bc2f` and `bc2t`.
+        0x11 | 0x12 => rs == 0x08,
+        _ => false,
+    };
+    if is_branch {
+        let offset = sign_extend((word & 0xffff) << 2, 18);
+        Some((delay_slot.wrapping_add_signed(offset), 0xffff))
+    } else if opcode == 0x02 || opcode == 0x03 {
+        // `j` and `jal` stay within the 256MiB region of their delay slot.
+        let target = delay_slot & !0x0fff_ffff | u64::from(word & 0x03ff_ffff) << 2;
+        Some((target, 0x03ff_ffff))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,76 @@
+//! Reading of functions from a PDB, for PE binaries that don't have a usable symbol table.
+
+use crate::linkage::Binding;
+use crate::linkage::Linkage;
+use crate::text_section_ranges;
+use crate::Function;
+use crate::Result;
+use anyhow::Context;
+use object::Object as _;
+use pdb::FallibleIterator as _;
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Returns the functions with non-zero sizes described by the PDB at `path`, which must have been
+/// produced alongside `object`.
+pub(crate) fn functions<'data>(
+    path: &Path,
+    object: &object::File<'data, &'data [u8]>,
+) -> Result<Vec<Function<'data>>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open PDB `{}`", path.display()))?;
+    read_functions(file, object).with_context(|| format!("Failed to read PDB `{}`", path.display()))
+}
+
+fn read_functions<'data>(
+    file: std::fs::File,
+    object: &object::File<'data, &'data [u8]>,
+) -> Result<Vec<Function<'data>>> {
+    let mut pdb = pdb::PDB::open(file)?;
+    let address_map = pdb.address_map()?;
+    let image_base = object.relative_address_base();
+    let text_sections = text_section_ranges(object);
+
+    // Procedure symbols, unlike public symbols, record the function's size. They live in the
+    // symbol stream of the module that defined them.
+    let mut functions = Vec::new();
+    let debug_info = pdb.debug_information()?;
+    let mut modules = debug_info.modules()?;
+    while let Some(module) = modules.next()? {
+        let Some(module_info) = pdb.module_info(&module)? else {
+            continue;
+        };
+        let mut symbols = module_info.symbols()?;
+        while let Some(symbol) = symbols.next()? {
+            let Ok(pdb::SymbolData::Procedure(procedure)) = symbol.parse() else {
+                continue;
+            };
+            let Some(rva) = procedure.offset.to_rva(&address_map) else {
+                continue;
+            };
+            if procedure.len == 0 {
+                continue;
+            }
+            let address = image_base + u64::from(rva.0);
+            let section_index = text_sections
+                .iter()
+                .find(|(_, range)| range.contains(&address))
+                .map(|(index, _)| *index);
+            functions.push(Function {
+                name: Some(Cow::Owned(procedure.name.to_string().into_owned())),
+                address,
+                size: u64::from(procedure.len),
+                section_index,
+                size_inferred: false,
+                thumb: false,
+                linkage: Linkage::from_binding(if procedure.global {
+                    Binding::Global
+                } else {
+                    Binding::Local
+                }),
+                file: None,
+            });
+        }
+    }
+    Ok(functions)
+}
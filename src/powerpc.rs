@@ -0,0 +1,142 @@
+//! Normalisation of PowerPC code, 32 and 64 bit, in either byte order. The PC-relative
+//! instructions are `b` and `bc` without the absolute-address bit, and Power10's prefixed loads,
+//! stores and `paddi` with the PC-relative bit, e.g. `pld` and `pla`.
+
+use crate::arch::read_word;
+use crate::arch::sign_extend;
+use crate::arch::strip_trailing_words;
+use crate::arch::trim_trailing_words;
+use crate::arch::write_word;
+use crate::arch::Arch;
+use crate::arch::Flow;
+use crate::arch::Instruction;
+use crate::arch::Normalised;
+use crate::Function;
+
+/// `ori 0, 0, 0`, which is the canonical NOP.
+const NOP: u32 = 0x6000_0000;
+
+pub(crate) struct PowerPc {
+    pub(crate) little_endian: bool,
+}
+
+impl Arch for PowerPc {
+    fn normalise(&self, fn_bytes: &[u8], function: &Function, strip_padding: bool) -> Normalised {
+        let mut normalised = normalise(fn_bytes, function.address, self.little_endian);
+        if strip_padding {
+            strip_trailing_words(&mut normalised.bytes, &[NOP], self.little_endian);
+        }
+        normalised
+    }
+
+    fn instructions(
+        &self,
+        mut fn_bytes: &[u8],
+        address: u64,
+        strip_padding: bool,
+    ) -> Option<Vec<Instruction>> {
+        if strip_padding {
+            fn_bytes = trim_trailing_words(fn_bytes, &[NOP], self.little_endian);
+        }
+        let mut instructions = Vec::new();
+        let mut offset = 0;
+        while offset + 4 <= fn_bytes.len() {
+            let word = read_word(&fn_bytes[offset..], self.little_endian);
+            let pc = address + offset as u64;
+            // Prefixed instructions are loads, stores and arithmetic, never branches.
+            let (len, flow) = if word >> 26 == 1 {
+                (8, Flow::Next)
+            } else {
+                (4, flow(word, pc))
+            };
+            instructions.push(Instruction {
+                end: pc + len,
+                flow,
+            });
+            offset += len as usize;
+        }
+        Some(instructions)
+    }
+}
+
+/// Returns how the instruction `word` at `pc` affects control flow. Branches with the LK bit set,
+/// e.g. `bl` and `bctrl`, are calls.
+fn flow(word: u32, pc: u64) -> Flow {
+    let links = word & 1 != 0;
+    match word >> 26 {
+        16 | 18 if links => Flow::Next,
+        16 | 18 => match reference(word, pc) {
+            Some((target, _)) => Flow::Branch(target),
+            // Absolute branches only make sense to fixed addresses outside the function.
+            None => Flow::Stop,
+        },
+        // `bclr`, `bcctr` and `bctar`, i.e. returns and indirect jumps.
+        19 if !links && matches!((word >> 1) & 0x3ff, 16 | 528 | 560) => Flow::Stop,
+        _ => Flow::Next,
+    }
+}
+
+fn normalise(fn_bytes: &[u8], address: u64, little_endian: bool) -> Normalised {
+    let mut bytes = fn_bytes.to_vec();
+    let mut references = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= bytes.len() {
+        let word = read_word(&bytes[offset..], little_endian);
+        let pc = address + offset as u64;
+        if word >> 26 == 1 {
+            // A prefix, which makes up a single 8 byte instruction with the word that follows.
+            if offset + 8 > bytes.len() {
+                break;
+            }
+            let suffix = read_word(&bytes[offset + 4..], little_endian);
+            if let Some(target) = prefixed_reference(word, suffix, pc) {
+                write_word(&mut bytes[offset..], word & !0x3_ffff, little_endian);
+                write_word(&mut bytes[offset + 4..], suffix & !0xffff, little_endian);
+                references.push((offset, target));
+            }
+            offset += 8;
+            continue;
+        }
+        if let Some((target, mask)) = reference(word, pc) {
+            write_word(&mut bytes[offset..], word & !mask, little_endian);
+            references.push((offset, target));
+        }
+        offset += 4;
+    }
+    Normalised { bytes, references }
+}
+
+/// If the instruction `word` at `pc` is a relative branch, returns its target and the bits that
+/// hold its offset. Branches with the AA bit set go to an absolute address, so they're the same
+/// wherever they are.
+fn reference(word: u32, pc: u64) -> Option<(u64, u32)> {
+    let absolute = word & 2 != 0;
+    match word >> 26 {
+        // `b` and `bl`.
+        18 if !absolute => {
+            let offset = sign_extend(word & 0x03ff_fffc, 26);
+            Some((pc.wrapping_add_signed(offset), 0x03ff_fffc))
+        }
+        // `bc` and `bcl`, i.e. conditional branches and the `bcl 20, 31` that loads the PC.
+        16 if !absolute => {
+            let offset = sign_extend(word & 0xfffc, 16);
+            Some((pc.wrapping_add_signed(offset), 0xfffc))
+        }
+        _ => None,
+    }
+}
+
+/// If the prefixed instruction made up of `prefix` and `suffix` at `pc` is PC-relative, returns its
+/// target. Its 34 bit displacement is split between the low 18 bits of the prefix and the low 16
+/// bits of the suffix.
+fn prefixed_reference(prefix: u32, suffix: u32, pc: u64) -> Option<u64> {
+    let prefix_type = (prefix >> 24) & 3;
+    let pc_relative = (prefix >> 20) & 1 != 0;
+    // Types 0 and 2 are the 8 byte and modified load/store forms, which have a displacement. The
+    // others are register-to-register.
+    if !pc_relative || !matches!(prefix_type, 0 | 2) {
+        return None;
+    }
+    let displacement = u64::from(prefix & 0x3_ffff) << 16 | u64::from(suffix & 0xffff);
+    Some(pc.wrapping_add_signed(sign_extend(displacement, 34)))
+}
@@ -0,0 +1,130 @@
+//! Weighting of duplicates by how often a CPU profile caught them executing. Duplicated cold code
+//! only costs disk space, but when several copies of a function are hot, they compete for the
+//! instruction cache.
+//!
+//! Profiles are text with one sample address per line, as written by `perf script -F ip`. A
+//! decimal count may follow the address, for profiles that have already been aggregated:
+//!
+//! ```text
+//! 401126
+//! 0x401130 250
+//! ```
+
+use crate::DuplicationReport;
+use crate::Result;
+use anyhow::bail;
+use serde::Serialize;
+use std::ops::Range;
+
+pub struct Profile {
+    /// Sample addresses, sorted and without duplicates, paired with the total number of samples
+    /// up to and including that address.
+    cumulative: Vec<(u64, u64)>,
+}
+
+/// A duplicate group that was sampled by the profile.
+#[derive(Serialize)]
+pub struct HotGroup {
+    pub name: String,
+    pub function_size: u64,
+    pub copies: u64,
+
+    /// The number of copies with at least one sample.
+    pub hot_copies: u64,
+
+    /// The number of samples in all copies.
+    pub samples: u64,
+
+    pub excess_bytes: u64,
+}
+
+impl Profile {
+    /// Parses a profile. `bias` is subtracted from each address, which is needed when the profile
+    /// has run-time addresses of a position-independent binary. Samples below `bias` are ignored.
+    pub fn parse(text: &str, bias: u64) -> Result<Profile> {
+        let mut samples = Vec::new();
+        for line in text.lines() {
+            let mut parts = line.split_whitespace();
+            let Some(address) = parts.next() else {
+                continue;
+            };
+            let address = address.strip_prefix("0x").unwrap_or(address);
+            // Lines that don't start with an address, e.g. comments or `perf script` headers, are
+            // skipped.
+            let Ok(address) = u64::from_str_radix(address, 16) else {
+                continue;
+            };
+            // `perf script -F ip,sym` puts the symbol after the address, so anything that isn't a
+            // count means a single sample.
+            let count = parts
+                .next()
+                .and_then(|count| count.parse::<u64>().ok())
+                .unwrap_or(1);
+            if let Some(address) = address.checked_sub(bias) {
+                samples.push((address, count));
+            }
+        }
+        if samples.is_empty() {
+            bail!(
+                "No samples found. Expected `perf script -F ip` output or lines of `ADDRESS COUNT`"
+            );
+        }
+        samples.sort_unstable();
+        let mut cumulative: Vec<(u64, u64)> = Vec::with_capacity(samples.len());
+        let mut total = 0;
+        for (address, count) in samples {
+            total += count;
+            match cumulative.last_mut() {
+                Some(last) if last.0 == address => last.1 = total,
+                _ => cumulative.push((address, total)),
+            }
+        }
+        Ok(Profile { cumulative })
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.cumulative.last().map_or(0, |(_, total)| *total)
+    }
+
+    /// Returns the number of samples with addresses in `range`.
+    pub fn samples_in(&self, range: Range<u64>) -> u64 {
+        let before = |address: u64| {
+            let index = self.cumulative.partition_point(|(a, _)| *a < address);
+            index.checked_sub(1).map_or(0, |i| self.cumulative[i].1)
+        };
+        before(range.end) - before(range.start)
+    }
+
+    /// Returns the duplicate groups of `report` that were sampled, ranked by their excess bytes
+    /// weighted by their samples, hottest last.
+    pub fn hot_groups(&self, report: &DuplicationReport) -> Vec<HotGroup> {
+        let mut hot_groups = report
+            .duplicates()
+            .filter_map(|group| {
+                let samples_per_copy = group
+                    .instances
+                    .iter()
+                    .map(|instance| {
+                        self.samples_in(instance.address..instance.address + group.function_size)
+                    })
+                    .collect::<Vec<_>>();
+                let samples = samples_per_copy.iter().sum::<u64>();
+                (samples > 0).then(|| HotGroup {
+                    name: group.representative_name().unwrap_or_default().to_owned(),
+                    function_size: group.function_size,
+                    copies: group.copies,
+                    hot_copies: samples_per_copy.iter().filter(|s| **s > 0).count() as u64,
+                    samples,
+                    excess_bytes: group.excess_bytes(),
+                })
+            })
+            .collect::<Vec<_>>();
+        hot_groups.sort_by_key(|group| {
+            (
+                u128::from(group.excess_bytes) * u128::from(group.samples),
+                group.samples,
+            )
+        });
+        hot_groups
+    }
+}
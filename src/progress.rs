@@ -0,0 +1,36 @@
+//! A progress bar on stderr, so that the tool doesn't appear to hang on huge binaries.
+
+use duplicate_function_checker::Progress;
+
+pub(crate) struct ProgressBar {
+    bar: indicatif::ProgressBar,
+}
+
+impl ProgressBar {
+    /// Creates a progress bar that's drawn to stderr if it's a terminal.
+    pub(crate) fn new() -> ProgressBar {
+        let bar = indicatif::ProgressBar::new(0);
+        if let Ok(style) = indicatif::ProgressStyle::with_template(
+            "{msg} [{elapsed_precise}] {wide_bar} {pos}/{len} functions",
+        ) {
+            bar.set_style(style);
+        }
+        bar.set_message("Keying functions");
+        ProgressBar { bar }
+    }
+
+    /// Removes the progress bar, so that it doesn't get mixed up with the output.
+    pub(crate) fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+impl Progress for ProgressBar {
+    fn add_total(&self, functions: u64) {
+        self.bar.inc_length(functions);
+    }
+
+    fn advance(&self, functions: u64) {
+        self.bar.inc(functions);
+    }
+}
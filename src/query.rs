@@ -0,0 +1,308 @@
+//! Expressions for `--where`, which select the duplicate groups that are reported and counted
+//! towards limits, e.g.
+//!
+//! ```text
+//! copies > 4 && size > 1024 && name ~ "serde"
+//! ```
+//!
+//! The numeric fields are `copies`, `size`, which is the size of one copy, and `excess`, which is
+//! the group's excess bytes. They're compared with `==`, `!=`, `<`, `<=`, `>` or `>=`. The string
+//! fields are `name`, `crate`, `section` and `id`, the group's ID as shown in reports. Since a
+//! group can have several names and its copies can be in several sections, `~` holds if any of
+//! them matches a regular expression, and `==` if any of them is equal to a string. `!~` and `!=`
+//! hold if none does. Comparisons are combined with `&&`, `||`, `!` and parentheses.
+
+use anyhow::bail;
+use anyhow::Context;
+use duplicate_function_checker::DuplicateGroup;
+use duplicate_function_checker::Result;
+use regex::Regex;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use std::str::FromStr;
+
+#[derive(Clone, Debug)]
+pub(crate) enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Number(NumberField, Comparison, u64),
+    Matches(StringField, Regex, bool),
+    Equals(StringField, String, bool),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum NumberField {
+    Copies,
+    Size,
+    Excess,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum StringField {
+    Name,
+    Crate,
+    Section,
+    Id,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Query {
+    pub(crate) fn matches(&self, group: &DuplicateGroup) -> bool {
+        match self {
+            Query::And(a, b) => a.matches(group) && b.matches(group),
+            Query::Or(a, b) => a.matches(group) || b.matches(group),
+            Query::Not(query) => !query.matches(group),
+            Query::Number(field, comparison, value) => {
+                let actual = match field {
+                    NumberField::Copies => group.copies,
+                    NumberField::Size => group.function_size,
+                    NumberField::Excess => group.excess_bytes(),
+                };
+                match comparison {
+                    Comparison::Eq => actual == *value,
+                    Comparison::Ne => actual != *value,
+                    Comparison::Lt => actual < *value,
+                    Comparison::Le => actual <= *value,
+                    Comparison::Gt => actual > *value,
+                    Comparison::Ge => actual >= *value,
+                }
+            }
+            Query::Matches(field, regex, expected) => {
+                field.values(group).any(|value| regex.is_match(&value)) == *expected
+            }
+            Query::Equals(field, text, expected) => {
+                field.values(group).any(|value| value == *text) == *expected
+            }
+        }
+    }
+}
+
+impl StringField {
+    fn values<'a>(self, group: &'a DuplicateGroup) -> Box<dyn Iterator<Item = String> + 'a> {
+        match self {
+            StringField::Name => Box::new(group.names.keys().cloned()),
+            StringField::Crate => Box::new(
+                group
+                    .names
+                    .keys()
+                    .filter_map(|name| duplicate_function_checker::crate_name(name)),
+            ),
+            StringField::Section => Box::new(
+                group
+                    .instances
+                    .iter()
+                    .filter_map(|instance| instance.section.clone()),
+            ),
+            StringField::Id => Box::new(std::iter::once(group.id())),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?.into_iter().peekable(),
+        };
+        let query = parser.or()?;
+        if let Some(token) = parser.tokens.next() {
+            bail!("Unexpected {token} in `{s}`");
+        }
+        Ok(query)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Field(String),
+    Number(u64),
+    String(String),
+    Op(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Field(name) => write!(f, "`{name}`"),
+            Token::Number(value) => write!(f, "`{value}`"),
+            Token::String(text) => write!(f, "\"{text}\""),
+            Token::Op(op) => write!(f, "`{op}`"),
+        }
+    }
+}
+
+/// Operators, longest first so that e.g. `<=` isn't read as `<`.
+const OPERATORS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "!~", "<", ">", "~", "!", "(", ")",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            tokens.push(Token::String(string_literal(text, &mut chars)?));
+        } else if c.is_ascii_digit() {
+            let digits = take_while(text, &mut chars, |c| c.is_ascii_alphanumeric());
+            let value = digits
+                .parse()
+                .with_context(|| format!("Invalid number `{digits}` in `{text}`"))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() {
+            let name = take_while(text, &mut chars, |c| c.is_ascii_alphanumeric() || c == '_');
+            tokens.push(Token::Field(name.to_owned()));
+        } else if let Some(op) = OPERATORS.iter().find(|op| text[start..].starts_with(**op)) {
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Op(op));
+        } else {
+            bail!("Unexpected `{c}` in `{text}`");
+        }
+    }
+    Ok(tokens)
+}
+
+fn take_while<'a>(
+    text: &'a str,
+    chars: &mut Peekable<CharIndices>,
+    predicate: impl Fn(char) -> bool,
+) -> &'a str {
+    let start = chars.peek().map_or(text.len(), |(i, _)| *i);
+    while chars.next_if(|(_, c)| predicate(*c)).is_some() {}
+    let end = chars.peek().map_or(text.len(), |(i, _)| *i);
+    &text[start..end]
+}
+
+/// Reads the rest of a string literal whose opening quote has been consumed. Backslashes escape
+/// quotes and backslashes, and are otherwise kept, so that regular expressions can be written as
+/// usual.
+fn string_literal(text: &str, chars: &mut Peekable<CharIndices>) -> Result<String> {
+    let mut literal = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '"' => return Ok(literal),
+            '\\' => match chars.next() {
+                Some((_, escaped @ ('"' | '\\'))) => literal.push(escaped),
+                Some((_, other)) => {
+                    literal.push('\\');
+                    literal.push(other);
+                }
+                None => break,
+            },
+            _ => literal.push(c),
+        }
+    }
+    bail!("Unterminated string in `{text}`")
+}
+
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Query> {
+        let mut query = self.and()?;
+        while self.tokens.next_if_eq(&Token::Op("||")).is_some() {
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query> {
+        let mut query = self.unary()?;
+        while self.tokens.next_if_eq(&Token::Op("&&")).is_some() {
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+        Ok(query)
+    }
+
+    fn unary(&mut self) -> Result<Query> {
+        match self.tokens.next() {
+            Some(Token::Op("!")) => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Op("(")) => {
+                let query = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Op(")")) => Ok(query),
+                    Some(token) => bail!("Expected `)`, found {token}"),
+                    None => bail!("Expected `)`"),
+                }
+            }
+            Some(Token::Field(name)) => self.comparison(&name),
+            Some(token) => bail!("Expected a field, found {token}"),
+            None => bail!("Expected a field"),
+        }
+    }
+
+    fn comparison(&mut self, field: &str) -> Result<Query> {
+        let Some(Token::Op(op)) = self.tokens.next() else {
+            bail!("Expected an operator after `{field}`");
+        };
+        let value = self.tokens.next();
+        if let Some(number) = number_field(field) {
+            let comparison = match op {
+                "==" => Comparison::Eq,
+                "!=" => Comparison::Ne,
+                "<" => Comparison::Lt,
+                "<=" => Comparison::Le,
+                ">" => Comparison::Gt,
+                ">=" => Comparison::Ge,
+                _ => bail!("`{op}` can't be used with `{field}`"),
+            };
+            let Some(Token::Number(value)) = value else {
+                bail!("Expected a number after `{op}`");
+            };
+            return Ok(Query::Number(number, comparison, value));
+        }
+        let Some(string_field) = string_field(field) else {
+            bail!(
+                "Unknown field `{field}`, expected copies, size, excess, name, crate, section or id"
+            );
+        };
+        let Some(Token::String(text)) = value else {
+            bail!("Expected a string after `{op}`");
+        };
+        match op {
+            "~" | "!~" => {
+                let regex = Regex::new(&text)
+                    .with_context(|| format!("Invalid regular expression `{text}`"))?;
+                Ok(Query::Matches(string_field, regex, op == "~"))
+            }
+            "==" | "!=" => Ok(Query::Equals(string_field, text, op == "==")),
+            _ => bail!("`{op}` can't be used with `{field}`"),
+        }
+    }
+}
+
+fn number_field(name: &str) -> Option<NumberField> {
+    match name {
+        "copies" => Some(NumberField::Copies),
+        "size" => Some(NumberField::Size),
+        "excess" => Some(NumberField::Excess),
+        _ => None,
+    }
+}
+
+fn string_field(name: &str) -> Option<StringField> {
+    match name {
+        "name" => Some(StringField::Name),
+        "crate" => Some(StringField::Crate),
+        "section" => Some(StringField::Section),
+        "id" => Some(StringField::Id),
+        _ => None,
+    }
+}
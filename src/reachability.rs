@@ -0,0 +1,181 @@
+//! A rough reachability analysis, for telling how much duplication is in code that a linker's
+//! `--gc-sections` would already remove, and so isn't something that ICF needs to fold.
+//!
+//! Starting from the entry point and exported symbols, we follow direct calls and jumps, as well as
+//! function addresses that instructions load. We don't know which data is reachable, so any
+//! function whose address appears in allocated data, e.g. in a vtable, `.init_array` or a dynamic
+//! relocation, is also a root. This errs towards treating functions as reachable, so if anything,
+//! unreachable duplication is understated.
+
+use crate::decode_asm;
+use crate::functions;
+use crate::get_fn_bytes;
+use crate::is_executable;
+use crate::require_x86;
+use crate::DuplicationReport;
+use crate::Function;
+use crate::Options;
+use crate::Result;
+use anyhow::bail;
+use object::Object as _;
+use object::ObjectKind;
+use object::ObjectSection as _;
+use std::collections::HashSet;
+
+pub struct Reachability {
+    /// The start address of each reachable function.
+    reachable: HashSet<u64>,
+
+    pub unreachable_functions: u64,
+
+    /// The total size of unreachable functions, whether or not they're duplicated.
+    pub unreachable_bytes: u64,
+}
+
+impl Reachability {
+    pub fn is_reachable(&self, address: u64) -> bool {
+        self.reachable.contains(&address)
+    }
+
+    /// Returns the excess bytes of `report` that removing unreachable functions would get rid of.
+    /// Groups with no reachable copies go entirely, otherwise only the reachable copies remain.
+    pub fn unreachable_excess_bytes(&self, report: &DuplicationReport) -> u64 {
+        report
+            .duplicates()
+            .map(|group| {
+                let reachable = group
+                    .instances
+                    .iter()
+                    .filter(|instance| self.is_reachable(instance.address))
+                    .count() as u64;
+                group.copies.saturating_sub(reachable.max(1)) * group.function_size
+            })
+            .sum()
+    }
+}
+
+pub fn find_reachable(data: &[u8], options: &Options) -> Result<Reachability> {
+    let object = object::File::parse(data)?;
+    if object.kind() == ObjectKind::Relocatable {
+        bail!("Reachability analysis requires a linked binary");
+    }
+    require_x86(&object, "Reachability analysis")?;
+
+    // Functions that are filtered out of the report can still call ones that aren't, so we look at
+    // all of them.
+    let mut functions = functions(&object, options)?;
+    functions.sort_by_key(|function| function.address);
+    functions.dedup_by_key(|function| function.address);
+    let bitness = crate::bitness(&object);
+
+    let mut pending = Vec::new();
+    let mut visited = vec![false; functions.len()];
+    let mut visit = |address: u64, pending: &mut Vec<usize>| {
+        if let Some(index) = containing_function(&functions, address) {
+            if !visited[index] {
+                visited[index] = true;
+                pending.push(index);
+            }
+        }
+    };
+
+    visit(object.entry(), &mut pending);
+    for export in object.exports()? {
+        visit(export.address(), &mut pending);
+    }
+    for address in data_pointers(&object) {
+        visit(address, &mut pending);
+    }
+
+    while let Some(index) = pending.pop() {
+        let function = &functions[index];
+        let Some(fn_bytes) = get_fn_bytes(function, &object) else {
+            continue;
+        };
+        for instruction in decode_asm(fn_bytes, function.address, bitness) {
+            if instruction.is_call_near()
+                || instruction.is_jmp_near()
+                || instruction.is_jcc_short_or_near()
+            {
+                visit(instruction.near_branch_target(), &mut pending);
+            }
+            if instruction.is_ip_rel_memory_operand() {
+                visit(instruction.ip_rel_memory_address(), &mut pending);
+            }
+            // Non-PIC 32 bit code loads function addresses as immediates.
+            for operand in 0..instruction.op_count() {
+                if matches!(
+                    instruction.op_kind(operand),
+                    iced_x86::OpKind::Immediate32
+                        | iced_x86::OpKind::Immediate64
+                        | iced_x86::OpKind::Immediate32to64
+                ) {
+                    visit(instruction.immediate(operand), &mut pending);
+                }
+            }
+        }
+    }
+
+    let mut reachability = Reachability {
+        reachable: HashSet::new(),
+        unreachable_functions: 0,
+        unreachable_bytes: 0,
+    };
+    for (function, visited) in functions.iter().zip(visited) {
+        if visited {
+            reachability.reachable.insert(function.address);
+        } else {
+            reachability.unreachable_functions += 1;
+            reachability.unreachable_bytes += function.size;
+        }
+    }
+    Ok(reachability)
+}
+
+/// Returns the index of the function in `functions`, which must be sorted by address, that contains
+/// `address`.
+fn containing_function(functions: &[Function], address: u64) -> Option<usize> {
+    let index = functions
+        .partition_point(|function| function.address <= address)
+        .checked_sub(1)?;
+    let function = &functions[index];
+    (address < function.address + function.size).then_some(index)
+}
+
+/// Returns every aligned pointer-sized value in the allocated, non-executable sections of `object`.
+/// Most of these won't be function addresses, but the ones that are include vtable entries,
+/// `.init_array` entries and the addends of dynamic relocations.
+fn data_pointers(object: &object::File) -> Vec<u64> {
+    let pointer_size = if object.is_64() { 8 } else { 4 };
+    let little_endian = object.is_little_endian();
+    let mut pointers = Vec::new();
+    // Sections that aren't loaded, such as debug info and the static symbol table, have no
+    // address.
+    for section in object.sections() {
+        if section.address() == 0 || is_executable(&section) {
+            continue;
+        }
+        let Ok(section_data) = section.data() else {
+            continue;
+        };
+        let skip = (pointer_size - section.address() % pointer_size) % pointer_size;
+        let Some(section_data) = section_data.get(skip as usize..) else {
+            continue;
+        };
+        pointers.extend(
+            section_data
+                .chunks_exact(pointer_size as usize)
+                .map(|chunk| {
+                    let mut bytes = [0; 8];
+                    if little_endian {
+                        bytes[..chunk.len()].copy_from_slice(chunk);
+                        u64::from_le_bytes(bytes)
+                    } else {
+                        bytes[8 - chunk.len()..].copy_from_slice(chunk);
+                        u64::from_be_bytes(bytes)
+                    }
+                }),
+        );
+    }
+    pointers
+}
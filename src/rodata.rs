@@ -0,0 +1,509 @@
+//! Detection of duplicated read-only data, such as string literals and lookup tables, which are
+//! keyed by their contents in the same way as functions are keyed by their code.
+//!
+//! Constants are found from data symbols that have a size. Most constants don't have a symbol, but
+//! in object files, rustc puts each in its own section, e.g. `.rodata..Lanon.<hash>.0`, so those
+//! sections are taken whole. This means that string literals are best found by analysing the
+//! objects and rlibs that go into a binary, e.g. `target/release/deps`. Sections that the linker
+//! merges anyway, such as mergeable strings and COMDAT groups, are left out of object files.
+//!
+//! Vtables and other constants that contain pointers are in `.data.rel.ro`, since the pointers need
+//! relocating. The addresses themselves differ between copies, so each pointer is keyed by the name
+//! of what it points to, without rustc's hash, so that vtables pointing at the same functions are
+//! grouped together. In linked binaries, these are only found if they have a symbol, e.g. C++
+//! vtables, and pointers are read from dynamic relocations.
+
+use crate::comdat_names;
+use crate::demangle::demangle;
+use crate::display_name;
+use crate::input_files;
+use crate::read_input;
+use crate::Options;
+use crate::Result;
+use anyhow::bail;
+use object::read::archive::ArchiveFile;
+use object::FileKind;
+use object::Object as _;
+use object::ObjectKind;
+use object::ObjectSection as _;
+use object::ObjectSymbol as _;
+use object::ObjectSymbolTable as _;
+use object::Relocation;
+use object::RelocationTarget;
+use object::SectionFlags;
+use object::SectionKind;
+use object::SymbolKind;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The number of bytes that are shown of constants that aren't text.
+const PREVIEW_BYTES: usize = 16;
+
+/// The number of characters that are shown of constants that are text.
+const PREVIEW_CHARS: usize = 60;
+
+/// The number of pointers that are shown of constants that contain pointers.
+const PREVIEW_POINTERS: usize = 3;
+
+pub struct RodataReport {
+    /// The total size of the read-only data sections, including those whose contents weren't
+    /// checked.
+    pub rodata_size: u64,
+
+    /// Constants grouped by their contents, sorted by excess bytes, smallest first.
+    pub groups: Vec<ConstantGroup>,
+}
+
+/// A set of constants with identical contents.
+pub struct ConstantGroup {
+    pub size: u64,
+    pub copies: u64,
+
+    /// The names of any symbols for the copies, with how many copies have each. Constants that
+    /// were found from their section have no name.
+    pub names: BTreeMap<String, u64>,
+
+    /// The start of the contents, either as a quoted string, as what it points to or as hex.
+    pub preview: String,
+
+    /// Whether the constants look like vtables, either because they're named like C++ vtables or
+    /// because they start with a pointer to Rust's drop glue.
+    pub vtable: bool,
+
+    pub instances: Vec<ConstantInstance>,
+}
+
+pub struct ConstantInstance {
+    pub address: u64,
+    pub section: String,
+
+    /// The archive member or file in a directory that the copy is in.
+    pub member: Option<String>,
+}
+
+impl RodataReport {
+    pub fn excess_bytes(&self) -> u64 {
+        self.groups.iter().map(|group| group.excess_bytes()).sum()
+    }
+
+    /// Excess bytes as a fraction of `rodata_size`, or zero if there's no read-only data.
+    pub fn excess_fraction(&self) -> f64 {
+        if self.rodata_size == 0 {
+            return 0.0;
+        }
+        self.excess_bytes() as f64 / self.rodata_size as f64
+    }
+
+    pub fn duplicates(&self) -> impl Iterator<Item = &ConstantGroup> {
+        self.groups.iter().filter(|group| group.copies > 1)
+    }
+
+    /// The excess bytes in groups of vtables.
+    pub fn vtable_excess_bytes(&self) -> u64 {
+        self.groups
+            .iter()
+            .filter(|group| group.vtable)
+            .map(|group| group.excess_bytes())
+            .sum()
+    }
+}
+
+/// What constants are grouped by: their contents, with the bytes of each pointer cleared, and the
+/// offset and target of each pointer.
+#[derive(PartialEq, Eq, Hash)]
+struct ConstantKey {
+    contents: Vec<u8>,
+    pointers: Vec<(u64, String)>,
+}
+
+/// A pointer in a constant, found from a relocation.
+struct Pointer {
+    /// The offset of the pointer in its section.
+    offset: u64,
+
+    /// The number of bytes that the relocation fills in.
+    size: u64,
+
+    /// The name of what the pointer points to, with any addend.
+    target: String,
+
+    /// Whether the addend is stored in the pointer's bytes, in which case they're kept in the key.
+    implicit_addend: bool,
+}
+
+impl ConstantGroup {
+    pub fn excess_bytes(&self) -> u64 {
+        self.copies.saturating_sub(1) * self.size
+    }
+}
+
+/// Groups the constants in the read-only data of the binary, archive or directory of objects at
+/// `path` by their contents.
+pub fn find_duplicate_constants(path: &Path, options: &Options) -> Result<RodataReport> {
+    if path.is_dir() {
+        let files = input_files::read_directory(path)?;
+        return process_input_files(&files, options);
+    }
+    let data = read_input(path)?;
+    if input_files::is_thin_archive(&data) {
+        let name = path.display().to_string();
+        let files = input_files::read_thin_archive(path, &name, &data)?;
+        return process_input_files(&files, options);
+    }
+    process_files(&[(None, &data)], options)
+}
+
+fn process_input_files(
+    files: &[input_files::InputFile],
+    options: &Options,
+) -> Result<RodataReport> {
+    let files = files
+        .iter()
+        .map(|file| (Some(file.name.as_str()), &*file.data))
+        .collect::<Vec<_>>();
+    process_files(&files, options)
+}
+
+fn process_files(files: &[(Option<&str>, &[u8])], options: &Options) -> Result<RodataReport> {
+    let mut rodata_size = 0;
+    let mut groups = HashMap::<ConstantKey, ConstantGroup>::new();
+    for &(file_name, data) in files {
+        if FileKind::parse(data)? == FileKind::Archive {
+            let archive = ArchiveFile::parse(data)?;
+            for member in archive.members() {
+                let member = member?;
+                let member_name = String::from_utf8_lossy(member.name()).into_owned();
+                let member_name = match file_name {
+                    Some(file_name) => format!("{file_name}({member_name})"),
+                    None => member_name,
+                };
+                // Skip members that aren't objects, such as the metadata in an rlib.
+                let Ok(object) = member.data(data).and_then(object::File::parse) else {
+                    continue;
+                };
+                rodata_size += process_object(&object, Some(&member_name), options, &mut groups)?;
+            }
+        } else {
+            let object = object::File::parse(data)?;
+            rodata_size += process_object(&object, file_name, options, &mut groups)?;
+        }
+    }
+    if rodata_size == 0 {
+        bail!("Binary has no read-only data");
+    }
+    let mut groups = groups.into_values().collect::<Vec<_>>();
+    for group in &mut groups {
+        group
+            .instances
+            .sort_by(|a, b| (&a.member, a.address).cmp(&(&b.member, b.address)));
+    }
+    // Ties are broken by where the first copy is, so that output is the same every run.
+    groups.sort_by(|a, b| {
+        a.excess_bytes()
+            .cmp(&b.excess_bytes())
+            .then(a.copies.cmp(&b.copies))
+            .then_with(|| {
+                let a = &a.instances[0];
+                let b = &b.instances[0];
+                (&a.member, a.address).cmp(&(&b.member, b.address))
+            })
+    });
+    Ok(RodataReport {
+        rodata_size,
+        groups,
+    })
+}
+
+/// Adds the constants in `object` to `groups` and returns the size of its read-only data.
+fn process_object(
+    object: &object::File,
+    member: Option<&str>,
+    options: &Options,
+    groups: &mut HashMap<ConstantKey, ConstantGroup>,
+) -> Result<u64> {
+    let relocatable = object.kind() == ObjectKind::Relocatable;
+    let comdat_sections = comdat_names(object);
+    let (dynamic_pointers, symbol_names) = if relocatable {
+        (Vec::new(), HashMap::new())
+    } else {
+        (dynamic_pointers(object), symbol_names(object))
+    };
+    // Stripped shared libraries still have their exported data, such as C++ vtables, in `.dynsym`.
+    let all_symbols = if object.symbol_table().is_some() {
+        object.symbols()
+    } else {
+        object.dynamic_symbols()
+    };
+    let mut symbols = HashMap::<_, Vec<_>>::new();
+    for symbol in all_symbols {
+        if symbol.kind() != SymbolKind::Data || symbol.size() == 0 || symbol.is_undefined() {
+            continue;
+        }
+        if let Some(index) = symbol.section_index() {
+            symbols.entry(index).or_default().push(symbol);
+        }
+    }
+
+    let mut rodata_size = 0;
+    for section in object.sections() {
+        if !is_read_only(&section) {
+            continue;
+        }
+        rodata_size += section.size();
+        if relocatable && (is_merged(&section) || comdat_sections.contains_key(&section.index())) {
+            continue;
+        }
+        let Ok(data) = section.data() else {
+            continue;
+        };
+        let pointers = if relocatable {
+            section
+                .relocations()
+                .map(|(offset, relocation)| {
+                    pointer(object, &symbol_names, offset, &relocation, false)
+                })
+                .collect::<Vec<_>>()
+        } else {
+            let range = section.address()..section.address() + section.size();
+            dynamic_pointers
+                .iter()
+                .filter(|(address, _)| range.contains(address))
+                .map(|(address, relocation)| {
+                    let offset = address - section.address();
+                    pointer(object, &symbol_names, offset, relocation, true)
+                })
+                .collect()
+        };
+        let section_name = section.name().unwrap_or("<unknown>");
+
+        // Constants are keyed by their offset and size, so that aliases are only counted once.
+        let mut constants = BTreeMap::<(u64, u64), Vec<String>>::new();
+        match symbols.get(&section.index()) {
+            Some(symbols) => {
+                for symbol in symbols {
+                    let Some(offset) = symbol.address().checked_sub(section.address()) else {
+                        continue;
+                    };
+                    let names = constants.entry((offset, symbol.size())).or_default();
+                    if let Ok(name) = symbol.name() {
+                        names.push(display_name(name, options));
+                    }
+                }
+            }
+            None if relocatable => {
+                constants.insert((0, data.len() as u64), Vec::new());
+            }
+            None => {}
+        }
+
+        for ((offset, size), names) in constants {
+            let end = offset + size;
+            let Some(contents) = data.get(offset as usize..end as usize) else {
+                continue;
+            };
+            let pointers = pointers
+                .iter()
+                .filter(|pointer| (offset..end).contains(&pointer.offset))
+                .collect::<Vec<_>>();
+            let vtable = names
+                .iter()
+                .any(|name| name.starts_with("_ZTV") || name.starts_with("??_7"))
+                || pointers.iter().any(|pointer| {
+                    pointer.offset == offset
+                        && pointer.target.starts_with("core::ptr::drop_in_place")
+                });
+            let preview = if pointers.is_empty() {
+                preview(contents)
+            } else {
+                pointers_preview(&pointers)
+            };
+            let mut key = ConstantKey {
+                contents: contents.to_vec(),
+                pointers: Vec::with_capacity(pointers.len()),
+            };
+            for pointer in pointers {
+                let start = pointer.offset - offset;
+                if !pointer.implicit_addend {
+                    let end = (start + pointer.size).min(size);
+                    key.contents[start as usize..end as usize].fill(0);
+                }
+                key.pointers.push((start, pointer.target.clone()));
+            }
+            let group = groups.entry(key).or_insert_with(|| ConstantGroup {
+                size,
+                copies: 0,
+                names: BTreeMap::new(),
+                preview,
+                vtable,
+                instances: Vec::new(),
+            });
+            group.copies += 1;
+            for name in names {
+                *group.names.entry(name).or_default() += 1;
+            }
+            group.instances.push(ConstantInstance {
+                address: section.address() + offset,
+                section: section_name.to_owned(),
+                member: member.map(str::to_owned),
+            });
+        }
+    }
+    Ok(rodata_size)
+}
+
+/// Whether `section` holds read-only data, including data such as vtables that's only read-only
+/// once it's been relocated.
+fn is_read_only<'data>(section: &impl object::ObjectSection<'data>) -> bool {
+    match section.kind() {
+        SectionKind::ReadOnlyData
+        | SectionKind::ReadOnlyString
+        | SectionKind::ReadOnlyDataWithRel => true,
+        // ELF has no section kind for this, since it's writable while it's being relocated.
+        SectionKind::Data => section
+            .name()
+            .is_ok_and(|name| name.starts_with(".data.rel.ro")),
+        _ => false,
+    }
+}
+
+/// Returns the address and relocation of each pointer that the dynamic linker fills in.
+fn dynamic_pointers(object: &object::File) -> Vec<(u64, Relocation)> {
+    let mut pointers = object
+        .dynamic_relocations()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    pointers.sort_by_key(|(address, _)| *address);
+    pointers
+}
+
+/// Describes the pointer that `relocation` fills in at `offset`. Relocations from the dynamic
+/// relocation table refer to dynamic symbols, or for relative relocations, to an address, which is
+/// looked up in `symbol_names`.
+fn pointer(
+    object: &object::File,
+    symbol_names: &HashMap<u64, String>,
+    offset: u64,
+    relocation: &Relocation,
+    dynamic: bool,
+) -> Pointer {
+    let size = match relocation.size() {
+        0 if object.is_64() => 8,
+        0 => 4,
+        bits => u64::from(bits) / 8,
+    };
+    let mut addend = relocation.addend();
+    let name = match relocation.target() {
+        RelocationTarget::Symbol(index) => {
+            let symbol = if dynamic {
+                object
+                    .dynamic_symbol_table()
+                    .and_then(|table| table.symbol_by_index(index).ok())
+            } else {
+                object.symbol_by_index(index).ok()
+            };
+            match symbol {
+                // Relocations against a section's symbol point into that section.
+                Some(symbol) if symbol.kind() == SymbolKind::Section => symbol
+                    .section_index()
+                    .and_then(|index| object.section_by_index(index).ok())
+                    .and_then(|section| section.name().ok().map(str::to_owned)),
+                Some(symbol) => symbol
+                    .name()
+                    .ok()
+                    .map(|name| demangle(name, true).into_owned()),
+                None => None,
+            }
+        }
+        RelocationTarget::Section(index) => object
+            .section_by_index(index)
+            .ok()
+            .and_then(|section| section.name().ok().map(str::to_owned)),
+        RelocationTarget::Absolute => {
+            let name = symbol_names.get(&(addend as u64)).cloned();
+            if name.is_some() {
+                addend = 0;
+            }
+            name
+        }
+        _ => None,
+    };
+    let target = match (name, addend) {
+        (Some(name), 0) => name,
+        (Some(name), addend) if addend < 0 => format!("{name}-{:#x}", addend.unsigned_abs()),
+        (Some(name), addend) => format!("{name}+{addend:#x}"),
+        (None, addend) => format!("{addend:#x}"),
+    };
+    Pointer {
+        offset,
+        size,
+        target,
+        implicit_addend: relocation.has_implicit_addend(),
+    }
+}
+
+/// Returns the name of a function or data symbol at each address, without rustc's hash,
+/// preferring the symbol table to the dynamic symbol table.
+fn symbol_names(object: &object::File) -> HashMap<u64, String> {
+    let mut names = HashMap::new();
+    for symbol in object.dynamic_symbols().chain(object.symbols()) {
+        if !matches!(symbol.kind(), SymbolKind::Text | SymbolKind::Data) || symbol.is_undefined() {
+            continue;
+        }
+        if let Ok(name) = symbol.name() {
+            names.insert(symbol.address(), demangle(name, true).into_owned());
+        }
+    }
+    names
+}
+
+/// Whether the linker merges identical entries in `section`, as it does for `SHF_MERGE` sections,
+/// such as those holding C strings.
+fn is_merged<'data>(section: &impl object::ObjectSection<'data>) -> bool {
+    match section.flags() {
+        SectionFlags::Elf { sh_flags } => sh_flags & u64::from(object::elf::SHF_MERGE) != 0,
+        _ => section.kind() == SectionKind::ReadOnlyString,
+    }
+}
+
+/// Returns the targets of the first few of `pointers`.
+fn pointers_preview(pointers: &[&Pointer]) -> String {
+    let mut preview = pointers
+        .iter()
+        .take(PREVIEW_POINTERS)
+        .map(|pointer| format!("&{}", pointer.target))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if pointers.len() > PREVIEW_POINTERS {
+        preview.push_str(", ...");
+    }
+    preview
+}
+
+/// Returns the start of `contents` as a quoted string if it's text, ignoring a trailing NUL, and
+/// otherwise as hex.
+fn preview(contents: &[u8]) -> String {
+    let text = contents.strip_suffix(b"\0").unwrap_or(contents);
+    if let Ok(text) = std::str::from_utf8(text) {
+        if !text.is_empty() && !text.contains(|c: char| c.is_control() && c != '\n' && c != '\t') {
+            let mut preview = text.chars().take(PREVIEW_CHARS).collect::<String>();
+            let truncated = preview.len() < text.len();
+            preview = format!("{preview:?}");
+            if truncated {
+                preview.push_str("...");
+            }
+            return preview;
+        }
+    }
+    let mut preview = contents
+        .iter()
+        .take(PREVIEW_BYTES)
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if contents.len() > PREVIEW_BYTES {
+        preview.push_str(" ...");
+    }
+    preview
+}
@@ -0,0 +1,196 @@
+//! Finding the shared libraries that an ELF binary depends on, the way the dynamic loader would, so
+//! that functions duplicated between a binary and its libraries can be found.
+
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context;
+use object::elf;
+use object::read::elf::Dyn as _;
+use object::read::elf::FileHeader;
+use object::Endianness;
+use object::FileKind;
+use object::Object as _;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The `DT_NEEDED`, `DT_RPATH` and `DT_RUNPATH` entries of a binary.
+#[derive(Default)]
+struct Dynamic {
+    needed: Vec<String>,
+    rpath: Vec<String>,
+    runpath: Vec<String>,
+}
+
+/// Returns the shared libraries that the ELF binary at `path` needs, directly or indirectly, in the
+/// breadth-first order that `ld.so` loads them. Libraries are searched for as `ld.so` does, except
+/// that if `sysroot` is set, system directories are looked for under it and `LD_LIBRARY_PATH` is
+/// ignored.
+pub fn dependencies(path: &Path, sysroot: Option<&Path>) -> Result<Vec<PathBuf>> {
+    let system_dirs = system_dirs(sysroot);
+    let mut found = Vec::new();
+    let mut seen = HashSet::from([canonical(path)]);
+    let mut pending = VecDeque::from([path.to_owned()]);
+    while let Some(dependent) = pending.pop_front() {
+        let data = crate::read_input(&dependent)
+            .with_context(|| format!("Failed to read `{}`", dependent.display()))?;
+        let dynamic = read_dynamic(&data).with_context(|| {
+            format!(
+                "Failed to read dynamic section of `{}`",
+                dependent.display()
+            )
+        })?;
+        let origin = dependent.parent().unwrap_or(Path::new("."));
+        // `DT_RPATH` is ignored if there's a `DT_RUNPATH`.
+        let rpath = if dynamic.runpath.is_empty() {
+            expand_origin(&dynamic.rpath, origin)
+        } else {
+            Vec::new()
+        };
+        let library_path = match sysroot {
+            Some(_) => Vec::new(),
+            None => std::env::var_os("LD_LIBRARY_PATH")
+                .map(|dirs| std::env::split_paths(&dirs).collect())
+                .unwrap_or_default(),
+        };
+        let search_dirs = rpath
+            .into_iter()
+            .chain(library_path)
+            .chain(expand_origin(&dynamic.runpath, origin))
+            .chain(system_dirs.iter().cloned())
+            .collect::<Vec<_>>();
+        for name in &dynamic.needed {
+            let library = if name.contains('/') {
+                Some(PathBuf::from(name))
+            } else {
+                search_dirs
+                    .iter()
+                    .map(|dir| dir.join(name))
+                    .find(|candidate| is_compatible(candidate, &data))
+            };
+            let Some(library) = library else {
+                bail!(
+                    "Couldn't find `{name}`, which `{}` needs. Set `LD_LIBRARY_PATH` or \
+                     `--sysroot` to where it's installed",
+                    dependent.display()
+                );
+            };
+            if seen.insert(canonical(&library)) {
+                found.push(library.clone());
+                pending.push_back(library);
+            }
+        }
+    }
+    Ok(found)
+}
+
+fn read_dynamic(data: &[u8]) -> Result<Dynamic> {
+    match FileKind::parse(data)? {
+        FileKind::Elf32 => read_dynamic_for::<elf::FileHeader32<Endianness>>(data),
+        FileKind::Elf64 => read_dynamic_for::<elf::FileHeader64<Endianness>>(data),
+        _ => bail!("Only ELF binaries have shared library dependencies"),
+    }
+}
+
+fn read_dynamic_for<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> Result<Dynamic> {
+    let header = Elf::parse(data)?;
+    let endian = header.endian()?;
+    let sections = header.sections(endian, data)?;
+    let mut dynamic = Dynamic::default();
+    // Statically linked binaries have no dynamic section.
+    let Some((entries, link)) = sections.dynamic(endian, data)? else {
+        return Ok(dynamic);
+    };
+    let strings = sections.strings(endian, data, link)?;
+    for entry in entries {
+        let tag = entry.tag32(endian);
+        if !matches!(tag, Some(elf::DT_NEEDED | elf::DT_RPATH | elf::DT_RUNPATH)) {
+            continue;
+        }
+        let value = String::from_utf8_lossy(entry.string(endian, strings)?).into_owned();
+        // Search paths are colon separated.
+        match tag {
+            Some(elf::DT_NEEDED) => dynamic.needed.push(value),
+            Some(elf::DT_RPATH) => dynamic.rpath.extend(value.split(':').map(str::to_owned)),
+            _ => dynamic.runpath.extend(value.split(':').map(str::to_owned)),
+        }
+    }
+    Ok(dynamic)
+}
+
+/// Replaces `$ORIGIN`, which means the directory of the binary being loaded, in each of `dirs`.
+fn expand_origin(dirs: &[String], origin: &Path) -> Vec<PathBuf> {
+    let origin = origin.to_string_lossy();
+    dirs.iter()
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| {
+            PathBuf::from(
+                dir.replace("${ORIGIN}", &origin)
+                    .replace("$ORIGIN", &origin),
+            )
+        })
+        .collect()
+}
+
+/// Returns the directories listed in `/etc/ld.so.conf`, followed by the default ones.
+fn system_dirs(sysroot: Option<&Path>) -> Vec<PathBuf> {
+    let root = sysroot.unwrap_or(Path::new("/"));
+    let mut dirs = Vec::new();
+    read_ld_so_conf(&root.join("etc/ld.so.conf"), root, &mut dirs);
+    for dir in ["lib64", "usr/lib64", "lib", "usr/lib"] {
+        dirs.push(root.join(dir));
+    }
+    dirs
+}
+
+/// Appends the directories listed in the `ld.so.conf` style file at `path`, following `include`
+/// directives. Files that can't be read are ignored, as `ldconfig` does.
+fn read_ld_so_conf(path: &Path, root: &Path, dirs: &mut Vec<PathBuf>) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(pattern) = line.strip_prefix("include") {
+            let pattern = pattern.trim();
+            // Relative patterns are relative to the including file.
+            let pattern = match pattern.strip_prefix('/') {
+                Some(absolute) => root.join(absolute),
+                None => path.parent().unwrap_or(root).join(pattern),
+            };
+            let Ok(paths) = glob::glob(&pattern.to_string_lossy()) else {
+                continue;
+            };
+            let mut paths = paths.filter_map(|path| path.ok()).collect::<Vec<_>>();
+            paths.sort();
+            for path in paths {
+                read_ld_so_conf(&path, root, dirs);
+            }
+        } else if let Some(dir) = line.strip_prefix('/') {
+            dirs.push(root.join(dir));
+        }
+    }
+}
+
+/// Whether `candidate` is a shared library that could be loaded alongside the binary `data`. Like
+/// `ld.so`, we skip libraries for other architectures, e.g. 32 bit libraries in a directory that
+/// has both.
+fn is_compatible(candidate: &Path, data: &[u8]) -> bool {
+    let Ok(candidate_data) = crate::read_input(candidate) else {
+        return false;
+    };
+    let (Ok(candidate), Ok(binary)) = (
+        object::File::parse(&*candidate_data),
+        object::File::parse(data),
+    ) else {
+        return false;
+    };
+    candidate.architecture() == binary.architecture() && candidate.is_64() == binary.is_64()
+}
+
+/// Resolves symlinks, such as `libfoo.so.1` to `libfoo.so.1.2.3`, so that each library is only
+/// analysed once.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
@@ -0,0 +1,78 @@
+//! Attribution of functions to source locations using DWARF debug info.
+
+use crate::DuplicateGroup;
+use crate::Result;
+use object::Object as _;
+use object::ObjectKind;
+use object::ObjectSection as _;
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::rc::Rc;
+
+pub struct SourceLocation {
+    pub file: String,
+    pub line: Option<u32>,
+}
+
+pub struct SourceLocations {
+    context: addr2line::Context<gimli::EndianRcSlice<gimli::RunTimeEndian>>,
+}
+
+impl SourceLocations {
+    /// Loads DWARF debug info from a linked binary. Returns `None` if `data` isn't a linked binary
+    /// or it has no debug info.
+    pub fn from_data(data: &[u8]) -> Result<Option<SourceLocations>> {
+        let Ok(object) = object::File::parse(data) else {
+            return Ok(None);
+        };
+        // Addresses in relocatable objects are relative to their section, so aren't enough to
+        // identify a function.
+        if object.kind() == ObjectKind::Relocatable
+            || object.section_by_name(".debug_info").is_none()
+        {
+            return Ok(None);
+        }
+        let endian = if object.is_little_endian() {
+            gimli::RunTimeEndian::Little
+        } else {
+            gimli::RunTimeEndian::Big
+        };
+        let dwarf = gimli::Dwarf::load(|id| -> Result<_> {
+            let data = match object.section_by_name(id.name()) {
+                Some(section) => section.uncompressed_data()?,
+                None => Cow::Borrowed(&[][..]),
+            };
+            Ok(gimli::EndianRcSlice::new(Rc::from(&*data), endian))
+        })?;
+        Ok(Some(SourceLocations {
+            context: addr2line::Context::from_dwarf(dwarf)?,
+        }))
+    }
+
+    /// Returns the source location of the first instruction at `address`.
+    pub fn find(&self, address: u64) -> Option<SourceLocation> {
+        let location = self.context.find_location(address).ok()??;
+        Some(SourceLocation {
+            file: location.file?.to_owned(),
+            line: location.line,
+        })
+    }
+
+    /// Returns the source location of the first copy in `group` for which we have one. Since the
+    /// copies are identical, they generally all come from the same source.
+    pub fn find_for_group(&self, group: &DuplicateGroup) -> Option<SourceLocation> {
+        group
+            .instances
+            .iter()
+            .find_map(|instance| self.find(instance.address))
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}", self.file),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
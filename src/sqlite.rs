@@ -0,0 +1,136 @@
+//! Output to a SQLite database, so that results from many runs, e.g. of nightly builds, can be
+//! queried with SQL. Each run appends to the database rather than replacing it.
+
+use crate::sorted_duplicates;
+use crate::split_top;
+use crate::Args;
+use anyhow::Context;
+use clap::ValueEnum as _;
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Result;
+use rusqlite::params;
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    binary TEXT NOT NULL,
+    -- Seconds since the Unix epoch.
+    analysed_at INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    text_size INTEGER NOT NULL,
+    excess_bytes INTEGER NOT NULL,
+    excess_percent REAL NOT NULL,
+    dup_groups INTEGER NOT NULL,
+    excess_instances INTEGER NOT NULL,
+    filtered_functions INTEGER NOT NULL,
+    filtered_bytes INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS duplicate_groups (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    function_size INTEGER NOT NULL,
+    copies INTEGER NOT NULL,
+    excess_bytes INTEGER NOT NULL,
+    -- `DuplicateGroup::id`, which identifies the same group across runs.
+    hash TEXT
+);
+CREATE TABLE IF NOT EXISTS names (
+    group_id INTEGER NOT NULL REFERENCES duplicate_groups(id),
+    name TEXT NOT NULL,
+    count INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS instances (
+    group_id INTEGER NOT NULL REFERENCES duplicate_groups(id),
+    -- SQLite integers are signed, so addresses above 2^63 are stored as negative numbers.
+    address INTEGER NOT NULL,
+    section TEXT,
+    member TEXT
+);
+CREATE INDEX IF NOT EXISTS duplicate_groups_run_id ON duplicate_groups(run_id);
+CREATE INDEX IF NOT EXISTS names_name ON names(name);
+";
+
+/// Appends a run for each of `reports` to the database given by `--output`, creating it if
+/// necessary. Duplicate groups respect `--top` and the minimums, as for other formats.
+pub(crate) fn write_reports(reports: &[(&Path, &DuplicationReport)], args: &Args) -> Result {
+    let path = args
+        .output
+        .as_deref()
+        .context("SQLite output needs `--output` to specify the database")?;
+    let mut connection = rusqlite::Connection::open(path)
+        .with_context(|| format!("Failed to open `{}`", path.display()))?;
+    connection.execute_batch(SCHEMA)?;
+    // Databases created before groups had an ID lack its column. Adding it to a database that
+    // already has it fails, which is fine.
+    let _ = connection.execute_batch("ALTER TABLE duplicate_groups ADD COLUMN hash TEXT");
+    let analysed_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let key = args
+        .key
+        .to_possible_value()
+        .map(|value| value.get_name().to_owned())
+        .unwrap_or_default();
+
+    // A single transaction makes inserting many rows much faster and means that an error doesn't
+    // leave a partial run behind.
+    let transaction = connection.transaction()?;
+    for (bin, report) in reports {
+        transaction.execute(
+            "INSERT INTO runs (binary, analysed_at, key, text_size, excess_bytes, excess_percent, \
+             dup_groups, excess_instances, filtered_functions, filtered_bytes) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                bin.display().to_string(),
+                analysed_at,
+                key,
+                report.text_size,
+                report.excess_bytes(),
+                report.excess_fraction() * 100.0,
+                report.duplicated_functions(),
+                report.excess_instances(),
+                report.filtered_functions,
+                report.filtered_bytes,
+            ],
+        )?;
+        let run_id = transaction.last_insert_rowid();
+
+        let mut symbols = sorted_duplicates(report, args);
+        split_top(&mut symbols, args.top);
+        for group in symbols {
+            transaction.execute(
+                "INSERT INTO duplicate_groups (run_id, function_size, copies, excess_bytes, hash) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    run_id,
+                    group.function_size,
+                    group.copies,
+                    group.excess_bytes(),
+                    group.id()
+                ],
+            )?;
+            let group_id = transaction.last_insert_rowid();
+            for (name, count) in &group.names {
+                transaction.execute(
+                    "INSERT INTO names (group_id, name, count) VALUES (?1, ?2, ?3)",
+                    params![group_id, name, count],
+                )?;
+            }
+            for instance in &group.instances {
+                transaction.execute(
+                    "INSERT INTO instances (group_id, address, section, member) \
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        group_id,
+                        instance.address as i64,
+                        instance.section,
+                        instance.member
+                    ],
+                )?;
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
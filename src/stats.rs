@@ -0,0 +1,119 @@
+//! Timings and counts of skipped functions, for understanding why some binaries take much longer to
+//! analyse than others and how many of their functions weren't checked.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A phase of the analysis that's timed separately.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+    /// Reading or mapping input files.
+    Read,
+
+    /// Parsing objects and their symbols.
+    Parse,
+
+    /// Building the key of each function, e.g. by decoding and normalising its instructions.
+    Keying,
+
+    /// Grouping functions by key.
+    Aggregation,
+
+    /// Writing the report.
+    Reporting,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Read,
+        Phase::Parse,
+        Phase::Keying,
+        Phase::Aggregation,
+        Phase::Reporting,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Phase::Read => "Read",
+            Phase::Parse => "Parse",
+            Phase::Keying => "Key building",
+            Phase::Aggregation => "Aggregation",
+            Phase::Reporting => "Reporting",
+        }
+    }
+}
+
+/// Why a function wasn't checked for duplication.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Skip {
+    /// Its symbol has a size of zero and its size wasn't inferred.
+    ZeroSize,
+
+    /// It isn't within a section that has data, so we couldn't read its code.
+    OutsideSection,
+
+    /// Its key couldn't be built, e.g. because its instructions didn't decode.
+    KeyFailure,
+}
+
+impl Skip {
+    pub const ALL: [Skip; 3] = [Skip::ZeroSize, Skip::OutsideSection, Skip::KeyFailure];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Skip::ZeroSize => "with zero size",
+            Skip::OutsideSection => "outside any section's data",
+            Skip::KeyFailure => "that couldn't be keyed",
+        }
+    }
+}
+
+/// Collects statistics while analysing, if `Options::stats` is set. Analyses of several binaries
+/// can share one, in which case their statistics are combined.
+#[derive(Default)]
+pub struct Stats {
+    durations: Mutex<[Duration; Phase::ALL.len()]>,
+    skipped: [AtomicU64; Skip::ALL.len()],
+}
+
+impl Stats {
+    /// Runs `f`, adding how long it took to `phase`.
+    pub fn time<T>(&self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.durations.lock().unwrap()[phase as usize] += start.elapsed();
+        result
+    }
+
+    /// The total time spent in `phase`.
+    pub fn duration(&self, phase: Phase) -> Duration {
+        self.durations.lock().unwrap()[phase as usize]
+    }
+
+    pub(crate) fn add_skipped(&self, skip: Skip, functions: u64) {
+        self.skipped[skip as usize].fetch_add(functions, Ordering::Relaxed);
+    }
+
+    /// The number of functions that were skipped for `skip`.
+    pub fn skipped(&self, skip: Skip) -> u64 {
+        self.skipped[skip as usize].load(Ordering::Relaxed)
+    }
+}
+
+/// Returns the peak resident set size of this process in bytes, if we know how to find it on this
+/// platform.
+pub fn peak_rss() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
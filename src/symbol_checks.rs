@@ -0,0 +1,139 @@
+//! Sanity checks of function symbols. Excess bytes are computed from symbol sizes, so symbols that
+//! overlap, that run past the end of their section, or that disagree about the size of the code at
+//! an address distort the results without anything looking wrong.
+
+use crate::display_name;
+use crate::functions;
+use crate::Function;
+use crate::Options;
+use crate::Result;
+use object::Object as _;
+use object::ObjectSection as _;
+
+/// A function symbol whose size doesn't look right.
+pub struct SymbolProblem {
+    pub name: Option<String>,
+    pub address: u64,
+    pub size: u64,
+    pub kind: ProblemKind,
+
+    /// The number of bytes that the problem affects, e.g. how many overlap. Problems are sorted by
+    /// this, smallest first.
+    pub affected_bytes: u64,
+}
+
+pub enum ProblemKind {
+    /// The function overlaps an earlier one, which starts at a different address.
+    Overlap {
+        other: Option<String>,
+        other_address: u64,
+    },
+
+    /// The function extends past the end of its section, which ends at `section_end`.
+    BeyondSection { section_end: u64 },
+
+    /// Another symbol at the same address has a different size.
+    SizeMismatch {
+        other: Option<String>,
+        other_size: u64,
+    },
+}
+
+impl ProblemKind {
+    /// The name that's used in JSON output.
+    pub fn id(&self) -> &'static str {
+        match self {
+            ProblemKind::Overlap { .. } => "overlap",
+            ProblemKind::BeyondSection { .. } => "beyond-section",
+            ProblemKind::SizeMismatch { .. } => "size-mismatch",
+        }
+    }
+}
+
+/// Checks the sizes of the function symbols in the binary `data`, including those that are
+/// excluded by filters, since they can still overlap functions that aren't.
+pub fn check_symbols(data: &[u8], options: &Options) -> Result<Vec<SymbolProblem>> {
+    let object = object::File::parse(data)?;
+    let mut functions = functions(&object, options)?;
+    functions.sort_by(|a, b| {
+        (a.section_index.map(|i| i.0), a.address, a.size, &a.name).cmp(&(
+            b.section_index.map(|i| i.0),
+            b.address,
+            b.size,
+            &b.name,
+        ))
+    });
+    let name = |function: &Function| {
+        function
+            .name
+            .as_ref()
+            .map(|name| display_name(name, options))
+    };
+
+    let mut problems = Vec::new();
+    for function in &functions {
+        let Some(section) = function
+            .section_index
+            .and_then(|index| object.section_by_index(index).ok())
+        else {
+            continue;
+        };
+        let section_end = section.address() + section.size();
+        let end = function.address + function.size;
+        if end > section_end {
+            problems.push(SymbolProblem {
+                name: name(function),
+                address: function.address,
+                size: function.size,
+                kind: ProblemKind::BeyondSection { section_end },
+                affected_bytes: end - function.address.max(section_end),
+            });
+        }
+    }
+
+    // Within each section, functions are sorted by address, so each only needs to be compared with
+    // the one before it at the same address and with whichever earlier one reaches furthest.
+    for section in functions.chunk_by(|a, b| a.section_index == b.section_index) {
+        let mut furthest: Option<&Function> = None;
+        for (i, function) in section.iter().enumerate() {
+            let previous = i.checked_sub(1).map(|i| &section[i]);
+            if let Some(previous) = previous.filter(|p| p.address == function.address) {
+                if previous.size != function.size {
+                    problems.push(SymbolProblem {
+                        name: name(function),
+                        address: function.address,
+                        size: function.size,
+                        kind: ProblemKind::SizeMismatch {
+                            other: name(previous),
+                            other_size: previous.size,
+                        },
+                        affected_bytes: function.size.abs_diff(previous.size),
+                    });
+                }
+            } else if let Some(other) = furthest {
+                let other_end = other.address + other.size;
+                if other.address != function.address && function.address < other_end {
+                    problems.push(SymbolProblem {
+                        name: name(function),
+                        address: function.address,
+                        size: function.size,
+                        kind: ProblemKind::Overlap {
+                            other: name(other),
+                            other_address: other.address,
+                        },
+                        affected_bytes: other_end.min(function.address + function.size)
+                            - function.address,
+                    });
+                }
+            }
+            let reaches_further = furthest
+                .is_none_or(|other| function.address + function.size > other.address + other.size);
+            if reaches_further {
+                furthest = Some(function);
+            }
+        }
+    }
+
+    problems.sort_by_key(|problem| (problem.affected_bytes, problem.address));
+    Ok(problems)
+}
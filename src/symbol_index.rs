@@ -0,0 +1,58 @@
+//! A listing of every function that was checked and the group it's in, for other tools to join
+//! against their own data, e.g. per-symbol sizes from a dashboard. Each line is a JSON object
+//! with a function's `name`, `demangled` name, `address`, `size`, and the `group` and `group_id`
+//! of the group that it's in.
+//!
+//! Addresses are hex strings, since JSON numbers can't hold every 64 bit value. Functions from
+//! archives or directories of objects also have a `member`, since addresses aren't unique. Groups
+//! are numbered in order of their lowest address, so `group` is only meaningful within one file.
+//! `group_id` is the group's `DuplicateGroup::id`, which doesn't depend on where functions are, so
+//! it carries over to other builds for as long as `id` does.
+
+use crate::demangle::demangle;
+use crate::DuplicationReport;
+use crate::Result;
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// The line for one function.
+#[derive(Serialize)]
+struct Line<'a> {
+    name: Option<&'a str>,
+    demangled: Option<Cow<'a, str>>,
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    member: Option<&'a str>,
+    size: u64,
+    group: usize,
+    group_id: String,
+}
+
+/// Writes a line for each function in `report`, ordered by group, then by address.
+pub fn write(out: &mut impl std::io::Write, report: &DuplicationReport) -> Result {
+    let mut groups = report.groups.iter().collect::<Vec<_>>();
+    groups.sort_by_key(|group| {
+        (
+            group.instances.iter().map(|i| i.address).min(),
+            group.key_hash,
+        )
+    });
+    for (index, group) in groups.into_iter().enumerate() {
+        let mut instances = group.instances.iter().collect::<Vec<_>>();
+        instances.sort_by(|a, b| (a.address, &a.member).cmp(&(b.address, &b.member)));
+        for instance in instances {
+            let line = Line {
+                name: instance.name.as_deref(),
+                demangled: instance.name.as_deref().map(|name| demangle(name, false)),
+                address: format!("{:x}", instance.address),
+                member: instance.member.as_deref(),
+                size: group.function_size,
+                group: index,
+                group_id: group.id(),
+            };
+            serde_json::to_writer(&mut *out, &line)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Reading of functions from a textual symbol dump, for binaries whose own symbol table is missing
+//! or unreliable, e.g. firmware that was stripped by hand but shipped with a separate symbol
+//! listing.
+//!
+//! Both `nm -S` and `objdump -t` output are accepted:
+//!
+//! ```text
+//! 0000000000401126 0000000000000015 T main
+//! 0000000000401126 g     F .text  0000000000000015              main
+//! ```
+
+use crate::linkage::Binding;
+use crate::linkage::Linkage;
+use crate::linkage::Visibility;
+use crate::text_section_ranges;
+use crate::Function;
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context;
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Returns the functions with non-zero sizes listed in the symbol dump at `path`, which must
+/// describe `object`.
+pub(crate) fn functions<'data>(
+    path: &Path,
+    object: &object::File<'data, &'data [u8]>,
+) -> Result<Vec<Function<'data>>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read symbols file `{}`", path.display()))?;
+    let text_sections = text_section_ranges(object);
+    let functions = text
+        .lines()
+        .filter_map(|line| parse_nm_line(line).or_else(|| parse_objdump_line(line)))
+        .filter(|symbol| symbol.size != 0)
+        .map(|symbol| Function {
+            name: Some(Cow::Owned(symbol.name.to_owned())),
+            address: symbol.address,
+            size: symbol.size,
+            section_index: text_sections
+                .iter()
+                .find(|(_, range)| range.contains(&symbol.address))
+                .map(|(index, _)| *index),
+            size_inferred: false,
+            thumb: false,
+            linkage: symbol.linkage,
+            file: None,
+        })
+        .collect::<Vec<_>>();
+    if functions.is_empty() {
+        bail!(
+            "No functions with sizes found in `{}`. Expected `nm -S` or `objdump -t` output",
+            path.display()
+        );
+    }
+    Ok(functions)
+}
+
+struct Symbol<'a> {
+    address: u64,
+    size: u64,
+    name: &'a str,
+    linkage: Linkage,
+}
+
+/// Parses `address size type name`. Lines without a size, which `nm` emits for symbols whose size
+/// it doesn't know, are skipped.
+fn parse_nm_line(line: &str) -> Option<Symbol<'_>> {
+    let mut parts = line.splitn(4, ' ');
+    let address = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let size = u64::from_str_radix(parts.next()?, 16).ok()?;
+    // `nm` doesn't show visibility.
+    let binding = match parts.next()? {
+        "T" => Binding::Global,
+        "t" => Binding::Local,
+        "W" | "w" => Binding::Weak,
+        _ => return None,
+    };
+    let name = parts.next()?.trim();
+    (!name.is_empty()).then_some(Symbol {
+        address,
+        size,
+        name,
+        linkage: Linkage::from_binding(binding),
+    })
+}
+
+/// Parses `address flags section<tab>size name`, where flags is a fixed width column in which `F`
+/// marks functions.
+fn parse_objdump_line(line: &str) -> Option<Symbol<'_>> {
+    let (address, rest) = line.split_once(' ')?;
+    let address = u64::from_str_radix(address, 16).ok()?;
+    let flags = rest.get(..7)?;
+    if !flags.contains('F') {
+        return None;
+    }
+    let (_section, rest) = rest.get(7..)?.split_once('\t')?;
+    let (size, name) = rest.split_once(' ')?;
+    let size = u64::from_str_radix(size, 16).ok()?;
+    // Symbols with non-default visibility are listed like `.hidden foo`.
+    let name = name.trim();
+    let (visibility, name) = [
+        (".hidden ", Visibility::Hidden),
+        (".internal ", Visibility::Hidden),
+        (".protected ", Visibility::Exported),
+    ]
+    .iter()
+    .find_map(|(prefix, visibility)| Some((*visibility, name.strip_prefix(prefix)?)))
+    .unwrap_or((Visibility::Exported, name));
+    // The first flag is `l` for local symbols and the second `w` for weak ones.
+    let linkage = if flags.starts_with('l') {
+        Linkage::from_binding(Binding::Local)
+    } else {
+        let binding = if flags[1..].starts_with('w') {
+            Binding::Weak
+        } else {
+            Binding::Global
+        };
+        Linkage {
+            visibility,
+            binding,
+        }
+    };
+    Some(Symbol {
+        address,
+        size,
+        name,
+        linkage,
+    })
+}
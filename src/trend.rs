@@ -0,0 +1,122 @@
+//! A history of headline numbers from many runs, e.g. one per commit on the main branch, so that
+//! duplication that creeps up slowly between releases is noticed. The history is a file with one
+//! JSON object per line, which each run appends to.
+
+use crate::DuplicationReport;
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::Write as _;
+use std::path::Path;
+
+const ENTRY_VERSION: u64 = 1;
+
+/// The summary of one run.
+#[derive(Serialize, Deserialize)]
+pub struct TrendEntry {
+    /// The commit that the binary was built from, if given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+
+    /// An identifier for the build, e.g. a CI build number, if given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_id: Option<String>,
+
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+
+    pub binary: String,
+    pub text_size: u64,
+    pub excess_bytes: u64,
+    pub dup_groups: u64,
+}
+
+impl TrendEntry {
+    pub fn from_report(
+        report: &DuplicationReport,
+        binary: &Path,
+        commit: Option<&str>,
+        build_id: Option<&str>,
+    ) -> TrendEntry {
+        TrendEntry {
+            commit: commit.map(str::to_owned),
+            build_id: build_id.map(str::to_owned),
+            recorded_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            binary: binary.display().to_string(),
+            text_size: report.text_size,
+            excess_bytes: report.excess_bytes(),
+            dup_groups: report.duplicated_functions(),
+        }
+    }
+
+    /// A short name for the run, preferring the commit to the build ID.
+    pub fn label(&self) -> Option<&str> {
+        self.commit.as_deref().or(self.build_id.as_deref())
+    }
+
+    /// Writes the entry as JSON, without a trailing newline.
+    pub fn write_json(&self, out: &mut impl std::io::Write) -> Result {
+        let stored = StoredEntry {
+            version: ENTRY_VERSION,
+            entry: self,
+        };
+        serde_json::to_writer(out, &stored)?;
+        Ok(())
+    }
+
+    fn from_json(text: &str) -> Result<TrendEntry> {
+        let Version { version } = serde_json::from_str(text)?;
+        if version != ENTRY_VERSION {
+            bail!("Unsupported history entry version {version}");
+        }
+        Ok(serde_json::from_str::<StoredEntry<TrendEntry>>(text)?.entry)
+    }
+}
+
+/// An entry as it's stored in the history, which has the version of the format alongside the
+/// entry's fields.
+#[derive(Serialize, Deserialize)]
+struct StoredEntry<E> {
+    version: u64,
+    #[serde(flatten)]
+    entry: E,
+}
+
+/// Just the version of a stored entry, which is read first to know how to read the rest.
+#[derive(Deserialize)]
+struct Version {
+    version: u64,
+}
+
+/// Adds `entry` to the end of the history at `path`, creating it if it doesn't exist.
+pub fn append(path: &Path, entry: &TrendEntry) -> Result {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history `{}`", path.display()))?;
+    let mut line = Vec::new();
+    entry.write_json(&mut line)?;
+    line.push(b'\n');
+    // The entry is written in one go, so that runs that finish at the same time don't interleave.
+    file.write_all(&line)?;
+    Ok(())
+}
+
+/// Reads the history at `path`, oldest first.
+pub fn load(path: &Path) -> Result<Vec<TrendEntry>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read history `{}`", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            TrendEntry::from_json(line)
+                .with_context(|| format!("Invalid entry on line {} of `{}`", i + 1, path.display()))
+        })
+        .collect()
+}
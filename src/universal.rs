@@ -0,0 +1,93 @@
+//! Mach-O universal, or fat, binaries, which hold a complete binary for each of several
+//! architectures. Each architecture's binary is analysed on its own, since their code can't be
+//! compared.
+
+use crate::Result;
+use anyhow::bail;
+use object::macho::FatArch32;
+use object::macho::FatArch64;
+use object::read::macho::FatArch;
+use object::read::macho::MachOFatFile;
+use object::Architecture;
+use object::FileKind;
+
+/// The binary for one architecture in a universal binary.
+pub struct Slice<'data> {
+    pub architecture: Architecture,
+    pub data: &'data [u8],
+}
+
+/// The name that Apple's tools give each architecture, followed by other names that `--arch`
+/// accepts for it, such as the first component of an LLVM target triple.
+const ARCHITECTURE_NAMES: &[(Architecture, &[&str])] = &[
+    (Architecture::X86_64, &["x86_64", "x86_64h"]),
+    (Architecture::Aarch64, &["arm64", "aarch64", "arm64e"]),
+    (Architecture::I386, &["i386", "i686", "x86"]),
+    (
+        Architecture::Arm,
+        &["arm", "armv7", "armv7s", "armv7k", "thumbv7"],
+    ),
+    (Architecture::PowerPc, &["ppc", "powerpc"]),
+    (Architecture::PowerPc64, &["ppc64", "powerpc64"]),
+    (Architecture::Mips, &["mips"]),
+];
+
+impl Slice<'_> {
+    /// The name of the slice's architecture, e.g. `arm64`.
+    pub fn name(&self) -> &'static str {
+        ARCHITECTURE_NAMES
+            .iter()
+            .find(|(architecture, _)| *architecture == self.architecture)
+            .map_or("unknown", |(_, names)| names[0])
+    }
+
+    /// Returns whether `arch` names this slice's architecture. This may be a name such as `arm64`
+    /// or a target triple such as `aarch64-apple-darwin`.
+    fn matches(&self, arch: &str) -> bool {
+        let name = arch.split('-').next().unwrap_or(arch);
+        ARCHITECTURE_NAMES.iter().any(|(architecture, names)| {
+            *architecture == self.architecture && names.iter().any(|n| n.eq_ignore_ascii_case(name))
+        })
+    }
+}
+
+/// Returns the binary for each architecture if `data` is a universal binary, or `None` otherwise.
+pub fn slices(data: &[u8]) -> Result<Option<Vec<Slice<'_>>>> {
+    match FileKind::parse(data) {
+        Ok(FileKind::MachOFat32) => slices_for::<FatArch32>(data).map(Some),
+        Ok(FileKind::MachOFat64) => slices_for::<FatArch64>(data).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn slices_for<Fat: FatArch>(data: &[u8]) -> Result<Vec<Slice<'_>>> {
+    MachOFatFile::<Fat>::parse(data)?
+        .arches()
+        .iter()
+        .map(|arch| {
+            Ok(Slice {
+                architecture: arch.architecture(),
+                data: arch.data(data)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the binary for `arch` if `data` is a universal binary, or `data` itself otherwise.
+pub fn select<'data>(data: &'data [u8], arch: Option<&str>) -> Result<&'data [u8]> {
+    let Some(slices) = slices(data)? else {
+        return Ok(data);
+    };
+    let names = slices
+        .iter()
+        .map(|slice| slice.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let Some(arch) = arch else {
+        bail!("This is a universal binary for {names}, so `--arch` is needed to pick one");
+    };
+    match slices.iter().find(|slice| slice.matches(arch)) {
+        Some(slice) => Ok(slice.data),
+        None => bail!("This universal binary has no `{arch}` binary, only {names}"),
+    }
+}
@@ -0,0 +1,180 @@
+//! Unpacking of binaries that are compressed or wrapped, as kernels and firmware images often are,
+//! so that they can be analysed without extracting the binary by hand first. We recognise:
+//!
+//! - gzip, xz and zstd compressed data.
+//! - U-Boot legacy images (uImage), whose payload may be compressed with gzip, LZMA or zstd.
+//! - Linux x86 kernels (vmlinuz or bzImage), which hold a compressed copy of vmlinux.
+//!
+//! Wrappers may be nested, e.g. a uImage holding a gzip-compressed kernel. Data that's already an
+//! object file or archive is left alone.
+
+use crate::Result;
+use anyhow::bail;
+use anyhow::Context;
+use object::FileKind;
+use std::io::Read as _;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b, 0x08];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const UIMAGE_MAGIC: &[u8] = &[0x27, 0x05, 0x19, 0x56];
+
+/// The size of a uImage header, which is followed by the payload.
+const UIMAGE_HEADER_SIZE: usize = 64;
+
+/// The offset of the `HdrS` signature in an x86 kernel's setup header.
+const BZIMAGE_SIGNATURE_OFFSET: usize = 0x202;
+
+/// How deeply wrappers may be nested before we give up.
+const MAX_DEPTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Compression {
+    Gzip,
+    Xz,
+    Zstd,
+    Lzma,
+}
+
+impl Compression {
+    /// Returns the compression that `data` starts with, if any. LZMA has no magic number, so is
+    /// only used when a header says so.
+    fn detect(data: &[u8]) -> Option<Compression> {
+        if data.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if data.starts_with(XZ_MAGIC) {
+            Some(Compression::Xz)
+        } else if data.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+            Compression::Lzma => "LZMA",
+        }
+    }
+
+    fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match self {
+            Compression::Gzip => {
+                flate2::read::GzDecoder::new(data).read_to_end(&mut out)?;
+            }
+            Compression::Xz => lzma_rs::xz_decompress(&mut &*data, &mut out)?,
+            Compression::Zstd => out = zstd::stream::decode_all(data)?,
+            Compression::Lzma => lzma_rs::lzma_decompress(&mut &*data, &mut out)?,
+        }
+        Ok(out)
+    }
+}
+
+/// Returns the binary inside `data` if it's compressed or wrapped, or `None` if `data` should be
+/// analysed as it is.
+pub fn unpack(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut layers = Vec::new();
+    let mut unpacked: Option<Vec<u8>> = None;
+    loop {
+        let current = unpacked.as_deref().unwrap_or(data);
+        if FileKind::parse(current).is_ok() {
+            if !layers.is_empty() {
+                tracing::info!("Unpacked {}", layers.join(", then "));
+            }
+            return Ok(unpacked);
+        }
+        let Some((layer, inner)) = unpack_layer(current)? else {
+            if layers.is_empty() {
+                return Ok(None);
+            }
+            bail!(
+                "Unpacking {} didn't give a binary that can be analysed",
+                layers.join(", then ")
+            );
+        };
+        layers.push(layer);
+        if layers.len() > MAX_DEPTH {
+            bail!("Too many nested wrappers: {}", layers.join(", then "));
+        }
+        unpacked = Some(inner);
+    }
+}
+
+/// Removes one wrapper from `data`, returning a description of it and its contents.
+fn unpack_layer(data: &[u8]) -> Result<Option<(String, Vec<u8>)>> {
+    if let Some(compression) = Compression::detect(data) {
+        let inner = compression
+            .decompress(data)
+            .with_context(|| format!("Failed to decompress {} data", compression.name()))?;
+        return Ok(Some((format!("{} data", compression.name()), inner)));
+    }
+    if data.starts_with(UIMAGE_MAGIC) {
+        return unpack_uimage(data).map(|inner| Some(("a uImage".to_owned(), inner)));
+    }
+    if data.get(BZIMAGE_SIGNATURE_OFFSET..BZIMAGE_SIGNATURE_OFFSET + 4) == Some(b"HdrS") {
+        return unpack_bzimage(data).map(|inner| Some(("a Linux bzImage".to_owned(), inner)));
+    }
+    Ok(None)
+}
+
+/// Returns the payload of a U-Boot legacy image, decompressed according to its header.
+fn unpack_uimage(data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < UIMAGE_HEADER_SIZE {
+        bail!("uImage header extends past the end of the file");
+    }
+    let size = read_u32_be(data, 12)? as usize;
+    let payload = data
+        .get(UIMAGE_HEADER_SIZE..UIMAGE_HEADER_SIZE + size)
+        .context("uImage payload extends past the end of the file")?;
+    let compression = match data[31] {
+        0 => return Ok(payload.to_vec()),
+        1 => Compression::Gzip,
+        3 => Compression::Lzma,
+        6 => Compression::Zstd,
+        other => bail!("Unsupported uImage compression type {other}"),
+    };
+    compression
+        .decompress(payload)
+        .with_context(|| format!("Failed to decompress {} uImage payload", compression.name()))
+}
+
+/// Returns the vmlinux inside an x86 kernel image. The setup header says where the compressed
+/// payload is relative to the protected-mode code, which follows the real-mode setup sectors.
+fn unpack_bzimage(data: &[u8]) -> Result<Vec<u8>> {
+    let setup_sectors = match data[0x1f1] {
+        // Old kernels leave this as zero to mean four.
+        0 => 4,
+        sectors => usize::from(sectors),
+    };
+    let start = (setup_sectors + 1) * 512 + read_u32_le(data, 0x248)? as usize;
+    let length = read_u32_le(data, 0x24c)? as usize;
+    let payload = data
+        .get(start..start + length)
+        .context("Kernel payload extends past the end of the file")?;
+    // The payload ends with the size of the decompressed kernel, which isn't part of the stream.
+    let stream = &payload[..payload.len().saturating_sub(4)];
+    let Some(compression) = Compression::detect(stream) else {
+        bail!("Kernel payload isn't compressed with gzip, xz or zstd");
+    };
+    compression
+        .decompress(stream)
+        .with_context(|| format!("Failed to decompress {} kernel payload", compression.name()))
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .context("Header extends past the end of the file")?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .context("Header extends past the end of the file")?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
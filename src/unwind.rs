@@ -0,0 +1,166 @@
+//! Reading of function bounds from unwind tables, for binaries whose symbols don't have sizes.
+//!
+//! Stripped binaries usually keep their unwind tables, since they're needed for panics and
+//! exceptions to unwind the stack. These have an entry for almost every function, which says where
+//! it starts and, except for ARM's `.ARM.exidx`, where it ends. Names are taken from any symbols at
+//! the same addresses, e.g. exported functions in `.dynsym`. Functions without one are only
+//! identified by their address.
+
+use crate::linkage::Linkage;
+use crate::text_section_ranges;
+use crate::Function;
+use crate::Result;
+use anyhow::Context as _;
+use gimli::UnwindSection as _;
+use object::Object as _;
+use object::ObjectKind;
+use object::ObjectSection as _;
+use object::ObjectSymbol as _;
+use object::SymbolKind;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// The size of an entry in `.ARM.exidx`.
+const EXIDX_ENTRY_SIZE: usize = 8;
+
+/// Returns whether `object` has unwind tables that we can read functions from. Relocatable objects
+/// are excluded, since the addresses in their tables are only filled in by relocations.
+pub(crate) fn has_tables(object: &object::File) -> bool {
+    object.kind() != ObjectKind::Relocatable
+        && (object.section_by_name(".eh_frame").is_some()
+            || object.section_by_name(".ARM.exidx").is_some())
+}
+
+/// Returns a function for each entry in the unwind tables of `object` that's within an executable
+/// section.
+pub(crate) fn functions<'data>(
+    object: &object::File<'data, &'data [u8]>,
+) -> Result<Vec<Function<'data>>> {
+    let mut ranges = eh_frame_ranges(object).context("Failed to read `.eh_frame`")?;
+    ranges.extend(exidx_ranges(object).context("Failed to read `.ARM.exidx`")?);
+    ranges.sort_unstable();
+    ranges.dedup_by_key(|(address, _)| *address);
+
+    let names = symbol_names(object);
+    let text_sections = text_section_ranges(object);
+    Ok(ranges
+        .into_iter()
+        .filter_map(|(address, size)| {
+            // Entries for functions that the linker discarded are left with an address of zero.
+            let (section_index, _) = text_sections
+                .iter()
+                .find(|(_, range)| range.contains(&(address & !1)))?;
+            let (name, linkage) = names
+                .get(&address)
+                .copied()
+                .map_or((None, Linkage::UNKNOWN), |(name, linkage)| {
+                    (Some(name), linkage)
+                });
+            (size != 0).then(|| Function {
+                name: name.map(Cow::Borrowed),
+                address,
+                size,
+                section_index: Some(*section_index),
+                size_inferred: false,
+                thumb: false,
+                linkage,
+                file: None,
+            })
+        })
+        .collect())
+}
+
+/// Returns the start address and size of each function with an FDE in `.eh_frame`.
+fn eh_frame_ranges(object: &object::File) -> Result<Vec<(u64, u64)>> {
+    let Some(section) = object.section_by_name(".eh_frame") else {
+        return Ok(Vec::new());
+    };
+    let endian = if object.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+    let eh_frame = gimli::EhFrame::new(section.data()?, endian);
+    // Pointers are usually relative to the FDE itself, but may be relative to other sections.
+    let mut bases = gimli::BaseAddresses::default().set_eh_frame(section.address());
+    if let Some(text) = object.section_by_name(".text") {
+        bases = bases.set_text(text.address());
+    }
+    if let Some(got) = object.section_by_name(".got") {
+        bases = bases.set_got(got.address());
+    }
+    let mut ranges = Vec::new();
+    let mut entries = eh_frame.entries(&bases);
+    while let Some(entry) = entries.next()? {
+        if let gimli::CieOrFde::Fde(partial) = entry {
+            let fde = partial.parse(gimli::EhFrame::cie_from_offset)?;
+            ranges.push((fde.initial_address(), fde.len()));
+        }
+    }
+    Ok(ranges)
+}
+
+/// Returns the start address and size of each function in `.ARM.exidx`. Entries only say where a
+/// function starts, so each is assumed to extend to the start of the next, or to the end of its
+/// section. Addresses of Thumb functions have their lowest bit set.
+fn exidx_ranges(object: &object::File) -> Result<Vec<(u64, u64)>> {
+    let Some(section) = object.section_by_name(".ARM.exidx") else {
+        return Ok(Vec::new());
+    };
+    let data = section.data()?;
+    let mut starts = data
+        .chunks_exact(EXIDX_ENTRY_SIZE)
+        .enumerate()
+        .map(|(i, entry)| {
+            let word = object_u32(object, &entry[..4]);
+            // The first word is a 31 bit offset from the entry to the function.
+            let offset = ((word << 1) as i32 >> 1) as i64;
+            let entry_address = section.address() + (i * EXIDX_ENTRY_SIZE) as u64;
+            entry_address.wrapping_add_signed(offset) & 0xffff_ffff
+        })
+        .collect::<Vec<_>>();
+    starts.sort_unstable();
+    starts.dedup();
+    let text_sections = text_section_ranges(object);
+    Ok(starts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &start)| {
+            let (_, section_range) = text_sections
+                .iter()
+                .find(|(_, range)| range.contains(&(start & !1)))?;
+            let end = starts
+                .get(i + 1)
+                .map(|next| next & !1)
+                .filter(|next| *next <= section_range.end)
+                .unwrap_or(section_range.end);
+            Some((start, end - (start & !1)))
+        })
+        .collect())
+}
+
+fn object_u32(object: &object::File, bytes: &[u8]) -> u32 {
+    let bytes = bytes.try_into().unwrap();
+    if object.is_little_endian() {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
+
+/// Returns the name and linkage of a function symbol at each address, preferring the symbol table
+/// to the dynamic symbol table.
+fn symbol_names<'data>(
+    object: &object::File<'data, &'data [u8]>,
+) -> HashMap<u64, (&'data str, Linkage)> {
+    let mut names = HashMap::new();
+    for sym in object.dynamic_symbols().chain(object.symbols()) {
+        if sym.kind() != SymbolKind::Text || sym.is_undefined() {
+            continue;
+        }
+        if let Ok(name) = sym.name() {
+            names.insert(sym.address(), (name, Linkage::of_symbol(&sym)));
+        }
+    }
+    names
+}
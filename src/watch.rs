@@ -0,0 +1,98 @@
+//! Re-running the analysis whenever the input binary is rebuilt, so that the effect of changes on
+//! duplication can be seen without running the tool by hand after each build.
+
+use crate::analyze;
+use crate::finish_progress;
+use crate::open_output;
+use crate::pretty_size;
+use crate::print_diff;
+use crate::print_diff_json;
+use crate::progress::ProgressBar;
+use crate::Args;
+use crate::OutputFormat;
+use anyhow::bail;
+use duplicate_function_checker::diff;
+use duplicate_function_checker::diff::Snapshot;
+use duplicate_function_checker::Options;
+use duplicate_function_checker::Result;
+use std::io::Write as _;
+use std::path::Path;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// How often to check whether the binary has changed.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Analyses `bin`, then each time that it changes, prints how duplication changed since the
+/// previous analysis. Only returns on errors writing output.
+pub(crate) fn watch(
+    bin: &Path,
+    options: &Options,
+    progress: Option<&ProgressBar>,
+    args: &Args,
+) -> Result {
+    if !matches!(args.format, OutputFormat::Text | OutputFormat::Json) {
+        bail!("Only text and JSON output are supported by `--watch`");
+    }
+    let mut out = open_output(args)?;
+    let mut modified = modification_time(bin);
+    let mut previous: Option<Snapshot> = None;
+    loop {
+        match analyze(bin, options) {
+            Ok(report) => {
+                finish_progress(progress);
+                let snapshot = Snapshot::from_report(&report);
+                if args.format == OutputFormat::Text {
+                    match &previous {
+                        None => writeln!(
+                            out,
+                            "   Excess bytes: {} in {} groups, watching for changes",
+                            pretty_size(snapshot.excess_bytes()),
+                            snapshot.groups.len()
+                        )?,
+                        Some(previous) => {
+                            print_diff(&mut out, &diff::diff_snapshots(previous, &snapshot), args)?
+                        }
+                    }
+                    writeln!(out)?;
+                } else {
+                    // Each run is a line of JSON. The first is compared against nothing, so it
+                    // lists every group as having appeared.
+                    let empty = Snapshot { groups: Vec::new() };
+                    let previous = previous.as_ref().unwrap_or(&empty);
+                    print_diff_json(&mut out, &diff::diff_snapshots(previous, &snapshot))?;
+                }
+                previous = Some(snapshot);
+            }
+            // The build may have failed, or we may have read the binary while the linker was still
+            // writing it, so rather than giving up, we wait for the next build.
+            Err(error) => {
+                finish_progress(progress);
+                tracing::error!("{error:#}");
+            }
+        }
+        out.flush()?;
+        modified = wait_for_change(bin, modified);
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Waits until the modification time of `path` differs from `previous`, then until it stays the
+/// same between two polls, so that a binary that's still being written isn't read. Returns the new
+/// modification time.
+fn wait_for_change(path: &Path, previous: Option<SystemTime>) -> Option<SystemTime> {
+    let mut last_seen = modification_time(path);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = modification_time(path);
+        if current.is_some() && current != previous && current == last_seen {
+            return current;
+        }
+        last_seen = current;
+    }
+}
@@ -0,0 +1,97 @@
+//! Side by side estimates of how much different deduplication strategies could save, to help decide
+//! between e.g. enabling linker ICF, sharing generics between crates or changing the source.
+
+use crate::fuzzy;
+use crate::icf;
+use crate::KeyType;
+use crate::Options;
+use crate::Result;
+use anyhow::Context;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    /// Folding functions that are byte-for-byte identical once normalised for their address, as
+    /// `--icf=safe` style linker options do.
+    ExactIcf,
+
+    /// Folding functions that are identical once calls to foldable functions are also folded, as
+    /// `--icf=all` does.
+    CallFoldingIcf,
+
+    /// Keeping only one copy of each monomorphisation, regardless of which crate instantiated it,
+    /// as `-Zshare-generics` aims to. Estimated by grouping functions with the same name, ignoring
+    /// rustc's hash, and size.
+    ShareGenerics,
+
+    /// Outlining the common parts of functions that are similar but not identical into a shared
+    /// implementation. This is in addition to the savings from removing exact duplicates.
+    MergeSimilar,
+}
+
+/// The estimated savings from a single strategy.
+pub struct Estimate {
+    pub strategy: Strategy,
+    pub saved_bytes: u64,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [
+        Strategy::ExactIcf,
+        Strategy::CallFoldingIcf,
+        Strategy::ShareGenerics,
+        Strategy::MergeSimilar,
+    ];
+
+    /// A short human-readable description.
+    pub fn description(self) -> &'static str {
+        match self {
+            Strategy::ExactIcf => "Exact ICF",
+            Strategy::CallFoldingIcf => "ICF with call-target folding",
+            Strategy::ShareGenerics => "Sharing generics",
+            Strategy::MergeSimilar => "Merging near-duplicates",
+        }
+    }
+
+    /// A stable identifier for machine-readable output.
+    pub fn id(self) -> &'static str {
+        match self {
+            Strategy::ExactIcf => "exact_icf",
+            Strategy::CallFoldingIcf => "call_folding_icf",
+            Strategy::ShareGenerics => "share_generics",
+            Strategy::MergeSimilar => "merge_similar",
+        }
+    }
+}
+
+/// Estimates the savings of each strategy for the linked binary `data`. Near-duplicates are
+/// functions whose instructions are at least `similarity` similar, where `similarity` is between 0
+/// and 1.
+pub fn estimate(data: &[u8], options: &Options, similarity: f64) -> Result<Vec<Estimate>> {
+    let icf = icf::simulate_icf(data, options).context("Failed to simulate ICF")?;
+    let share_generics = crate::analyze_data(
+        data,
+        &Options {
+            key: KeyType::NameWithoutRustHash,
+            ..options.clone()
+        },
+    )
+    .context("Failed to group functions by name")?
+    .excess_bytes();
+    let merge_similar = fuzzy::find_similar(data, options, similarity)
+        .context("Failed to find similar functions")?
+        .iter()
+        .map(|cluster| cluster.potential_savings())
+        .sum();
+    Ok(Strategy::ALL
+        .into_iter()
+        .map(|strategy| Estimate {
+            strategy,
+            saved_bytes: match strategy {
+                Strategy::ExactIcf => icf.byte_identical_excess_bytes,
+                Strategy::CallFoldingIcf => icf.icf_excess_bytes,
+                Strategy::ShareGenerics => share_generics,
+                Strategy::MergeSimilar => merge_similar,
+            },
+        })
+        .collect())
+}
@@ -0,0 +1,21 @@
+//! Helpers that are shared by the tests for several architectures.
+
+use duplicate_function_checker::metrics::CodeMetrics;
+use duplicate_function_checker::metrics::GroupMetrics;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+/// Returns the instruction and basic block counts of the first duplicate group in the fixture
+/// `name`.
+pub fn first_group_metrics(name: &str, options: &Options) -> CodeMetrics {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name);
+    let data = std::fs::read(&path).unwrap();
+    let report = duplicate_function_checker::analyze(&path, options).unwrap();
+    let group = report.duplicates().next().unwrap();
+    GroupMetrics::from_data(&data, options)
+        .unwrap()
+        .get(group)
+        .unwrap()
+}
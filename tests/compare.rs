@@ -0,0 +1,48 @@
+use duplicate_function_checker::compare;
+use duplicate_function_checker::compare::Comparison;
+use duplicate_function_checker::compare::Listing;
+use duplicate_function_checker::compare::Row;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+fn listing(lines: &[&str]) -> Listing {
+    Listing {
+        function_size: lines.len() as u64,
+        matches: 1,
+        lines: lines.iter().map(|line| line.to_string()).collect(),
+    }
+}
+
+#[test]
+fn function_is_identical_to_itself() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/i686-duplicates");
+    let data = std::fs::read(path).unwrap();
+    let comparison = Comparison {
+        old: compare::listing(&data, "dup1", &Options::default()).unwrap(),
+        new: compare::listing(&data, "dup1", &Options::default()).unwrap(),
+    };
+    assert!(comparison.is_identical());
+    // The call is to a symbol, not an address, so it would match even if `helper` had moved.
+    assert_eq!(comparison.old.lines.len(), 4);
+    assert_eq!(comparison.old.lines[1], "call helper");
+}
+
+#[test]
+fn removed_and_added_lines_are_paired() {
+    let comparison = Comparison {
+        old: listing(&["push rbp", "call a", "mov eax, 1", "pop rbp", "ret"]),
+        new: listing(&["push rbp", "call b", "pop rbp", "nop", "ret"]),
+    };
+    assert!(!comparison.is_identical());
+    assert_eq!(
+        comparison.rows(),
+        [
+            Row::Same("push rbp"),
+            Row::Changed("call a", "call b"),
+            Row::Removed("mov eax, 1"),
+            Row::Same("pop rbp"),
+            Row::Added("nop"),
+            Row::Same("ret"),
+        ]
+    );
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the checker on the fixture with a config file containing `config` and returns the line
+/// with the excess bytes.
+fn excess_with_config(name: &str, config: &str, args: &[&str]) -> String {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let path = std::env::temp_dir().join(format!("config-{name}-{}.toml", std::process::id()));
+    std::fs::write(&path, config).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .current_dir(&data)
+        .args(["--quiet", "--no-cache", "--key", "name-without-rust-hash"])
+        .arg("--config")
+        .arg(&path)
+        .args(args)
+        .arg("libfixture.rlib")
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .find(|line| line.contains("Excess bytes"))
+        .unwrap()
+        .trim()
+        .to_owned()
+}
+
+#[test]
+fn where_is_read_from_config() {
+    let excess = excess_with_config("where", r#"where = 'name ~ "swap"'"#, &[]);
+    assert_eq!(excess, "Excess bytes: 7.0B (1.0% of executable code)");
+}
+
+#[test]
+fn where_on_the_command_line_takes_precedence() {
+    let excess = excess_with_config(
+        "where-override",
+        r#"where = 'name ~ "swap"'"#,
+        &["--where", "copies > 2"],
+    );
+    assert_eq!(excess, "Excess bytes: 300.0B (43.0% of executable code)");
+}
@@ -0,0 +1,29 @@
+# An AArch64 binary with two identical functions, `fixture::dup1` and `fixture::dup2`. We can't
+# normalise AArch64 code, so only keys that don't look at instructions are supported. Built with:
+#
+#   llvm-mc -triple=aarch64-linux-gnu -filetype=obj -o aarch64.o aarch64-duplicates.s
+#   ld.lld -o aarch64-duplicates aarch64.o
+
+    .text
+
+    .globl _start
+    .type _start, %function
+_start:
+    bl _ZN7fixture4dup117h0123456789abcdefE
+    bl _ZN7fixture4dup217h0123456789abcdefE
+1:  b 1b
+    .size _start, .-_start
+
+    .globl _ZN7fixture4dup117h0123456789abcdefE
+    .type _ZN7fixture4dup117h0123456789abcdefE, %function
+_ZN7fixture4dup117h0123456789abcdefE:
+    add x0, x0, #1
+    ret
+    .size _ZN7fixture4dup117h0123456789abcdefE, .-_ZN7fixture4dup117h0123456789abcdefE
+
+    .globl _ZN7fixture4dup217h0123456789abcdefE
+    .type _ZN7fixture4dup217h0123456789abcdefE, %function
+_ZN7fixture4dup217h0123456789abcdefE:
+    add x0, x0, #1
+    ret
+    .size _ZN7fixture4dup217h0123456789abcdefE, .-_ZN7fixture4dup217h0123456789abcdefE
@@ -0,0 +1,48 @@
+//! Source of `libfixture.rlib`, built with:
+//!
+//! ```sh
+//! rustc --edition 2021 --crate-type rlib --crate-name fixture -C opt-level=1 -C codegen-units=2 \
+//!     -C debuginfo=0 -C embed-bitcode=no -C metadata=golden fixture.rs
+//! ```
+//!
+//! Generic functions instantiated with types of the same size have the same name, once rustc's
+//! hash is removed, and the same size, so `--key name-without-rust-hash` groups them.
+
+#[inline(never)]
+pub fn count<T: Copy + PartialEq>(values: &[T], wanted: T) -> usize {
+    values.iter().filter(|v| **v == wanted).count()
+}
+
+#[inline(never)]
+pub fn first<T: Copy>(values: &[T]) -> Option<T> {
+    values.first().copied()
+}
+
+#[inline(never)]
+pub fn swap<T: Copy>(pair: (T, T)) -> (T, T) {
+    (pair.1, pair.0)
+}
+
+pub mod a {
+    pub fn use_u32(values: &[u32]) -> usize {
+        super::count(values, 1) + super::first(values).unwrap_or(0) as usize
+    }
+
+    pub fn use_i32(values: &[i32]) -> usize {
+        super::count(values, 1) + super::first(values).unwrap_or(0) as usize
+    }
+}
+
+pub mod b {
+    pub fn use_u64(pair: (u64, u64)) -> u64 {
+        super::swap(pair).0
+    }
+
+    pub fn use_i64(pair: (i64, i64)) -> i64 {
+        super::swap(pair).0
+    }
+
+    pub fn use_char(values: &[char]) -> usize {
+        super::count(values, 'x') + super::first(values).map_or(0, |c| c as usize)
+    }
+}
@@ -0,0 +1,50 @@
+# A 32-bit x86 binary with two identical functions, `dup1` and `dup2`, at different addresses. Both
+# contain `pusha` and `popa`, which aren't valid in 64-bit mode, so they only decode correctly if
+# we use the right bitness. Built with:
+#
+#   gcc -m32 -nostdlib -static -Wl,--build-id=none -o i686-duplicates i686-duplicates.s
+
+    .text
+
+    .globl _start
+    .type _start, @function
+_start:
+    call dup1
+    call dup2
+    call distinct
+    hlt
+    .size _start, .-_start
+
+    .globl helper
+    .type helper, @function
+helper:
+    inc %eax
+    ret
+    .size helper, .-helper
+
+    .globl dup1
+    .type dup1, @function
+dup1:
+    pusha
+    call helper
+    popa
+    ret
+    .size dup1, .-dup1
+
+    .globl dup2
+    .type dup2, @function
+dup2:
+    pusha
+    call helper
+    popa
+    ret
+    .size dup2, .-dup2
+
+    .globl distinct
+    .type distinct, @function
+distinct:
+    pusha
+    mov $1, %eax
+    popa
+    ret
+    .size distinct, .-distinct
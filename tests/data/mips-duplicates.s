@@ -0,0 +1,70 @@
+# A MIPS binary with two identical functions, `dup1` and `dup2`, at different addresses. Both call
+# `helper` with `bal`, whose offset depends on where the call is, so they're only found to be the
+# same if PC-relative instructions are normalised. Built for each byte order with:
+#
+#   llvm-mc -triple=mips-linux-gnu -filetype=obj -o mips.o mips-duplicates.s
+#   ld.lld -o mips-duplicates mips.o
+#   llvm-mc -triple=mips64el-linux-gnuabi64 -filetype=obj -o mips64el.o mips-duplicates.s
+#   ld.lld -o mips64el-duplicates mips64el.o
+
+    .set noreorder
+    .text
+
+    .globl __start
+    .type __start, @function
+__start:
+    jal dup1
+    nop
+    jal dup2
+    nop
+    jal distinct
+    nop
+1:  b 1b
+    nop
+    .size __start, .-__start
+
+    .globl helper
+    .type helper, @function
+helper:
+    jr $ra
+    addiu $v0, $v0, 1
+    .size helper, .-helper
+
+    .globl dup1
+    .type dup1, @function
+dup1:
+    move $t0, $ra
+    li $t1, 3
+1:  bal helper
+    addiu $t1, $t1, -1
+    bnez $t1, 1b
+    nop
+    jr $t0
+    nop
+    .size dup1, .-dup1
+
+    .globl dup2
+    .type dup2, @function
+dup2:
+    move $t0, $ra
+    li $t1, 3
+1:  bal helper
+    addiu $t1, $t1, -1
+    bnez $t1, 1b
+    nop
+    jr $t0
+    nop
+    .size dup2, .-dup2
+
+    .globl distinct
+    .type distinct, @function
+distinct:
+    move $t0, $ra
+    li $t1, 4
+1:  bal helper
+    addiu $t1, $t1, -1
+    bnez $t1, 1b
+    nop
+    jr $t0
+    nop
+    .size distinct, .-distinct
@@ -0,0 +1,84 @@
+# A 64-bit PowerPC binary with two identical functions, `dup1` and `dup2`, at different addresses.
+# Both call `helper` with `bl` and load the address of `counter` with Power10's PC-relative
+# `paddi`, the offsets of which depend on where the instructions are, so they're only found to be
+# the same if PC-relative instructions are normalised. Built for each byte order with:
+#
+#   llvm-mc -triple=powerpc64-linux-gnu -mcpu=pwr10 -filetype=obj -o ppc64.o powerpc64-duplicates.s
+#   ld.lld -o powerpc64-duplicates ppc64.o
+#   llvm-mc -triple=powerpc64le-linux-gnu -mcpu=pwr10 -filetype=obj -o ppc64le.o \
+#       powerpc64-duplicates.s
+#   ld.lld -o powerpc64le-duplicates ppc64le.o
+
+    .abiversion 2
+    .text
+
+    .globl _start
+    .type _start, @function
+_start:
+    bl dup1
+    bl dup2
+    bl distinct
+1:  b 1b
+    .size _start, .-_start
+
+    .globl helper
+    .type helper, @function
+helper:
+    addi 3, 3, 1
+    blr
+    .size helper, .-helper
+
+    .globl dup1
+    .type dup1, @function
+dup1:
+    mflr 0
+    std 0, 16(1)
+    paddi 5, 0, counter@pcrel, 1
+    li 4, 3
+1:  bl helper
+    addi 4, 4, -1
+    cmpdi 4, 0
+    bne 0, 1b
+    stw 3, 0(5)
+    ld 0, 16(1)
+    mtlr 0
+    blr
+    .size dup1, .-dup1
+
+    .globl dup2
+    .type dup2, @function
+dup2:
+    mflr 0
+    std 0, 16(1)
+    paddi 5, 0, counter@pcrel, 1
+    li 4, 3
+1:  bl helper
+    addi 4, 4, -1
+    cmpdi 4, 0
+    bne 0, 1b
+    stw 3, 0(5)
+    ld 0, 16(1)
+    mtlr 0
+    blr
+    .size dup2, .-dup2
+
+    .globl distinct
+    .type distinct, @function
+distinct:
+    mflr 0
+    std 0, 16(1)
+    paddi 5, 0, counter@pcrel, 1
+    li 4, 4
+1:  bl helper
+    addi 4, 4, -1
+    cmpdi 4, 0
+    bne 0, 1b
+    stw 3, 0(5)
+    ld 0, 16(1)
+    mtlr 0
+    blr
+    .size distinct, .-distinct
+
+    .data
+counter:
+    .long 0
@@ -0,0 +1,21 @@
+# The AArch64 slice of a universal Mach-O object, where `_dup1`, `_dup2` and `_dup3` are identical.
+# See `universal-x86_64.s` for how it's built.
+
+    .text
+
+    .globl _dup1
+_dup1:
+    add x0, x0, #1
+    ret
+
+    .globl _dup2
+_dup2:
+    add x0, x0, #1
+    ret
+
+    .globl _dup3
+_dup3:
+    add x0, x0, #1
+    ret
+
+    .subsections_via_symbols
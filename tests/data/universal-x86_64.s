@@ -0,0 +1,25 @@
+# The x86-64 slice of a universal Mach-O object, where `_dup1` and `_dup2` are identical. Built
+# with:
+#
+#   llvm-mc -triple=x86_64-apple-macos -filetype=obj -o x86_64.o universal-x86_64.s
+#   llvm-mc -triple=arm64-apple-macos -filetype=obj -o arm64.o universal-arm64.s
+#   llvm-lipo -create -output universal x86_64.o arm64.o
+
+    .text
+
+    .globl _dup1
+_dup1:
+    lea 1(%rdi), %eax
+    ret
+
+    .globl _dup2
+_dup2:
+    lea 1(%rdi), %eax
+    ret
+
+    .globl _distinct
+_distinct:
+    xor %eax, %eax
+    ret
+
+    .subsections_via_symbols
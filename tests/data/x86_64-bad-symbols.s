@@ -0,0 +1,49 @@
+# An x86-64 binary whose function symbols have sizes that don't fit together: `outer` overlaps
+# `inner`, `alias` claims to be shorter than `aliased`, which is at the same address, and `last`
+# extends past the end of `.text`. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64.o x86_64-bad-symbols.s
+#   ld.lld -o x86_64-bad-symbols x86_64.o
+
+    .text
+
+    .globl _start
+    .type _start, @function
+_start:
+    call outer
+    call aliased
+    call last
+    ret
+    .size _start, .-_start
+
+    .globl outer
+    .type outer, @function
+outer:
+    xor %eax, %eax
+    .size outer, 8
+
+    .globl inner
+    .type inner, @function
+inner:
+    xor %ecx, %ecx
+    xor %edx, %edx
+    xor %esi, %esi
+    ret
+    .size inner, .-inner
+
+    .globl aliased
+    .type aliased, @function
+    .globl alias
+    .type alias, @function
+aliased:
+alias:
+    mov $1, %eax
+    ret
+    .size aliased, .-aliased
+    .size alias, 2
+
+    .globl last
+    .type last, @function
+last:
+    ret
+    .size last, 4
@@ -0,0 +1,36 @@
+# An x86-64 binary with two functions that are the same except that `short_branch` uses the 8 bit
+# form of `je` and `near_branch` the 32 bit form, as an assembler might choose differently for
+# copies from different compilation units. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64.o x86_64-branch-widths.s
+#   ld.lld -o x86_64-branch-widths x86_64.o
+
+    .text
+
+    .globl _start
+    .type _start, @function
+_start:
+    call short_branch
+    call near_branch
+    ret
+    .size _start, .-_start
+
+    .globl short_branch
+    .type short_branch, @function
+short_branch:
+    test %edi, %edi
+    je 1f
+    mov $1, %eax
+1:
+    ret
+    .size short_branch, .-short_branch
+
+    .globl near_branch
+    .type near_branch, @function
+near_branch:
+    test %edi, %edi
+    {disp32} je 1f
+    mov $1, %eax
+1:
+    ret
+    .size near_branch, .-near_branch
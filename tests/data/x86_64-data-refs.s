@@ -0,0 +1,52 @@
+# An x86-64 binary with two pairs of duplicate functions. The pairs are the same except that
+# `load_one_*` read `one` and `load_two_*` read `two`, so they're different groups that would get
+# the same ID if data references were all that told them apart. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64.o x86_64-data-refs.s
+#   ld -n -z noseparate-code -o x86_64-data-refs x86_64.o
+
+    .text
+
+    .globl _start
+    .type _start, @function
+_start:
+    call load_one_a
+    call load_one_b
+    call load_two_a
+    call load_two_b
+    ret
+    .size _start, .-_start
+
+    .globl load_one_a
+    .type load_one_a, @function
+load_one_a:
+    mov one(%rip), %eax
+    ret
+    .size load_one_a, .-load_one_a
+
+    .globl load_one_b
+    .type load_one_b, @function
+load_one_b:
+    mov one(%rip), %eax
+    ret
+    .size load_one_b, .-load_one_b
+
+    .globl load_two_a
+    .type load_two_a, @function
+load_two_a:
+    mov two(%rip), %eax
+    ret
+    .size load_two_a, .-load_two_a
+
+    .globl load_two_b
+    .type load_two_b, @function
+load_two_b:
+    mov two(%rip), %eax
+    ret
+    .size load_two_b, .-load_two_b
+
+    .section .rodata
+one:
+    .long 1
+two:
+    .long 2
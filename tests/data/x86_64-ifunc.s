@@ -0,0 +1,35 @@
+# An x86-64 relocatable object with an IFUNC, `checksum`, whose resolver is at the start of its
+# section, like `dup1` and `dup2`, which are ordinary duplicates in sections of their own. Built
+# with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64-ifunc.o x86_64-ifunc.s
+
+    .section .text.checksum,"ax",@progbits
+    .globl checksum
+    .type checksum, @gnu_indirect_function
+checksum:
+    lea generic(%rip), %rax
+    ret
+    .size checksum, .-checksum
+
+    .type generic, @function
+generic:
+    xor %eax, %eax
+    ret
+    .size generic, .-generic
+
+    .section .text.dup1,"ax",@progbits
+    .globl dup1
+    .type dup1, @function
+dup1:
+    mov $1, %eax
+    ret
+    .size dup1, .-dup1
+
+    .section .text.dup2,"ax",@progbits
+    .globl dup2
+    .type dup2, @function
+dup2:
+    mov $1, %eax
+    ret
+    .size dup2, .-dup2
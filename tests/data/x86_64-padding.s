@@ -0,0 +1,27 @@
+# An x86-64 relocatable object with two identical functions, `first` and `second`, whose symbols
+# have no size, so their sizes are inferred and include the `int3` padding after each of them.
+# Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64-padding.o x86_64-padding.s
+
+    .text
+
+    .globl first
+    .type first, @function
+first:
+    test %edi, %edi
+    je 1f
+    mov $1, %eax
+1:
+    ret
+    .p2align 4, 0xcc
+
+    .globl second
+    .type second, @function
+second:
+    test %edi, %edi
+    je 1f
+    mov $1, %eax
+1:
+    ret
+    .p2align 4, 0xcc
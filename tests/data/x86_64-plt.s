@@ -0,0 +1,41 @@
+# An x86-64 shared library with a PLT, for calls to `puts`, and two IFUNCs, `checksum` and `crc`,
+# whose resolvers are identical. `dup1` and `dup2` are ordinary duplicates. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64.o x86_64-plt.s
+#   ld.lld -shared -o x86_64-plt x86_64.o
+
+    .text
+
+    .globl dup1
+    .type dup1, @function
+dup1:
+    call puts@PLT
+    ret
+    .size dup1, .-dup1
+
+    .globl dup2
+    .type dup2, @function
+dup2:
+    call puts@PLT
+    ret
+    .size dup2, .-dup2
+
+    .globl checksum
+    .type checksum, @gnu_indirect_function
+checksum:
+    lea generic(%rip), %rax
+    ret
+    .size checksum, .-checksum
+
+    .globl crc
+    .type crc, @gnu_indirect_function
+crc:
+    lea generic(%rip), %rax
+    ret
+    .size crc, .-crc
+
+    .type generic, @function
+generic:
+    xor %eax, %eax
+    ret
+    .size generic, .-generic
@@ -0,0 +1,61 @@
+# An x86-64 object file with duplicated read-only data. The string literal "duplicated string" is
+# in two of rustc-style per-constant sections, `TABLE_A` and `TABLE_B` are identical tables, with
+# `TABLE_ALIAS` at the same address as `TABLE_A`, and "unique" only appears once. The copies of
+# "merged" are in a mergeable string section, which the linker would deduplicate, so they aren't
+# reported. The two pointer tables both point at `TABLE_A`. Of the three vtables, two point at the
+# same functions, but refer to drop glue with different hashes, as they would if they came from
+# different crates. The third has a different method. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64-rodata.o x86_64-rodata.s
+
+    .section .rodata..Lanon.fixture.0,"a",@progbits
+    .ascii "duplicated string"
+
+    .section .rodata..Lanon.fixture.1,"a",@progbits
+    .ascii "duplicated string"
+
+    .section .rodata..Lanon.fixture.2,"a",@progbits
+    .ascii "unique"
+
+    .section .rodata.TABLE_A,"a",@progbits
+    .globl TABLE_A
+    .globl TABLE_ALIAS
+    .type TABLE_A, @object
+    .type TABLE_ALIAS, @object
+TABLE_A:
+TABLE_ALIAS:
+    .long 1, 2, 3, 5, 8, 13
+    .size TABLE_A, 24
+    .size TABLE_ALIAS, 24
+
+    .section .rodata.TABLE_B,"a",@progbits
+    .globl TABLE_B
+    .type TABLE_B, @object
+TABLE_B:
+    .long 1, 2, 3, 5, 8, 13
+    .size TABLE_B, 24
+
+    .section .rodata.str1.1,"aMS",@progbits,1
+    .asciz "merged"
+    .asciz "merged"
+
+    .section .rodata..Lanon.fixture.3,"a",@progbits
+    .quad TABLE_A
+
+    .section .rodata..Lanon.fixture.4,"a",@progbits
+    .quad TABLE_A
+
+    .section .data.rel.ro..L__unnamed_1,"aw",@progbits
+    .quad _ZN4core3ptr42drop_in_place$LT$alloc..string..String$GT$17h1111111111111111E
+    .quad 24, 8
+    .quad _ZN7fixture6Widget4draw17h3333333333333333E
+
+    .section .data.rel.ro..L__unnamed_2,"aw",@progbits
+    .quad _ZN4core3ptr42drop_in_place$LT$alloc..string..String$GT$17h2222222222222222E
+    .quad 24, 8
+    .quad _ZN7fixture6Widget4draw17h3333333333333333E
+
+    .section .data.rel.ro..L__unnamed_3,"aw",@progbits
+    .quad _ZN4core3ptr42drop_in_place$LT$alloc..string..String$GT$17h1111111111111111E
+    .quad 24, 8
+    .quad _ZN7fixture6Widget4hide17h3333333333333333E
@@ -0,0 +1,37 @@
+# An x86-64 shared library whose symbol table has gone stale: `dup2` was removed from `.symtab`,
+# but is still exported in `.dynsym`. `dup1` and `dup2` are the same size, as are the local
+# functions `local1` and `local2`, which only `.symtab` has. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64.o x86_64-stale-symtab.s
+#   ld.lld -shared -o x86_64-stale-symtab x86_64.o
+#   llvm-objcopy --strip-symbol=dup2 x86_64-stale-symtab
+
+    .text
+
+    .globl dup1
+    .type dup1, @function
+dup1:
+    mov $1, %eax
+    add %edi, %eax
+    ret
+    .size dup1, .-dup1
+
+    .globl dup2
+    .type dup2, @function
+dup2:
+    mov $1, %eax
+    add %edi, %eax
+    ret
+    .size dup2, .-dup2
+
+    .type local1, @function
+local1:
+    lea 2(%rdi), %eax
+    ret
+    .size local1, .-local1
+
+    .type local2, @function
+local2:
+    lea 2(%rdi), %eax
+    ret
+    .size local2, .-local2
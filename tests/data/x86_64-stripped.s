@@ -0,0 +1,40 @@
+# A stripped x86-64 binary, so the only record of where its functions start and end is its
+# `.eh_frame`. `first` and `second` are identical and `different` isn't. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64.o x86_64-stripped.s
+#   ld.lld --strip-all -o x86_64-stripped x86_64.o
+
+    .text
+
+    .globl _start
+    .type _start, @function
+_start:
+    .cfi_startproc
+    call first
+    call second
+    call different
+    ret
+    .cfi_endproc
+
+    .type first, @function
+first:
+    .cfi_startproc
+    lea 1(%rdi), %rax
+    imul %rsi, %rax
+    ret
+    .cfi_endproc
+
+    .type second, @function
+second:
+    .cfi_startproc
+    lea 1(%rdi), %rax
+    imul %rsi, %rax
+    ret
+    .cfi_endproc
+
+    .type different, @function
+different:
+    .cfi_startproc
+    lea 2(%rdi), %rax
+    ret
+    .cfi_endproc
@@ -0,0 +1,30 @@
+# An x86-64 binary whose function `undecodable` starts with a byte that isn't a valid instruction
+# in 64-bit mode, as if data had been placed in `.text`. Built with:
+#
+#   llvm-mc -triple=x86_64-linux-gnu -filetype=obj -o x86_64.o x86_64-undecodable.s
+#   ld.lld -o x86_64-undecodable x86_64.o
+
+    .text
+
+    .globl _start
+    .type _start, @function
+_start:
+    call undecodable
+    call helper
+    ret
+    .size _start, .-_start
+
+    .globl undecodable
+    .type undecodable, @function
+undecodable:
+    # `push es`, which was removed in 64-bit mode.
+    .byte 0x06
+    ret
+    .size undecodable, .-undecodable
+
+    .globl helper
+    .type helper, @function
+helper:
+    xor %eax, %eax
+    ret
+    .size helper, .-helper
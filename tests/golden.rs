@@ -0,0 +1,179 @@
+//! Runs the checker on `tests/data/libfixture.rlib` and compares what it prints with the expected
+//! output in `tests/data/golden`. After an intended change to the output, run with `UPDATE_GOLDEN=1`
+//! to overwrite the expected output, then review the diff.
+//!
+//! Functions are keyed by name, so that the output doesn't depend on how instructions are decoded.
+//! It does still depend on what rustc generated, so `libfixture.rlib` is checked in rather than
+//! built by the tests. It was built by rustc 1.95.0 from `fixture.rs`; rebuilding it with another
+//! version will most likely change the expected output.
+
+use std::path::Path;
+use std::process::Command;
+
+fn check_output(golden_name: &str, args: &[&str]) {
+    check_subcommand_output(golden_name, &[], args);
+}
+
+/// Like `check_output`, but runs `subcommand`, which has to come before any other arguments.
+fn check_subcommand_output(golden_name: &str, subcommand: &[&str], args: &[&str]) {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .current_dir(&data)
+        .args(subcommand)
+        .args(["--quiet", "--no-cache", "--key", "name-without-rust-hash"])
+        .args(["--demangle-no-hash"])
+        .args(args)
+        .arg("libfixture.rlib")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let actual = String::from_utf8(output.stdout).unwrap();
+    let golden = data.join("golden").join(golden_name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&golden).unwrap();
+    assert!(
+        actual == expected,
+        "Output differs from `{}`. If that's intended, rerun with UPDATE_GOLDEN=1.\n\
+         Expected:\n{expected}\nActual:\n{actual}",
+        golden.display()
+    );
+}
+
+#[test]
+fn text() {
+    check_output("text.txt", &[]);
+}
+
+#[test]
+fn verbose_text() {
+    check_output("verbose.txt", &["--verbose"]);
+}
+
+#[test]
+fn verbose_text_sorted_by_copies() {
+    // Two groups have the same number of copies, so this checks that ties are broken the same way
+    // each time.
+    check_output("verbose-by-copies.txt", &["--verbose", "--sort", "copies"]);
+}
+
+#[test]
+fn verbose_text_sorted_by_name() {
+    // `--top` still keeps the groups with the most excess bytes, which are then sorted by name.
+    check_output(
+        "verbose-by-name.txt",
+        &["--verbose", "--sort", "name", "--top", "2"],
+    );
+}
+
+#[test]
+fn text_relative_to_file_size() {
+    check_output("text-file-size.txt", &["--denominator", "file-size"]);
+}
+
+#[test]
+fn text_with_alignment() {
+    check_output("text-alignment.txt", &["--alignment", "16"]);
+}
+
+#[test]
+fn verbose_text_with_tail() {
+    check_output("verbose-top.txt", &["--verbose", "--top", "1"]);
+}
+
+#[test]
+fn verbose_text_in_bytes() {
+    check_output("verbose-bytes.txt", &["--verbose", "--bytes"]);
+}
+
+#[test]
+fn text_with_size_precision() {
+    check_output("text-size-precision.txt", &["--size-precision", "2"]);
+}
+
+#[test]
+fn text_by_module() {
+    check_output("text-by-module.txt", &["--by-module=3"]);
+}
+
+#[test]
+fn verbose_text_with_ignore_file() {
+    check_output(
+        "verbose-ignore-file.txt",
+        &["--verbose", "--ignore-file", "ignore-list.txt"],
+    );
+}
+
+#[test]
+fn verbose_text_ignoring_group_by_id() {
+    check_output(
+        "verbose-ignore-id.txt",
+        &["--verbose", "--ignore-file", "ignore-ids.txt"],
+    );
+}
+
+#[test]
+fn json() {
+    check_output("report.json", &["--format", "json"]);
+}
+
+#[test]
+fn csv() {
+    check_output("report.csv", &["--format", "csv"]);
+}
+
+#[test]
+fn markdown() {
+    check_output("report.md", &["--format", "markdown"]);
+}
+
+#[test]
+fn markdown_with_baseline() {
+    // The baseline has fewer copies of one group, lacks another and has one that's since gone.
+    check_output(
+        "report-with-baseline.md",
+        &["--format", "markdown", "--baseline", "baseline.json"],
+    );
+}
+
+#[test]
+fn markdown_with_baseline_ids() {
+    // One group was renamed since the baseline, but still has the same ID, and another has a
+    // different ID, so it's matched by name.
+    check_output(
+        "report-with-baseline-ids.md",
+        &["--format", "markdown", "--baseline", "baseline-ids.json"],
+    );
+}
+
+#[test]
+fn find() {
+    // Both groups with this name are listed, even though only one of them has duplicates.
+    check_subcommand_output("find.txt", &["find", "fixture::first"], &[]);
+}
+
+#[test]
+fn output_is_the_same_every_run() {
+    // Groups are built in hash maps, which are seeded differently by each process.
+    for _ in 0..5 {
+        check_output("verbose.txt", &["--verbose"]);
+    }
+}
+
+#[test]
+fn verbose_text_where() {
+    check_output(
+        "verbose-where.txt",
+        &[
+            "--verbose",
+            "--where",
+            r#"copies > 2 || (name ~ "^fixture::sw" && !(size < 4))"#,
+        ],
+    );
+}
@@ -0,0 +1,98 @@
+use duplicate_function_checker::hint;
+use duplicate_function_checker::hint::Hint;
+use duplicate_function_checker::DuplicateGroup;
+
+fn group(names: &[&str], copies: u64) -> DuplicateGroup {
+    DuplicateGroup {
+        function_size: 64,
+        copies,
+        names: names.iter().map(|name| (name.to_string(), 1)).collect(),
+        instances: Vec::new(),
+        key_hash: 0,
+    }
+}
+
+fn suggest(names: &[&str]) -> Option<Hint> {
+    hint::suggest(&group(names, names.len().max(2) as u64))
+}
+
+#[test]
+fn drop_glue_for_different_types_can_be_folded() {
+    let hint = suggest(&[
+        "core::ptr::drop_in_place<alloc::string::String>",
+        "core::ptr::drop_in_place<alloc::vec::Vec<u8>>",
+    ]);
+    assert_eq!(hint.unwrap().id(), "linker_icf");
+}
+
+#[test]
+fn identical_monomorphisations_of_one_generic() {
+    let hint = suggest(&["foo::parse<u8>", "foo::parse<u16>", "foo::parse<u32>"]);
+    assert_eq!(
+        hint,
+        Some(Hint::NonGenericInner {
+            function: "foo::parse".to_owned(),
+            instantiations: 3,
+        })
+    );
+}
+
+#[test]
+fn one_instantiation_in_several_crates() {
+    let hint = suggest(&["foo::parse<u8>"]);
+    assert_eq!(
+        hint,
+        Some(Hint::ShareGenerics {
+            function: "foo::parse".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn duplicated_closures() {
+    let hint = hint::suggest(&group(&["foo::run::{{closure}}"], 5));
+    assert_eq!(hint, Some(Hint::ExtractClosure { copies: 5 }));
+    assert_eq!(
+        hint.unwrap().to_string(),
+        "closure duplicated 5 times — consider extracting a non-generic inner fn"
+    );
+}
+
+#[test]
+fn non_generic_function_compiled_several_times() {
+    let hint = hint::suggest(&group(&["crc32"], 3));
+    assert_eq!(
+        hint,
+        Some(Hint::DefineOnce {
+            function: "crc32".to_owned(),
+            copies: 3,
+        })
+    );
+}
+
+#[test]
+fn rust_function_with_the_same_path() {
+    let hint = suggest(&[
+        "_ZN7fixture5count17h86588b87aacbe93aE",
+        "_ZN7fixture5count17h8b105412402d8217E",
+    ]);
+    assert_eq!(
+        hint,
+        Some(Hint::SamePath {
+            function: "fixture::count".to_owned(),
+            copies: 2,
+        })
+    );
+}
+
+#[test]
+fn different_functions_can_be_folded() {
+    let hint = suggest(&["dup1", "dup2"]);
+    assert_eq!(hint.unwrap().id(), "linker_icf");
+}
+
+#[test]
+fn no_hint_without_names_or_duplicates() {
+    assert_eq!(hint::suggest(&group(&[], 2)), None);
+    assert_eq!(hint::suggest(&group(&["foo"], 1)), None);
+}
@@ -0,0 +1,37 @@
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+fn analyze_fixture() -> duplicate_function_checker::DuplicationReport {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/i686-duplicates");
+    duplicate_function_checker::analyze(&path, &Options::default()).unwrap()
+}
+
+#[test]
+fn finds_duplicates_in_32_bit_binary() {
+    let report = analyze_fixture();
+    let duplicates = report.duplicates().collect::<Vec<_>>();
+    assert_eq!(duplicates.len(), 1);
+    let mut names = duplicates[0].names.keys().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["dup1", "dup2"]);
+    assert_eq!(duplicates[0].function_size, 8);
+    assert_eq!(report.excess_bytes(), 8);
+}
+
+#[test]
+fn all_32_bit_functions_decode() {
+    // If we decoded as 64 bit, `pusha` would be invalid and only `_start` and `helper` would be
+    // keyed.
+    let report = analyze_fixture();
+    let copies: u64 = report.groups.iter().map(|g| g.copies).sum();
+    assert_eq!(copies, 5);
+}
+
+#[test]
+fn byte_identical_functions_can_be_listed_for_folding() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/i686-duplicates");
+    let data = std::fs::read(path).unwrap();
+    let groups =
+        duplicate_function_checker::icf::byte_identical_groups(&data, &Options::default()).unwrap();
+    assert_eq!(groups, [["dup1", "dup2"]]);
+}
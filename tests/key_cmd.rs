@@ -0,0 +1,66 @@
+use duplicate_function_checker::stats::Skip;
+use duplicate_function_checker::stats::Stats;
+use duplicate_function_checker::Options;
+use std::path::Path;
+use std::sync::Arc;
+
+#[test]
+fn functions_are_grouped_by_the_programs_output() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let options = Options {
+        key_cmd: Some(data.join("key-by-size.sh")),
+        ..Options::default()
+    };
+    let report =
+        duplicate_function_checker::analyze(&data.join("i686-duplicates"), &options).unwrap();
+    // `distinct` is the same size as `dup1` and `dup2`, so the program considers it the same.
+    let duplicates = report.duplicates().collect::<Vec<_>>();
+    assert_eq!(duplicates.len(), 1);
+    let mut names = duplicates[0].names.keys().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["distinct", "dup1", "dup2"]);
+    // `_start` was left out, leaving just `helper`.
+    assert_eq!(report.groups.len(), 2);
+}
+
+#[test]
+fn functions_left_out_are_counted_as_skipped() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let stats = Arc::new(Stats::default());
+    let options = Options {
+        key_cmd: Some(data.join("key-by-size.sh")),
+        stats: Some(stats.clone()),
+        ..Options::default()
+    };
+    duplicate_function_checker::analyze(&data.join("i686-duplicates"), &options).unwrap();
+    assert_eq!(stats.skipped(Skip::KeyFailure), 1);
+    assert_eq!(stats.skipped(Skip::OutsideSection), 0);
+}
+
+#[test]
+fn coverage_excludes_functions_left_out() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let options = Options {
+        key_cmd: Some(data.join("key-by-size.sh")),
+        ..Options::default()
+    };
+    let report =
+        duplicate_function_checker::analyze(&data.join("i686-duplicates"), &options).unwrap();
+    let keyed: u64 = report
+        .groups
+        .iter()
+        .map(|group| group.function_size * group.copies)
+        .sum();
+    assert_eq!(report.checked_bytes, keyed);
+    assert!(report.coverage_fraction() < 1.0);
+}
+
+#[test]
+fn failing_program_is_an_error() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/i686-duplicates");
+    let options = Options {
+        key_cmd: Some("false".into()),
+        ..Options::default()
+    };
+    assert!(duplicate_function_checker::analyze(&path, &options).is_err());
+}
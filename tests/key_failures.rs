@@ -0,0 +1,17 @@
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+#[test]
+fn undecodable_functions_are_recorded() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-undecodable");
+    let report = duplicate_function_checker::analyze(&path, &Options::default()).unwrap();
+    assert_eq!(report.key_failures.len(), 1);
+    let failure = &report.key_failures[0];
+    assert_eq!(failure.name.as_deref(), Some("undecodable"));
+    assert_eq!(failure.size, 2);
+    assert!(failure.error.contains("0x20112b"), "{}", failure.error);
+    assert_eq!(report.key_failure_bytes(), 2);
+    // The other functions are still keyed.
+    let copies: u64 = report.groups.iter().map(|g| g.copies).sum();
+    assert_eq!(copies, 2);
+}
@@ -0,0 +1,47 @@
+//! Checks that logging goes to stderr at the requested verbosity, leaving the report alone.
+
+use std::path::Path;
+use std::process::Command;
+use std::process::Output;
+
+fn run(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data"))
+        .args(["--quiet", "--no-cache"])
+        .args(args)
+        .arg("mips-duplicates.gz")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn nothing_is_logged_by_default() {
+    let output = run(&[]);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn verbose_logging_leaves_report_unchanged() {
+    let quiet = run(&[]);
+    let verbose = run(&["-vv"]);
+    assert_eq!(quiet.stdout, verbose.stdout);
+    let log = String::from_utf8(verbose.stderr).unwrap();
+    assert!(log.contains("Unpacked gzip data"), "{log}");
+    assert!(log.contains("Checked 5 functions"), "{log}");
+}
+
+#[test]
+fn json_logs_have_an_object_per_line() {
+    let output = run(&["-v", "--log-format", "json"]);
+    let log = String::from_utf8(output.stderr).unwrap();
+    assert!(log.contains("Unpacked gzip data"), "{log}");
+    for line in log.lines() {
+        assert!(line.starts_with('{') && line.ends_with('}'), "{line}");
+    }
+}
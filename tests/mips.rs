@@ -0,0 +1,66 @@
+mod common;
+
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+fn analyze_fixture(name: &str) -> duplicate_function_checker::DuplicationReport {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name);
+    duplicate_function_checker::analyze(&path, &Options::default()).unwrap()
+}
+
+/// Checks that `dup1` and `dup2` are the only duplicates. Their calls to `helper` have different
+/// offsets, so this fails if `bal` isn't normalised.
+fn check_duplicates(name: &str) {
+    let report = analyze_fixture(name);
+    let duplicates = report.duplicates().collect::<Vec<_>>();
+    assert_eq!(duplicates.len(), 1);
+    let names = duplicates[0].names.keys().collect::<Vec<_>>();
+    assert_eq!(names, ["dup1", "dup2"]);
+    assert_eq!(duplicates[0].function_size, 32);
+    assert_eq!(report.excess_bytes(), 32);
+}
+
+#[test]
+fn finds_duplicates_in_big_endian_mips_binary() {
+    check_duplicates("mips-duplicates");
+}
+
+#[test]
+fn finds_duplicates_in_little_endian_mips64_binary() {
+    check_duplicates("mips64el-duplicates");
+}
+
+#[test]
+fn range_limits_functions_and_total_size() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/mips-duplicates");
+    let analyze_range = |range| {
+        let options = Options {
+            address_range: Some(range),
+            ..Options::default()
+        };
+        duplicate_function_checker::analyze(&path, &options).unwrap()
+    };
+
+    // Just `dup1` and `dup2`.
+    let report = analyze_range(0x20178..0x201b8);
+    assert_eq!(report.text_size, 64);
+    assert_eq!(report.excess_bytes(), 32);
+
+    // `dup2` extends past the end of the window, so is left out.
+    let report = analyze_range(0x20178..0x201a0);
+    assert_eq!(report.text_size, 40);
+    assert_eq!(report.excess_bytes(), 0);
+}
+
+/// `dup1` is 8 instructions. Its blocks start at its entry, at the loop that calls `helper` and at
+/// the `jr` after the loop's branch and delay slot. The `bal` is a call, so doesn't end a block.
+#[test]
+fn counts_instructions_and_basic_blocks() {
+    for name in ["mips-duplicates", "mips64el-duplicates"] {
+        let metrics = common::first_group_metrics(name, &Options::default());
+        assert_eq!(metrics.instructions, 8);
+        assert_eq!(metrics.basic_blocks, 3);
+    }
+}
@@ -0,0 +1,42 @@
+//! Checks the completion scripts and man page that packagers generate. Their exact contents come
+//! from clap, so only the options that they mention are checked.
+
+use std::process::Command;
+
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn completions_include_options_and_subcommands() {
+    for shell in ["bash", "zsh", "fish"] {
+        let script = run(&["completions", shell]);
+        assert!(script.contains("with-deps"), "{shell}: {script}");
+        assert!(script.contains("compare-fn"), "{shell}: {script}");
+    }
+}
+
+#[test]
+fn manpage_documents_options() {
+    let page = run(&["--generate-manpage"]);
+    assert!(page.contains(".TH "), "{page}");
+    assert!(page.contains("with\\-deps"), "{page}");
+}
+
+#[test]
+fn completions_need_a_known_shell() {
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .args(["completions", "not-a-shell"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,54 @@
+use duplicate_function_checker::DuplicationReport;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+fn analyze_fixture(include_plt: bool) -> DuplicationReport {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let options = Options {
+        key_cmd: Some(data.join("key-by-size.sh")),
+        include_plt,
+        ..Options::default()
+    };
+    duplicate_function_checker::analyze(&data.join("x86_64-plt"), &options).unwrap()
+}
+
+fn duplicate_names(report: &DuplicationReport) -> Vec<Vec<&str>> {
+    let mut groups = report
+        .duplicates()
+        .map(|group| group.names.keys().map(String::as_str).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    groups.sort();
+    groups
+}
+
+#[test]
+fn plt_and_ifunc_resolvers_are_left_out_by_default() {
+    let report = analyze_fixture(false);
+    assert_eq!(duplicate_names(&report), [["dup1", "dup2"]]);
+    // `.text` only. `.plt` has two 16 byte entries and the resolvers are 8 bytes each.
+    assert_eq!(report.text_size, 31);
+    assert_eq!(report.plt_bytes, 48);
+}
+
+#[test]
+fn include_plt_checks_them_too() {
+    let report = analyze_fixture(true);
+    assert_eq!(
+        duplicate_names(&report),
+        [["checksum", "crc"], ["dup1", "dup2"]]
+    );
+    assert_eq!(report.text_size, 63);
+    assert_eq!(report.plt_bytes, 0);
+}
+
+#[test]
+fn only_resolvers_are_left_out_of_objects() {
+    // Sections in objects all start at zero, so `dup1` and `dup2` have the same address as the
+    // resolver of `checksum`.
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let report =
+        duplicate_function_checker::analyze(&data.join("x86_64-ifunc.o"), &Options::default())
+            .unwrap();
+    assert_eq!(duplicate_names(&report), [["dup1", "dup2"]]);
+    assert_eq!(report.plt_bytes, 8);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use duplicate_function_checker::KeyType;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+fn analyze_fixture(name: &str, key: KeyType) -> duplicate_function_checker::DuplicationReport {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name);
+    let options = Options {
+        key,
+        ..Options::default()
+    };
+    duplicate_function_checker::analyze(&path, &options).unwrap()
+}
+
+/// Checks that `dup1` and `dup2` are the only duplicates. Their calls to `helper` and their loads of
+/// `counter`'s address have different offsets, so this fails if `bl` or prefixed PC-relative
+/// instructions aren't normalised.
+fn check_duplicates(name: &str, key: KeyType) {
+    let report = analyze_fixture(name, key);
+    let duplicates = report.duplicates().collect::<Vec<_>>();
+    assert_eq!(duplicates.len(), 1);
+    let names = duplicates[0].names.keys().collect::<Vec<_>>();
+    assert_eq!(names, ["dup1", "dup2"]);
+    assert_eq!(duplicates[0].function_size, 52);
+    assert_eq!(report.excess_bytes(), 52);
+}
+
+#[test]
+fn finds_duplicates_in_big_endian_binary() {
+    check_duplicates("powerpc64-duplicates", KeyType::Instructions);
+}
+
+#[test]
+fn finds_duplicates_in_little_endian_binary() {
+    check_duplicates("powerpc64le-duplicates", KeyType::Instructions);
+}
+
+#[test]
+fn finds_duplicates_by_instructions_with_calls() {
+    check_duplicates("powerpc64le-duplicates", KeyType::InstructionsWithCalls);
+}
+
+/// `dup1` is 12 instructions, since its `paddi` is a single 8 byte instruction. Its blocks start at
+/// its entry, at the loop that calls `helper` and after the loop's `bne`.
+#[test]
+fn counts_instructions_and_basic_blocks() {
+    for name in ["powerpc64-duplicates", "powerpc64le-duplicates"] {
+        let metrics = common::first_group_metrics(name, &Options::default());
+        assert_eq!(metrics.instructions, 12);
+        assert_eq!(metrics.basic_blocks, 3);
+    }
+}
@@ -0,0 +1,15 @@
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+/// A range or section filter can leave no code to take percentages of, in which case they're zero
+/// rather than NaN, which isn't valid JSON.
+#[test]
+fn percentages_of_no_code_are_zero() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-data-refs");
+    let mut report = duplicate_function_checker::analyze(&path, &Options::default()).unwrap();
+    assert!(report.excess_bytes() > 0);
+    report.text_size = 0;
+    assert_eq!(report.excess_fraction(), 0.0);
+    assert_eq!(report.fraction_of_denominator(report.excess_bytes()), 0.0);
+    assert_eq!(report.coverage_fraction(), 0.0);
+}
@@ -0,0 +1,68 @@
+use duplicate_function_checker::rodata::find_duplicate_constants;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+#[test]
+fn duplicated_constants_are_found() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-rodata.o");
+    let report = find_duplicate_constants(&path, &Options::default()).unwrap();
+    let duplicates = report
+        .duplicates()
+        .map(|group| {
+            (
+                group.preview.as_str(),
+                group.copies,
+                group.names.keys().map(String::as_str).collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+    // The mergeable strings aren't reported, and `TABLE_ALIAS` is a name for `TABLE_A`, not another
+    // copy.
+    assert_eq!(
+        duplicates,
+        [
+            ("&TABLE_A", 2, vec![]),
+            ("\"duplicated string\"", 2, vec![]),
+            (
+                "01 00 00 00 02 00 00 00 03 00 00 00 05 00 00 00 ...",
+                2,
+                vec!["TABLE_A", "TABLE_ALIAS", "TABLE_B"]
+            ),
+            (
+                "&core::ptr::drop_in_place<alloc::string::String>, &fixture::Widget::draw",
+                2,
+                vec![]
+            ),
+        ]
+    );
+    assert_eq!(report.excess_bytes(), 8 + 17 + 24 + 32);
+    assert_eq!(
+        report.rodata_size,
+        17 * 2 + 6 + 24 * 2 + 14 + 8 * 2 + 32 * 3
+    );
+}
+
+#[test]
+fn vtables_are_grouped_by_what_they_point_to() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-rodata.o");
+    let report = find_duplicate_constants(&path, &Options::default()).unwrap();
+    // The third vtable has a different method, so it isn't a copy of the other two, even though
+    // its bytes are the same before relocation.
+    let vtables = report
+        .groups
+        .iter()
+        .filter(|group| group.vtable)
+        .map(|group| group.copies)
+        .collect::<Vec<_>>();
+    assert_eq!(vtables, [1, 2]);
+    assert_eq!(report.vtable_excess_bytes(), 32);
+}
+
+#[test]
+fn no_read_only_data_is_zero_percent_excess() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-rodata.o");
+    let mut report = find_duplicate_constants(&path, &Options::default()).unwrap();
+    assert!(report.excess_fraction() > 0.0);
+    report.rodata_size = 0;
+    assert_eq!(report.excess_fraction(), 0.0);
+}
@@ -0,0 +1,27 @@
+use duplicate_function_checker::symbol_checks::check_symbols;
+use duplicate_function_checker::symbol_checks::ProblemKind;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+#[test]
+fn inconsistent_symbol_sizes_are_reported() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-bad-symbols");
+    let data = std::fs::read(path).unwrap();
+    let problems = check_symbols(&data, &Options::default()).unwrap();
+    let summary = problems
+        .iter()
+        .map(|p| (p.name.as_deref().unwrap(), p.kind.id(), p.affected_bytes))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("last", "beyond-section", 3),
+            ("aliased", "size-mismatch", 4),
+            ("inner", "overlap", 6),
+        ]
+    );
+    assert!(matches!(
+        &problems[2].kind,
+        ProblemKind::Overlap { other: Some(other), other_address: 0x201130 } if other == "outer"
+    ));
+}
@@ -0,0 +1,74 @@
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the checker on `fixture` with `--emit-symbol-index` and returns the parsed lines.
+fn symbol_index(fixture: &str) -> Vec<Value> {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let index = std::env::temp_dir().join(format!("index-{}-{fixture}", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .current_dir(&data)
+        .args(["--quiet", "--no-cache", "--emit-symbol-index"])
+        .arg(&index)
+        .arg(fixture)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let text = std::fs::read_to_string(&index).unwrap();
+    std::fs::remove_file(&index).unwrap();
+    text.lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn every_function_is_listed_with_its_group() {
+    let entries = symbol_index("mips-duplicates");
+    let rows = entries
+        .iter()
+        .map(|entry| {
+            (
+                entry["name"].as_str().unwrap(),
+                entry["address"].as_str().unwrap(),
+                entry["size"].as_u64().unwrap(),
+                entry["group"].as_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        [
+            ("__start", "20150", 32, 0),
+            ("helper", "20170", 8, 1),
+            ("dup1", "20178", 32, 2),
+            ("dup2", "20198", 32, 2),
+            ("distinct", "201b8", 32, 3),
+        ]
+    );
+    // Only the duplicates share an ID.
+    let ids = entries
+        .iter()
+        .map(|entry| entry["group_id"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(ids[2], ids[3]);
+    assert_ne!(ids[1], ids[2]);
+    assert_ne!(ids[3], ids[4]);
+}
+
+#[test]
+fn names_are_demangled() {
+    let entries = symbol_index("libfixture.rlib");
+    let swap = entries
+        .iter()
+        .find(|entry| entry["name"].as_str().unwrap() == "_ZN7fixture4swap17hbf53d6f75c70b297E")
+        .unwrap();
+    assert_eq!(
+        swap["demangled"].as_str().unwrap(),
+        "fixture::swap::hbf53d6f75c70b297"
+    );
+    assert!(swap.get("member").is_some());
+}
@@ -0,0 +1,59 @@
+use duplicate_function_checker::Options;
+use duplicate_function_checker::SymbolSource;
+use std::path::Path;
+
+/// Returns the names of the functions in each duplicate group of the stale symbol table fixture,
+/// reading functions from `symbols`.
+fn duplicate_names(symbols: SymbolSource) -> Vec<Vec<String>> {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let options = Options {
+        key_cmd: Some(data.join("key-by-size.sh")),
+        symbols,
+        ..Options::default()
+    };
+    let report =
+        duplicate_function_checker::analyze(&data.join("x86_64-stale-symtab"), &options).unwrap();
+    let mut groups = report
+        .duplicates()
+        .map(|group| {
+            let mut names = group
+                .names
+                .keys()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        })
+        .collect::<Vec<_>>();
+    groups.sort();
+    groups
+}
+
+#[test]
+fn symbol_table_can_be_chosen() {
+    // `dup2` is missing from `.symtab`, so only the local functions are found to be duplicates.
+    assert_eq!(duplicate_names(SymbolSource::Auto), [["local1", "local2"]]);
+    assert_eq!(
+        duplicate_names(SymbolSource::Symtab),
+        [["local1", "local2"]]
+    );
+    assert_eq!(duplicate_names(SymbolSource::Dynsym), [["dup1", "dup2"]]);
+    // `dup1` is in both tables, but is only counted once.
+    assert_eq!(
+        duplicate_names(SymbolSource::Both),
+        [["dup1", "dup2"], ["local1", "local2"]]
+    );
+}
+
+#[test]
+fn missing_symbol_table_is_an_error() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-stripped");
+    let options = Options {
+        symbols: SymbolSource::Symtab,
+        ..Options::default()
+    };
+    let Err(error) = duplicate_function_checker::analyze(&path, &options) else {
+        panic!("A stripped binary has no symbol table to read functions from");
+    };
+    assert!(error.to_string().contains("no symbol table"), "{error:#}");
+}
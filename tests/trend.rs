@@ -0,0 +1,62 @@
+use duplicate_function_checker::trend;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `trend` on the fixture with `args`, appending to `history`, and returns what it printed.
+fn run_trend(history: &Path, args: &[&str]) -> String {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .current_dir(&data)
+        .arg("trend")
+        .args(["--quiet", "--no-cache", "--key", "name-without-rust-hash"])
+        .arg("--history")
+        .arg(history)
+        .args(args)
+        .arg("libfixture.rlib")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn runs_are_appended_to_the_history() {
+    let history = std::env::temp_dir().join(format!("trend-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&history);
+    // Leaving out `swap` makes the first run have fewer excess bytes.
+    run_trend(&history, &["--commit", "aaaaaaa", "--exclude", "swap"]);
+    let output = run_trend(&history, &["--build-id", "42"]);
+    let entries = trend::load(&history).unwrap();
+    std::fs::remove_file(&history).unwrap();
+
+    let summary = entries
+        .iter()
+        .map(|entry| (entry.label(), entry.excess_bytes))
+        .collect::<Vec<_>>();
+    assert_eq!(summary, [(Some("aaaaaaa"), 316), (Some("42"), 323)]);
+    assert!(output.contains("\n42 "), "{output}");
+    assert!(
+        output.ends_with("Over 2 runs: 316.0B -> 323.0B excess bytes (+7.0B)\n"),
+        "{output}"
+    );
+}
+
+/// Histories may have been edited by other tools, so escapes and large numbers that we wouldn't
+/// write ourselves are read exactly.
+#[test]
+fn entries_are_read_exactly() {
+    let history = std::env::temp_dir().join(format!("trend-exact-{}.jsonl", std::process::id()));
+    std::fs::write(
+        &history,
+        r#"{"version":1,"commit":"\ud83e\udd80","recorded_at":0,"binary":"a","text_size":9007199254740993,"excess_bytes":1,"dup_groups":1}"#,
+    )
+    .unwrap();
+    let entries = trend::load(&history).unwrap();
+    std::fs::remove_file(&history).unwrap();
+    assert_eq!(entries[0].commit.as_deref(), Some("🦀"));
+    assert_eq!(entries[0].text_size, (1 << 53) + 1);
+}
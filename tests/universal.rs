@@ -0,0 +1,70 @@
+use duplicate_function_checker::universal;
+use duplicate_function_checker::Options;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn data_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data")
+}
+
+fn analyze_arch(arch: Option<&str>) -> duplicate_function_checker::Result<u64> {
+    let options = Options {
+        key_cmd: Some(data_dir().join("key-by-size.sh")),
+        arch: arch.map(str::to_owned),
+        ..Options::default()
+    };
+    let report = duplicate_function_checker::analyze(&data_dir().join("universal"), &options)?;
+    Ok(report.excess_bytes())
+}
+
+#[test]
+fn slices_are_listed_by_architecture() {
+    let data = std::fs::read(data_dir().join("universal")).unwrap();
+    let slices = universal::slices(&data).unwrap().unwrap();
+    let names = slices.iter().map(|slice| slice.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["x86_64", "arm64"]);
+}
+
+#[test]
+fn arch_selects_a_slice() {
+    assert_eq!(analyze_arch(Some("x86_64")).unwrap(), 4);
+    assert_eq!(analyze_arch(Some("arm64")).unwrap(), 16);
+    // Target triples are accepted too.
+    assert_eq!(analyze_arch(Some("aarch64-apple-darwin")).unwrap(), 16);
+}
+
+#[test]
+fn arch_is_needed_to_analyse_one_slice() {
+    let error = analyze_arch(None).unwrap_err();
+    assert!(error.to_string().contains("`--arch`"), "{error}");
+    let error = analyze_arch(Some("ppc")).unwrap_err();
+    assert!(error.to_string().contains("only x86_64, arm64"), "{error}");
+}
+
+#[test]
+fn every_slice_is_reported_without_arch() {
+    let output = Command::new(env!("CARGO_BIN_EXE_duplicate-function-checker"))
+        .current_dir(data_dir())
+        .args(["--quiet", "--no-cache", "--key-cmd", "./key-by-size.sh"])
+        .arg("universal")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Architecture: x86_64\n"), "{stdout}");
+    assert!(stdout.contains("Architecture: arm64\n"), "{stdout}");
+    assert!(
+        stdout
+            .contains("  arm64: 24.0B of executable code, 16.0B excess (66.7%), 1 fns with dupes"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("Universal total: 35.0B of executable code, 20.0B excess (57.1%)"),
+        "{stdout}"
+    );
+}
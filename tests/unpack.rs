@@ -0,0 +1,52 @@
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+/// Checks that the wrapped copy of `mips-duplicates` gives the same result as the original.
+fn check_unpacked(name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name);
+    let report = duplicate_function_checker::analyze(&path, &Options::default()).unwrap();
+    let duplicates = report.duplicates().collect::<Vec<_>>();
+    assert_eq!(duplicates.len(), 1);
+    let names = duplicates[0].names.keys().collect::<Vec<_>>();
+    assert_eq!(names, ["dup1", "dup2"]);
+    assert_eq!(report.excess_bytes(), 32);
+}
+
+#[test]
+fn unpacks_gzip() {
+    check_unpacked("mips-duplicates.gz");
+}
+
+#[test]
+fn unpacks_xz() {
+    check_unpacked("mips-duplicates.xz");
+}
+
+#[test]
+fn unpacks_zstd() {
+    check_unpacked("mips-duplicates.zst");
+}
+
+#[test]
+fn unpacks_lzma_uimage() {
+    check_unpacked("mips-duplicates.uimage");
+}
+
+#[test]
+fn unpacks_bzimage() {
+    check_unpacked("mips-duplicates.bzimage");
+}
+
+#[test]
+fn reports_compressed_data_that_isnt_a_binary() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/mips-duplicates.s.gz");
+    let error = duplicate_function_checker::analyze(&path, &Options::default())
+        .err()
+        .unwrap();
+    assert!(
+        format!("{error:#}").contains("Unpacking gzip data didn't give a binary"),
+        "{error:#}"
+    );
+}
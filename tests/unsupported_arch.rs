@@ -0,0 +1,33 @@
+use duplicate_function_checker::KeyType;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+fn analyze_fixture(
+    key: KeyType,
+) -> duplicate_function_checker::Result<duplicate_function_checker::DuplicationReport> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/aarch64-duplicates");
+    duplicate_function_checker::analyze(
+        &path,
+        &Options {
+            key,
+            ..Options::default()
+        },
+    )
+}
+
+/// AArch64 code would decode as garbage x86, so keying it by instructions should fail rather than
+/// report nonsense.
+#[test]
+fn instructions_key_is_rejected() {
+    let Err(error) = analyze_fixture(KeyType::Instructions) else {
+        panic!("AArch64 code shouldn't be keyed by instructions");
+    };
+    assert!(error.to_string().contains("--key name-and-size"), "{error}");
+}
+
+#[test]
+fn name_and_size_key_works() {
+    let report = analyze_fixture(KeyType::NameAndSize).unwrap();
+    assert_eq!(report.duplicates().count(), 0);
+    assert!(report.text_size > 0);
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use duplicate_function_checker::KeyType;
+use duplicate_function_checker::Options;
+use std::path::Path;
+
+fn duplicate_names(key: KeyType) -> Vec<Vec<String>> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-branch-widths");
+    let options = Options {
+        key,
+        ..Options::default()
+    };
+    let report = duplicate_function_checker::analyze(&path, &options).unwrap();
+    report
+        .duplicates()
+        .map(|group| {
+            let mut names = group.names.keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            names
+        })
+        .collect()
+}
+
+/// Copies that only differ in whether a branch was assembled in its short or near form are the same
+/// code.
+#[test]
+fn branch_widths_are_ignored() {
+    for key in [
+        KeyType::Instructions,
+        KeyType::InstructionsWithCalls,
+        KeyType::InstructionShape,
+        KeyType::DecodedInstructions,
+    ] {
+        assert_eq!(
+            duplicate_names(key),
+            [["near_branch", "short_branch"]],
+            "{key:?}"
+        );
+    }
+}
+
+/// Without a symbol table, functions are found through `.eh_frame` and only known by address.
+#[test]
+fn stripped_functions_are_found_from_unwind_tables() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-stripped");
+    let report = duplicate_function_checker::analyze(&path, &Options::default()).unwrap();
+    assert_eq!(report.unwind_functions, 4);
+    let duplicates = report.duplicates().collect::<Vec<_>>();
+    assert_eq!(duplicates.len(), 1);
+    assert!(duplicates[0].names.is_empty());
+    let mut addresses = duplicates[0]
+        .instances
+        .iter()
+        .map(|instance| instance.address)
+        .collect::<Vec<_>>();
+    addresses.sort();
+    assert_eq!(addresses, [0x20119c, 0x2011a5]);
+}
+
+/// `short_branch` is `test`, `je`, `mov` and `ret`. Its blocks start at its entry, after the `je` and
+/// at the `ret` that the `je` branches to.
+#[test]
+fn counts_instructions_and_basic_blocks() {
+    let metrics = common::first_group_metrics("x86_64-branch-widths", &Options::default());
+    assert_eq!(metrics.instructions, 4);
+    assert_eq!(metrics.basic_blocks, 3);
+}
+
+/// The sizes of `first` and `second` are inferred, so include 6 `int3`s of padding, which aren't
+/// counted unless padding is kept.
+#[test]
+fn padding_isnt_counted() {
+    let options = Options {
+        infer_sizes: true,
+        ..Options::default()
+    };
+    let metrics = common::first_group_metrics("x86_64-padding.o", &options);
+    assert_eq!(metrics.instructions, 4);
+    assert_eq!(metrics.basic_blocks, 3);
+
+    let options = Options {
+        keep_padding: true,
+        ..options
+    };
+    let metrics = common::first_group_metrics("x86_64-padding.o", &options);
+    assert_eq!(metrics.instructions, 10);
+}
+
+/// `load_one_*` and `load_two_*` only differ in which data they read, which isn't part of the hash
+/// that IDs come from, but the two groups still get different IDs.
+#[test]
+fn groups_that_only_differ_in_data_have_different_ids() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/x86_64-data-refs");
+    for key in [
+        KeyType::Instructions,
+        KeyType::InstructionsWithCalls,
+        KeyType::InstructionShape,
+        KeyType::DecodedInstructions,
+    ] {
+        let options = Options {
+            key,
+            ..Options::default()
+        };
+        let report = duplicate_function_checker::analyze(&path, &options).unwrap();
+        let ids = report
+            .duplicates()
+            .map(|group| group.id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 2, "{key:?}");
+        assert_ne!(ids[0], ids[1], "{key:?}");
+    }
+}